@@ -0,0 +1,546 @@
+use axum::{
+    Router,
+    routing::{get, post, delete},
+    response::{Json, Response},
+    extract::{FromRequestParts, Path, Request, State},
+    http::{request::Parts, StatusCode},
+    middleware::{self, Next},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use chrono::Utc;
+use tokio::io::AsyncWriteExt;
+
+use crate::bots::{self, BotKind};
+use crate::game::{GameState, Ship, ShipType};
+use crate::lobby::GameRegistry;
+use crate::ships;
+use crate::websocket::GameServer;
+
+const AUDIT_LOG: &str = "../data/admin_audit.log";
+const SNAPSHOT_DIR: &str = "../data/snapshots";
+
+type AdminResult<T> = Result<Json<T>, (StatusCode, String)>;
+
+// Request guard: the X-Admin-Token header must match ADMIN_TOKEN. With no token
+// configured the admin API doesn't exist
+pub struct Admin;
+
+#[axum::async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Admin {
+    type Rejection = StatusCode;
+    
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let expected = match std::env::var("ADMIN_TOKEN") {
+            Ok(token) if !token.is_empty() => token,
+            _ => return Err(StatusCode::NOT_FOUND),
+        };
+        let given = parts.headers.get("x-admin-token").and_then(|v| v.to_str().ok()).unwrap_or("");
+        if tokens_match(given, &expected) {
+            Ok(Admin)
+        } else {
+            Err(StatusCode::UNAUTHORIZED)
+        }
+    }
+}
+
+// Compares digests rather than the tokens themselves, so the time taken says nothing
+// about how much of a guess was right, or how long the real token is
+fn tokens_match(given: &str, expected: &str) -> bool {
+    let given = Sha256::digest(given.as_bytes());
+    let expected = Sha256::digest(expected.as_bytes());
+    given.iter().zip(expected.iter()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+#[derive(Serialize)]
+struct AdminResponse {
+    message: String,
+}
+
+#[derive(Serialize)]
+struct PlayerSummary {
+    id: String,
+    name: String,
+    current_ap: i32,
+    max_ap: i32,
+    credits: i32,
+    level: u32,
+    ships: Vec<String>,
+    banned: bool,
+}
+
+#[derive(Deserialize)]
+struct GrantRequest {
+    player_id: String,
+    ap: Option<i32>,
+    credits: Option<i32>,
+}
+
+#[derive(Deserialize)]
+struct SpawnShipRequest {
+    owner: String, // Player ID
+    ship_type: ShipType,
+    sector_id: String,
+    name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SectorControlRequest {
+    player_id: Option<String>, // None makes the sector neutral
+}
+
+#[derive(Deserialize)]
+struct CreateBotRequest {
+    name: String,
+    strategy: BotKind,
+    interval_secs: Option<u64>, // Defaults to 5 minutes between turns
+}
+
+#[derive(Serialize)]
+struct BotResponse {
+    player_id: String,
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct KickRequest {
+    player_id: String,
+    reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RestoreRequest {
+    snapshot: String, // File name returned by the snapshot endpoint
+}
+
+pub fn router() -> Router<Arc<GameRegistry>> {
+    Router::new()
+        .route("/games/:game_id/players", get(list_players))
+        .route("/games/:game_id/ships", get(list_ships).post(spawn_ship))
+        .route("/games/:game_id/ships/:ship_id", delete(delete_ship))
+        .route("/games/:game_id/bots", post(create_bot))
+        .route("/games/:game_id/grant", post(grant))
+        .route("/games/:game_id/sectors/:sector_id/control", post(set_sector_control))
+        .route("/games/:game_id/cycle", post(force_cycle))
+        .route("/games/:game_id/kick", post(kick))
+        .route("/games/:game_id/ban", post(ban))
+        .route("/games/:game_id/unban", post(unban))
+        .route("/games/:game_id/snapshots", get(list_snapshots).post(snapshot))
+        .route("/games/:game_id/restore", post(restore))
+        .route("/ships/reload", post(reload_ships))
+        .layer(middleware::from_fn(audit_failures))
+}
+
+// Successful actions are audited by their handlers; this records everything that was
+// refused, from a bad token to a request for a player who doesn't exist
+async fn audit_failures(request: Request, next: Next) -> Response {
+    // Nothing to audit when the admin API is switched off
+    if !std::env::var("ADMIN_TOKEN").is_ok_and(|token| !token.is_empty()) {
+        return next.run(request).await;
+    }
+    let method = request.method().to_string();
+    let path = request.uri().path().to_string();
+    let response = next.run(request).await;
+    
+    let status = response.status();
+    if status.is_client_error() || status.is_server_error() {
+        let game_id = path.strip_prefix("/games/")
+            .and_then(|rest| rest.split('/').next())
+            .unwrap_or("*");
+        let action = if status == StatusCode::UNAUTHORIZED { "rejected_token" } else { "failed" };
+        audit(game_id, action, json!({ "method": method, "path": path, "status": status.as_u16() })).await;
+    }
+    response
+}
+
+async fn find_game(registry: &GameRegistry, game_id: &str) -> Result<Arc<GameServer>, (StatusCode, String)> {
+    registry.get(game_id).await.ok_or((StatusCode::NOT_FOUND, format!("No game {}", game_id)))
+}
+
+fn bad_request(message: String) -> (StatusCode, String) {
+    (StatusCode::BAD_REQUEST, message)
+}
+
+// Every admin action is appended to the audit log as one JSON line
+async fn audit(game_id: &str, action: &str, details: serde_json::Value) {
+    let entry = json!({
+        "at": Utc::now(),
+        "game_id": game_id,
+        "action": action,
+        "details": details,
+    });
+    tracing::info!(game = %game_id, action = %action, "admin: {}", entry["details"]);
+    
+    let file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(AUDIT_LOG)
+        .await;
+    let result = match file {
+        Ok(mut file) => file.write_all(format!("{}\n", entry).as_bytes()).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        eprintln!("Failed to write audit log: {}", e);
+    }
+}
+
+async fn list_players(
+    _: Admin,
+    Path(game_id): Path<String>,
+    State(registry): State<Arc<GameRegistry>>,
+) -> AdminResult<Vec<PlayerSummary>> {
+    let game_server = find_game(&registry, &game_id).await?;
+    let game_state = game_server.game_state.read().await;
+    
+    let mut players: Vec<PlayerSummary> = game_state.players.values()
+        .map(|p| PlayerSummary {
+            id: p.id.clone(),
+            name: p.name.clone(),
+            current_ap: p.current_ap,
+            max_ap: p.max_ap,
+            credits: p.credits,
+            level: p.level,
+            ships: p.owned_ships.clone(),
+            banned: game_state.banned.contains(&p.id),
+        })
+        .collect();
+    players.sort_by(|a, b| a.name.cmp(&b.name));
+    
+    audit(&game_id, "list_players", json!({})).await;
+    Ok(Json(players))
+}
+
+async fn list_ships(
+    _: Admin,
+    Path(game_id): Path<String>,
+    State(registry): State<Arc<GameRegistry>>,
+) -> AdminResult<Vec<Ship>> {
+    let game_server = find_game(&registry, &game_id).await?;
+    let game_state = game_server.game_state.read().await;
+    
+    let mut ships: Vec<Ship> = game_state.ships.values().cloned().collect();
+    ships.sort_by(|a, b| a.id.cmp(&b.id));
+    
+    audit(&game_id, "list_ships", json!({})).await;
+    Ok(Json(ships))
+}
+
+async fn grant(
+    _: Admin,
+    Path(game_id): Path<String>,
+    State(registry): State<Arc<GameRegistry>>,
+    Json(req): Json<GrantRequest>,
+) -> AdminResult<AdminResponse> {
+    let game_server = find_game(&registry, &game_id).await?;
+    let mut game_state = game_server.game_state.write().await;
+    
+    let player = game_state.players.get_mut(&req.player_id)
+        .ok_or_else(|| bad_request(format!("No player {}", req.player_id)))?;
+    let ap = req.ap.unwrap_or(0);
+    let credits = req.credits.unwrap_or(0);
+    player.current_ap = (player.current_ap + ap).clamp(0, player.max_ap);
+    player.credits += credits;
+    let message = format!("{} now has {} AP and {} credits", player.name, player.current_ap, player.credits);
+    
+    game_server.save(&game_state).await;
+    audit(&game_id, "grant", json!({ "player_id": req.player_id, "ap": ap, "credits": credits })).await;
+    Ok(Json(AdminResponse { message }))
+}
+
+async fn spawn_ship(
+    _: Admin,
+    Path(game_id): Path<String>,
+    State(registry): State<Arc<GameRegistry>>,
+    Json(req): Json<SpawnShipRequest>,
+) -> AdminResult<AdminResponse> {
+    let game_server = find_game(&registry, &game_id).await?;
+    let mut game_state = game_server.game_state.write().await;
+    
+    if !game_state.players.contains_key(&req.owner) {
+        return Err(bad_request(format!("No player {}", req.owner)));
+    }
+    let name = req.name.clone().unwrap_or_else(|| format!("{:?}", req.ship_type));
+    let ship_id = game_state.spawn_ship(&req.owner, req.ship_type.clone(), &req.sector_id, &name)
+        .ok_or_else(|| bad_request(format!("No sector {}", req.sector_id)))?;
+    
+    game_server.save(&game_state).await;
+    audit(&game_id, "spawn_ship", json!({
+        "ship_id": ship_id,
+        "owner": req.owner,
+        "ship_type": req.ship_type,
+        "sector_id": req.sector_id,
+    })).await;
+    Ok(Json(AdminResponse { message: format!("Spawned {} ({})", name, ship_id) }))
+}
+
+async fn create_bot(
+    _: Admin,
+    Path(game_id): Path<String>,
+    State(registry): State<Arc<GameRegistry>>,
+    Json(req): Json<CreateBotRequest>,
+) -> AdminResult<BotResponse> {
+    let game_server = find_game(&registry, &game_id).await?;
+    let interval_secs = req.interval_secs.unwrap_or(5 * 60).max(1);
+    
+    let mut game_state = game_server.game_state.write().await;
+    let player_id = game_state.add_bot(&req.name, req.strategy, interval_secs).map_err(bad_request)?;
+    game_server.save(&game_state).await;
+    drop(game_state);
+    
+    tokio::spawn(bots::run_bot(game_server.clone(), player_id.clone()));
+    
+    audit(&game_id, "create_bot", json!({ "player_id": player_id, "name": req.name, "strategy": req.strategy })).await;
+    Ok(Json(BotResponse {
+        player_id,
+        message: format!("{:?} bot {} joined the game", req.strategy, req.name),
+    }))
+}
+
+async fn delete_ship(
+    _: Admin,
+    Path((game_id, ship_id)): Path<(String, String)>,
+    State(registry): State<Arc<GameRegistry>>,
+) -> AdminResult<AdminResponse> {
+    let game_server = find_game(&registry, &game_id).await?;
+    let mut game_state = game_server.game_state.write().await;
+    
+    let ship = game_state.ships.get(&ship_id).cloned()
+        .ok_or_else(|| bad_request(format!("No ship {}", ship_id)))?;
+    game_state.destroy_ship(&ship_id);
+    
+    game_server.save(&game_state).await;
+    audit(&game_id, "delete_ship", json!({ "ship_id": ship_id, "owner": ship.owner })).await;
+    Ok(Json(AdminResponse { message: format!("Deleted {} ({})", ship.name, ship_id) }))
+}
+
+async fn set_sector_control(
+    _: Admin,
+    Path((game_id, sector_id)): Path<(String, String)>,
+    State(registry): State<Arc<GameRegistry>>,
+    Json(req): Json<SectorControlRequest>,
+) -> AdminResult<AdminResponse> {
+    let game_server = find_game(&registry, &game_id).await?;
+    let mut game_state = game_server.game_state.write().await;
+    
+    if let Some(player_id) = &req.player_id {
+        if !game_state.players.contains_key(player_id) {
+            return Err(bad_request(format!("No player {}", player_id)));
+        }
+    }
+    let sector = game_state.sectors.iter_mut().find(|s| s.id == sector_id)
+        .ok_or_else(|| bad_request(format!("No sector {}", sector_id)))?;
+    
+    // A garrison belongs to the old controller
+    if sector.controlled_by != req.player_id {
+        sector.garrison_ship = None;
+    }
+    sector.controlled_by = req.player_id.clone();
+    let message = match &req.player_id {
+        Some(player_id) => format!("{} is now controlled by {}", sector_id, game_state.owner_name(player_id)),
+        None => format!("{} is now neutral", sector_id),
+    };
+    
+    game_server.save(&game_state).await;
+    audit(&game_id, "set_sector_control", json!({ "sector_id": sector_id, "player_id": req.player_id })).await;
+    Ok(Json(AdminResponse { message }))
+}
+
+async fn force_cycle(
+    _: Admin,
+    Path(game_id): Path<String>,
+    State(registry): State<Arc<GameRegistry>>,
+) -> AdminResult<AdminResponse> {
+    let game_server = find_game(&registry, &game_id).await?;
+    game_server.run_cycle().await;
+    let cycle = game_server.game_state.read().await.cycle_number;
+    
+    audit(&game_id, "force_cycle", json!({ "cycle": cycle })).await;
+    Ok(Json(AdminResponse { message: format!("Cycle {} processed", cycle) }))
+}
+
+async fn kick(
+    _: Admin,
+    Path(game_id): Path<String>,
+    State(registry): State<Arc<GameRegistry>>,
+    Json(req): Json<KickRequest>,
+) -> AdminResult<AdminResponse> {
+    let game_server = find_game(&registry, &game_id).await?;
+    let name = player_name(&game_server, &req.player_id).await?;
+    
+    let reason = req.reason.clone().unwrap_or_else(|| "Disconnected by an administrator".to_string());
+    game_server.kick(&req.player_id, &reason);
+    
+    audit(&game_id, "kick", json!({ "player_id": req.player_id, "reason": reason })).await;
+    Ok(Json(AdminResponse { message: format!("Kicked {}", name) }))
+}
+
+async fn ban(
+    _: Admin,
+    Path(game_id): Path<String>,
+    State(registry): State<Arc<GameRegistry>>,
+    Json(req): Json<KickRequest>,
+) -> AdminResult<AdminResponse> {
+    let game_server = find_game(&registry, &game_id).await?;
+    let name = player_name(&game_server, &req.player_id).await?;
+    
+    let mut game_state = game_server.game_state.write().await;
+    if !game_state.banned.contains(&req.player_id) {
+        game_state.banned.push(req.player_id.clone());
+    }
+    game_server.save(&game_state).await;
+    drop(game_state);
+    
+    let reason = req.reason.clone().unwrap_or_else(|| "Banned by an administrator".to_string());
+    game_server.kick(&req.player_id, &reason);
+    
+    audit(&game_id, "ban", json!({ "player_id": req.player_id, "reason": reason })).await;
+    Ok(Json(AdminResponse { message: format!("Banned {}", name) }))
+}
+
+async fn unban(
+    _: Admin,
+    Path(game_id): Path<String>,
+    State(registry): State<Arc<GameRegistry>>,
+    Json(req): Json<KickRequest>,
+) -> AdminResult<AdminResponse> {
+    let game_server = find_game(&registry, &game_id).await?;
+    let mut game_state = game_server.game_state.write().await;
+    
+    let before = game_state.banned.len();
+    game_state.banned.retain(|id| id != &req.player_id);
+    if game_state.banned.len() == before {
+        return Err(bad_request(format!("{} isn't banned", req.player_id)));
+    }
+    game_server.save(&game_state).await;
+    
+    audit(&game_id, "unban", json!({ "player_id": req.player_id })).await;
+    Ok(Json(AdminResponse { message: format!("Unbanned {}", req.player_id) }))
+}
+
+async fn player_name(game_server: &GameServer, player_id: &str) -> Result<String, (StatusCode, String)> {
+    game_server.game_state.read().await.players.get(player_id)
+        .map(|p| p.name.clone())
+        .ok_or_else(|| bad_request(format!("No player {}", player_id)))
+}
+
+async fn snapshot(
+    _: Admin,
+    Path(game_id): Path<String>,
+    State(registry): State<Arc<GameRegistry>>,
+) -> AdminResult<AdminResponse> {
+    let game_server = find_game(&registry, &game_id).await?;
+    let game_state = game_server.game_state.read().await;
+    
+    let file_name = format!("{}-{}.json", game_id, Utc::now().format("%Y%m%d-%H%M%S"));
+    let json = serde_json::to_string_pretty(&*game_state)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    drop(game_state);
+    
+    let written = match tokio::fs::create_dir_all(SNAPSHOT_DIR).await {
+        Ok(()) => tokio::fs::write(format!("{}/{}", SNAPSHOT_DIR, file_name), json).await,
+        Err(e) => Err(e),
+    };
+    written.map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    
+    audit(&game_id, "snapshot", json!({ "snapshot": file_name })).await;
+    Ok(Json(AdminResponse { message: file_name }))
+}
+
+async fn list_snapshots(
+    _: Admin,
+    Path(game_id): Path<String>,
+    State(registry): State<Arc<GameRegistry>>,
+) -> AdminResult<Vec<String>> {
+    find_game(&registry, &game_id).await?;
+    
+    let mut snapshots = Vec::new();
+    if let Ok(mut entries) = tokio::fs::read_dir(SNAPSHOT_DIR).await {
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with(&format!("{}-", game_id)) && name.ends_with(".json") {
+                snapshots.push(name);
+            }
+        }
+    }
+    snapshots.sort();
+    
+    audit(&game_id, "list_snapshots", json!({})).await;
+    Ok(Json(snapshots))
+}
+
+async fn restore(
+    _: Admin,
+    Path(game_id): Path<String>,
+    State(registry): State<Arc<GameRegistry>>,
+    Json(req): Json<RestoreRequest>,
+) -> AdminResult<AdminResponse> {
+    let game_server = find_game(&registry, &game_id).await?;
+    
+    // Only plain file names from this game's snapshots
+    if req.snapshot.contains(['/', '\\']) || !req.snapshot.starts_with(&format!("{}-", game_id)) {
+        return Err(bad_request(format!("Invalid snapshot {}", req.snapshot)));
+    }
+    let json = tokio::fs::read_to_string(format!("{}/{}", SNAPSHOT_DIR, req.snapshot)).await
+        .map_err(|_| bad_request(format!("No snapshot {}", req.snapshot)))?;
+    let mut restored: GameState = serde_json::from_str(&json)
+        .map_err(|e| bad_request(format!("Snapshot {} is unreadable: {}", req.snapshot, e)))?;
+    restored.upgrade_map();
+    restored.apply_ship_catalog();
+    
+    let mut game_state = game_server.game_state.write().await;
+    // Bots still in the game keep their running loops; ones only the snapshot has need starting
+    let new_bots: Vec<String> = restored.bots.iter()
+        .filter(|b| !game_state.bots.iter().any(|running| running.player_id == b.player_id))
+        .map(|b| b.player_id.clone())
+        .collect();
+    *game_state = restored;
+    game_server.send_state_to_all(&game_state, "game_update", "The game was restored to an earlier state by an administrator");
+    game_server.save(&game_state).await;
+    drop(game_state);
+    
+    for bot_id in new_bots {
+        tokio::spawn(bots::run_bot(game_server.clone(), bot_id));
+    }
+    
+    audit(&game_id, "restore", json!({ "snapshot": req.snapshot })).await;
+    Ok(Json(AdminResponse { message: format!("Restored {}", req.snapshot) }))
+}
+
+// Re-reads the ship file for every game; a file that fails validation changes nothing
+async fn reload_ships(
+    _: Admin,
+    State(registry): State<Arc<GameRegistry>>,
+) -> AdminResult<AdminResponse> {
+    let count = ships::load_ship_catalog().await.map_err(bad_request)?;
+    
+    let mut updated = 0;
+    for game_server in registry.all().await {
+        let mut game_state = game_server.game_state.write().await;
+        let changed = game_state.apply_ship_catalog();
+        if changed > 0 {
+            game_server.save(&game_state).await;
+            updated += changed;
+        }
+    }
+    
+    audit("*", "reload_ships", json!({ "classes": count, "ships_updated": updated })).await;
+    Ok(Json(AdminResponse {
+        message: format!("Reloaded {} ship classes; {} ships in play updated", count, updated),
+    }))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn tokens_must_match_exactly() {
+        assert!(tokens_match("change-me", "change-me"));
+        assert!(!tokens_match("change-m", "change-me"));
+        assert!(!tokens_match("", "change-me"));
+    }
+}
@@ -0,0 +1,331 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::game::{GameState, Command, Player, Ship, ShipType};
+use crate::ships::Ability;
+use crate::websocket::GameServer;
+
+const MAX_ACTIONS_PER_TURN: usize = 5;
+pub const MAX_BOTS_PER_GAME: usize = 8;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum BotKind {
+    Aggressive,
+    Expansionist,
+}
+
+// Saved with the game so bots resume after a restart
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BotConfig {
+    pub player_id: String,
+    pub strategy: BotKind,
+    pub interval_secs: u64, // Time between turns
+}
+
+// Everything a bot may base a decision on. Built from the same filtered data a human
+// client receives: its own fleet, per-player sector counts, and contacts only where it has ships
+pub struct BotView {
+    pub player_id: String,
+    pub current_ap: i32,
+    pub ships: Vec<Ship>,
+    pub sectors: Vec<SectorView>,
+    pub at_peace_with: Vec<String>, // Player IDs with an accepted treaty
+}
+
+pub struct SectorView {
+    pub id: String,
+    pub links: Vec<String>,
+    pub controlled_by: Option<String>,
+    pub ship_count: usize,
+    pub has_garrison: bool,
+    pub contacts: Vec<Contact>,
+}
+
+pub struct Contact {
+    pub ship_id: String,
+    pub owner: String,
+    pub ship_type: ShipType,
+}
+
+pub trait BotStrategy: Send + Sync {
+    // Next command for this turn, or None to end it
+    fn next_command(&self, view: &BotView) -> Option<Command>;
+}
+
+impl BotKind {
+    pub fn strategy(&self) -> Box<dyn BotStrategy> {
+        match self {
+            BotKind::Aggressive => Box::new(Aggressive),
+            BotKind::Expansionist => Box::new(Expansionist),
+        }
+    }
+}
+
+impl BotView {
+    fn sector(&self, sector_id: &str) -> Option<&SectorView> {
+        self.sectors.iter().find(|s| s.id == sector_id)
+    }
+    
+    // Hops along sector links, i32::MAX if there's no route
+    fn distance(&self, from_id: &str, to_id: &str) -> i32 {
+        let mut distances = HashMap::from([(from_id, 0)]);
+        let mut queue = VecDeque::from([from_id]);
+        while let Some(current) = queue.pop_front() {
+            if current == to_id {
+                return distances[current];
+            }
+            let next_distance = distances[current] + 1;
+            for next in self.sector(current).map(|s| s.links.as_slice()).unwrap_or_default() {
+                if !distances.contains_key(next.as_str()) {
+                    distances.insert(next.as_str(), next_distance);
+                    queue.push_back(next.as_str());
+                }
+            }
+        }
+        i32::MAX
+    }
+    
+    fn is_hostile(&self, owner: &str) -> bool {
+        owner != self.player_id && !self.at_peace_with.iter().any(|p| p == owner)
+    }
+    
+    fn own_ships_in(&self, sector_id: &str) -> usize {
+        self.ships.iter().filter(|s| s.current_sector == sector_id).count()
+    }
+    
+    fn can_afford_move(&self, ship: &Ship) -> bool {
+        ship.ap_cost <= self.current_ap
+    }
+    
+    fn in_fuel_range(&self, ship: &Ship, sector_id: &str) -> bool {
+        Ship::fuel_for(self.distance(&ship.current_sector, sector_id).max(0) as usize) <= ship.fuel()
+    }
+}
+
+// Hunts down anything hostile: attacks where it can, otherwise sends its heaviest hitter
+// to the nearest sector with enemy presence
+pub struct Aggressive;
+
+impl BotStrategy for Aggressive {
+    fn next_command(&self, view: &BotView) -> Option<Command> {
+        // Strike the lightest hostile hull sharing a sector with us
+        let target = view.sectors.iter()
+            .filter(|s| view.own_ships_in(&s.id) > 0)
+            .flat_map(|s| s.contacts.iter())
+            .filter(|c| view.is_hostile(&c.owner))
+            .min_by_key(|c| (c.ship_type.get_stats().0, c.ship_id.clone()));
+        if let Some(target) = target {
+            return Some(Command::Attack { target_ship_id: target.ship_id.clone() });
+        }
+        
+        let warship = view.ships.iter()
+            .filter(|s| view.can_afford_move(s))
+            .max_by_key(|s| (s.damage, s.id.clone()))?;
+        
+        let destination = view.sectors.iter()
+            .filter(|s| s.id != warship.current_sector)
+            .filter(|s| view.in_fuel_range(warship, &s.id))
+            .filter(|s| {
+                s.controlled_by.as_deref().is_some_and(|c| view.is_hostile(c))
+                    || s.ship_count > view.own_ships_in(&s.id)
+            })
+            .min_by_key(|s| (view.distance(&warship.current_sector, &s.id), s.id.clone()))?;
+        
+        Some(Command::Move { ship_id: warship.id.clone(), sector_id: destination.id.clone() })
+    }
+}
+
+// Grabs territory: claims sectors with command ships, garrisons them, and spreads
+// spare ships into empty neutral space
+pub struct Expansionist;
+
+impl BotStrategy for Expansionist {
+    fn next_command(&self, view: &BotView) -> Option<Command> {
+        let controls = |sector_id: &str| {
+            view.sector(sector_id).and_then(|s| s.controlled_by.as_deref()) == Some(view.player_id.as_str())
+        };
+        
+        // Claim wherever a command ship is sitting
+        if let Some(ship) = view.ships.iter()
+            .find(|s| s.ship_type.has_ability(Ability::ClaimSector) && !controls(&s.current_sector)) {
+            return Some(Command::DeclareControl {
+                sector_id: ship.current_sector.clone(),
+                command_ship_id: ship.id.clone(),
+            });
+        }
+        
+        // Lock down claimed sectors
+        if let Some(ship) = view.ships.iter().find(|s| {
+            s.ship_type.has_ability(Ability::Garrison)
+                && controls(&s.current_sector)
+                && view.sector(&s.current_sector).is_some_and(|sec| !sec.has_garrison)
+        }) {
+            return Some(Command::SetGarrison {
+                sector_id: ship.current_sector.clone(),
+                garrison_ship_id: ship.id.clone(),
+            });
+        }
+        
+        // Command ships move on once their sector is ours; other ships spread out
+        // when they're doubled up with another of ours
+        let mover = view.ships.iter()
+            .filter(|s| view.can_afford_move(s))
+            .filter(|s| {
+                if s.ship_type.has_ability(Ability::ClaimSector) {
+                    controls(&s.current_sector)
+                } else {
+                    !s.ship_type.has_ability(Ability::Garrison) && view.own_ships_in(&s.current_sector) > 1
+                }
+            })
+            .min_by_key(|s| (s.ap_cost, s.id.clone()))?;
+        
+        let destination = view.sectors.iter()
+            .filter(|s| s.controlled_by.is_none() && s.ship_count == 0)
+            .filter(|s| view.in_fuel_range(mover, &s.id))
+            .min_by_key(|s| (view.distance(&mover.current_sector, &s.id), s.id.clone()))?;
+        
+        Some(Command::Move { ship_id: mover.id.clone(), sector_id: destination.id.clone() })
+    }
+}
+
+impl GameState {
+    pub fn bot_view(&self, player_id: &str) -> Option<BotView> {
+        let player = self.players.get(player_id)?;
+        
+        // Ships under way can't take orders until they arrive
+        let ships: Vec<Ship> = player.owned_ships.iter()
+            .filter_map(|id| self.ships.get(id))
+            .filter(|s| !s.in_transit())
+            .cloned()
+            .collect();
+        
+        let sectors = self.sectors.iter().map(|sector| {
+            let visible = self.visible_ships(sector, player_id);
+            let present = ships.iter().any(|s| s.current_sector == sector.id);
+            SectorView {
+                id: sector.id.clone(),
+                links: sector.links.clone(),
+                controlled_by: sector.controlled_by.clone(),
+                ship_count: self.shown_ship_count(sector, player_id),
+                has_garrison: sector.garrison_ship.is_some(),
+                contacts: if present {
+                    visible.iter()
+                        .filter(|s| s.owner != player_id)
+                        .map(|s| Contact {
+                            ship_id: s.id.clone(),
+                            owner: s.owner.clone(),
+                            ship_type: s.ship_type.clone(),
+                        })
+                        .collect()
+                } else {
+                    Vec::new()
+                },
+            }
+        }).collect();
+        
+        let at_peace_with = self.players.keys()
+            .filter(|id| self.treaty_between(player_id, id).is_some())
+            .cloned()
+            .collect();
+        
+        Some(BotView {
+            player_id: player_id.to_string(),
+            current_ap: player.current_ap,
+            ships,
+            sectors,
+            at_peace_with,
+        })
+    }
+    
+    // Registers a bot commander with a starter fleet in the emptiest corner of the map
+    pub fn add_bot(&mut self, name: &str, strategy: BotKind, interval_secs: u64) -> Result<String, String> {
+        if self.bots.len() >= MAX_BOTS_PER_GAME {
+            return Err(format!("This game already has the maximum of {} bots", MAX_BOTS_PER_GAME));
+        }
+        let player_id = format!("bot-{}", Uuid::new_v4());
+        
+        self.players.insert(player_id.clone(), Player::new(&player_id, name));
+        
+        if let Some(home) = self.open_home_sector() {
+            self.spawn_ship(&player_id, ShipType::Frigate, &home, &format!("{} Flagship", name));
+            self.spawn_ship(&player_id, ShipType::ScoutDrone, &home, &format!("{} Eye", name));
+        }
+        
+        self.bots.push(BotConfig {
+            player_id: player_id.clone(),
+            strategy,
+            interval_secs,
+        });
+        
+        Ok(player_id)
+    }
+}
+
+// One bot's loop; ends when the bot is removed from the game
+pub async fn run_bot(game_server: Arc<GameServer>, player_id: String) {
+    loop {
+        let interval = match game_server.game_state.read().await.bots.iter().find(|b| b.player_id == player_id) {
+            Some(bot) => bot.interval_secs,
+            None => return,
+        };
+        tokio::time::sleep(tokio::time::Duration::from_secs(interval)).await;
+        
+        let mut game_state = game_server.game_state.write().await;
+        take_turn(&game_server, &mut game_state, &player_id);
+    }
+}
+
+// Asks the strategy for commands one at a time against a fresh view, and stops at the
+// first failure so a bot can't spin on an order the rules reject
+pub fn take_turn(game_server: &GameServer, game_state: &mut GameState, player_id: &str) {
+    let strategy = match game_state.bots.iter().find(|b| b.player_id == player_id) {
+        Some(bot) => bot.strategy,
+        None => return,
+    };
+    let brain = strategy.strategy();
+    
+    for _ in 0..MAX_ACTIONS_PER_TURN {
+        let view = match game_state.bot_view(player_id) {
+            Some(v) => v,
+            None => return,
+        };
+        let command = match brain.next_command(&view) {
+            Some(c) => c,
+            None => break,
+        };
+        
+        let decision = format!("{:?}", command);
+        let result = game_server.run_command(game_state, player_id, command);
+        tracing::info!(
+            bot = %player_id,
+            strategy = ?strategy,
+            success = result.success,
+            "{} -> {}",
+            decision,
+            result.message.lines().next().unwrap_or("")
+        );
+        
+        if !result.success {
+            break;
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn bots_per_game_are_capped() {
+        let mut state = GameState::new();
+        for i in 0..MAX_BOTS_PER_GAME {
+            assert!(state.add_bot(&format!("Bot {}", i), BotKind::Aggressive, 60).is_ok());
+        }
+        assert!(state.add_bot("One Too Many", BotKind::Expansionist, 60).is_err());
+        assert_eq!(state.bots.len(), MAX_BOTS_PER_GAME);
+    }
+}
@@ -0,0 +1,141 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use chrono::{DateTime, Utc, Duration};
+
+const HISTORY_LIMIT: usize = 50; // Messages kept per channel
+const RATE_LIMIT_MESSAGES: usize = 5; // Max messages per player...
+const RATE_LIMIT_WINDOW_SECS: i64 = 10; // ...within this many seconds
+pub const MAX_MESSAGE_LENGTH: usize = 280;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ChatChannel {
+    Global,
+    Alliance,
+    Direct,
+}
+
+// Senders and recipients go over the wire by name only
+#[derive(Serialize, Clone)]
+pub struct ChatMessage {
+    pub channel: ChatChannel,
+    #[serde(skip)]
+    pub from: String, // Player ID
+    pub from_name: String,
+    #[serde(skip)]
+    pub recipients: Vec<String>, // Player IDs besides the sender, empty for global
+    pub recipient_names: Vec<String>,
+    pub text: String,
+    pub sent_at: DateTime<Utc>,
+}
+
+impl ChatMessage {
+    pub fn is_visible_to(&self, player_id: &str) -> bool {
+        self.channel == ChatChannel::Global
+            || self.from == player_id
+            || self.recipients.iter().any(|r| r == player_id)
+    }
+    
+    // History bucket: one global channel, one alliance channel per sender, one per DM pair
+    fn channel_key(&self) -> String {
+        match self.channel {
+            ChatChannel::Global => "global".to_string(),
+            ChatChannel::Alliance => format!("alliance:{}", self.from),
+            ChatChannel::Direct => {
+                let mut pair = [self.from.as_str(), self.recipients.first().map_or("", |r| r.as_str())];
+                pair.sort();
+                format!("dm:{}|{}", pair[0], pair[1])
+            }
+        }
+    }
+}
+
+// Server-side chat bookkeeping; lives next to the game state but isn't saved with it
+pub struct ChatState {
+    history: HashMap<String, VecDeque<ChatMessage>>,
+    recent_sends: HashMap<String, VecDeque<DateTime<Utc>>>, // Player ID -> send times
+    mutes: HashMap<String, HashSet<String>>, // Player ID -> muted player IDs
+}
+
+impl ChatState {
+    pub fn new() -> Self {
+        Self {
+            history: HashMap::new(),
+            recent_sends: HashMap::new(),
+            mutes: HashMap::new(),
+        }
+    }
+    
+    // Records the attempt if it's allowed
+    pub fn check_rate_limit(&mut self, player_id: &str, now: DateTime<Utc>) -> Result<(), String> {
+        let sends = self.recent_sends.entry(player_id.to_string()).or_default();
+        while sends.front().is_some_and(|t| now - *t > Duration::seconds(RATE_LIMIT_WINDOW_SECS)) {
+            sends.pop_front();
+        }
+        
+        if sends.len() >= RATE_LIMIT_MESSAGES {
+            return Err(format!(
+                "Slow down: at most {} messages every {} seconds",
+                RATE_LIMIT_MESSAGES, RATE_LIMIT_WINDOW_SECS
+            ));
+        }
+        
+        sends.push_back(now);
+        Ok(())
+    }
+    
+    pub fn record(&mut self, message: ChatMessage) {
+        let channel = self.history.entry(message.channel_key()).or_default();
+        channel.push_back(message);
+        while channel.len() > HISTORY_LIMIT {
+            channel.pop_front();
+        }
+    }
+    
+    // Everything a player is allowed to read, minus muted senders, oldest first
+    pub fn history_for(&self, player_id: &str) -> Vec<&ChatMessage> {
+        let mut messages: Vec<&ChatMessage> = self.history.values()
+            .flatten()
+            .filter(|m| m.is_visible_to(player_id) && !self.is_muted(player_id, &m.from))
+            .collect();
+        messages.sort_by_key(|m| m.sent_at);
+        messages
+    }
+    
+    pub fn is_muted(&self, listener_id: &str, sender_id: &str) -> bool {
+        self.mutes.get(listener_id).is_some_and(|m| m.contains(sender_id))
+    }
+    
+    // Returns false if the target was already muted
+    pub fn mute(&mut self, player_id: &str, target_id: &str) -> bool {
+        self.mutes.entry(player_id.to_string()).or_default().insert(target_id.to_string())
+    }
+    
+    // Returns false if the target wasn't muted
+    pub fn unmute(&mut self, player_id: &str, target_id: &str) -> bool {
+        self.mutes.get_mut(player_id).is_some_and(|m| m.remove(target_id))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn serialized_messages_carry_names_not_player_ids() {
+        let message = ChatMessage {
+            channel: ChatChannel::Direct,
+            from: "secret-sender-id".to_string(),
+            from_name: "Alpha".to_string(),
+            recipients: vec!["secret-recipient-id".to_string()],
+            recipient_names: vec!["Beta".to_string()],
+            text: "hello".to_string(),
+            sent_at: Utc::now(),
+        };
+        let json = serde_json::to_string(&message).unwrap();
+        assert!(!json.contains("secret"));
+        assert!(json.contains("Alpha") && json.contains("Beta"));
+        assert!(message.is_visible_to("secret-recipient-id"));
+    }
+}
@@ -0,0 +1,302 @@
+use crate::game::{GameState, CommandResult, GameEvent, Ship};
+use crate::supply::Supply;
+
+pub const MAX_MORALE: i32 = 100;
+const OUTNUMBERED_MORALE_LOSS: i32 = 15; // Per cycle
+const CUT_OFF_MORALE_LOSS: i32 = 10; // Per cycle
+const MORALE_RECOVERY: i32 = 10; // Per cycle with nothing wrong
+const RETREAT_MORALE: i32 = 25; // At or below this, a threatened crew pulls back on its own
+const SHAKEN_MORALE: i32 = 50; // Below this, a crew fights less well
+const SHAKEN_DAMAGE_PENALTY: i32 = 20; // %
+pub const CREW_INTEL_ACCURACY: i32 = 75; // Scanners at least this accurate report crew quality
+const MAX_NAME_LENGTH: usize = 32;
+
+#[derive(PartialEq, Debug)]
+pub enum Rank {
+    Green,
+    Regular,
+    Veteran,
+    Elite,
+}
+
+impl Rank {
+    // Battles survived to reach each rank
+    fn from_battles(battles: u32) -> Self {
+        match battles {
+            0..=1 => Rank::Green,
+            2..=4 => Rank::Regular,
+            5..=9 => Rank::Veteran,
+            _ => Rank::Elite,
+        }
+    }
+    
+    // % bonus to damage dealt and % of incoming damage evaded
+    fn bonus(&self) -> i32 {
+        match self {
+            Rank::Green => 0,
+            Rank::Regular => 5,
+            Rank::Veteran => 10,
+            Rank::Elite => 15,
+        }
+    }
+}
+
+pub fn full_morale() -> i32 {
+    MAX_MORALE
+}
+
+impl Ship {
+    pub fn rank(&self) -> Rank {
+        Rank::from_battles(self.battles)
+    }
+    
+    // Damage per volley once crew quality and morale are counted
+    pub fn effective_damage(&self) -> i32 {
+        let mut percent = 100 + self.rank().bonus();
+        if self.morale < SHAKEN_MORALE {
+            percent -= SHAKEN_DAMAGE_PENALTY;
+        }
+        self.damage * percent / 100
+    }
+    
+    // Damage actually taken from a hit; experienced crews dodge some of it
+    pub fn evade(&self, damage: i32) -> i32 {
+        damage * (100 - self.rank().bonus()) / 100
+    }
+    
+    pub fn crew_summary(&self) -> String {
+        let plural = if self.battles == 1 { "" } else { "s" };
+        format!("{:?} crew ({} battle{}), morale {}", self.rank(), self.battles, plural, self.morale)
+    }
+}
+
+impl GameState {
+    // Called for every ship still afloat after an exchange of fire
+    pub fn survived_battle(&mut self, ship_id: &str) {
+        let ship = match self.ships.get_mut(ship_id) {
+            Some(s) => s,
+            None => return,
+        };
+        let before = ship.rank();
+        ship.battles += 1;
+        let after = ship.rank();
+        if after != before && self.players.contains_key(&ship.owner) {
+            let message = if after == Rank::Elite {
+                format!("{}'s crew is now Elite! Give the ship a name with `rename {} <name>`", ship.name, ship.id)
+            } else {
+                format!("{}'s crew is now {:?}", ship.name, after)
+            };
+            self.events.push(GameEvent {
+                player_id: ship.owner.clone(),
+                message,
+            });
+        }
+    }
+    
+    // Hostile ships in the sector outnumber ours and our allies'
+    fn outnumbered(&self, ship: &Ship) -> bool {
+        let sector = match self.sectors.iter().find(|s| s.id == ship.current_sector) {
+            Some(s) => s,
+            None => return false,
+        };
+        let (mut friends, mut hostiles) = (0, 0);
+        for other in sector.ships_present.iter().filter_map(|id| self.ships.get(id)) {
+            if other.owner == ship.owner || self.is_allied(&ship.owner, &other.owner) {
+                friends += 1;
+            } else if self.treaty_between(&ship.owner, &other.owner).is_none() {
+                hostiles += 1;
+            }
+        }
+        hostiles > friends
+    }
+    
+    // Morale falls while outnumbered or cut off and recovers otherwise. A crew whose morale
+    // breaks under threat retreats one hop toward supply on its own. Called once per cycle
+    pub fn process_morale(&mut self) {
+        let mut ship_ids: Vec<String> = self.ships.values()
+            .filter(|s| !s.in_transit() && self.players.contains_key(&s.owner))
+            .map(|s| s.id.clone())
+            .collect();
+        ship_ids.sort();
+        
+        for ship_id in ship_ids {
+            let ship = match self.ships.get(&ship_id) {
+                Some(s) => s.clone(),
+                None => continue,
+            };
+            let outnumbered = self.outnumbered(&ship);
+            let cut_off = self.supply_state(&ship) == Supply::CutOff;
+            
+            let mut change = 0;
+            if outnumbered {
+                change -= OUTNUMBERED_MORALE_LOSS;
+            }
+            if cut_off {
+                change -= CUT_OFF_MORALE_LOSS;
+            }
+            if change == 0 {
+                change = MORALE_RECOVERY;
+            }
+            let morale = (ship.morale + change).clamp(0, MAX_MORALE);
+            self.ships.get_mut(&ship_id).unwrap().morale = morale;
+            
+            if morale <= RETREAT_MORALE && (outnumbered || cut_off) {
+                self.retreat(&ship_id);
+            }
+        }
+    }
+    
+    // The neighbouring sector a ship would fall back to, preferring supplied sectors
+    // without hostiles in them
+    pub fn fallback_sector(&self, ship: &Ship) -> Option<String> {
+        let supplied = self.supplied_sectors(&ship.owner);
+        let mut options: Vec<String> = self.adjacent_sectors(&ship.current_sector);
+        options.sort_by_key(|id| {
+            let hostiles = self.sectors.iter()
+                .find(|s| &s.id == id)
+                .map_or(0, |s| s.ships_present.iter()
+                    .filter_map(|other| self.ships.get(other))
+                    .filter(|other| other.owner != ship.owner && self.treaty_between(&ship.owner, &other.owner).is_none())
+                    .count());
+            (!supplied.contains(id), hostiles, id.clone())
+        });
+        options.into_iter().next()
+    }
+    
+    // Pulls a ship back one hop
+    fn retreat(&mut self, ship_id: &str) {
+        let ship = self.ships[ship_id].clone();
+        let destination = match self.fallback_sector(&ship) {
+            Some(d) => d,
+            None => return,
+        };
+        
+        if !self.withdraw(ship_id, &destination) {
+            return;
+        }
+        self.events.push(GameEvent {
+            player_id: ship.owner.clone(),
+            message: format!("{}'s crew lost their nerve and is falling back to {}", ship.name, self.sector_name(&destination)),
+        });
+    }
+    
+    // Only elite crews earn the right to name their ship
+    pub fn rename_ship(&mut self, player_id: &str, ship_id: &str, name: &str) -> CommandResult {
+        let ship = match self.ships.get(ship_id) {
+            Some(s) if s.owner == player_id => s,
+            _ => return CommandResult {
+                success: false,
+                message: "Ship not found".to_string(),
+                ap_spent: 0,
+                game_state: self.clone(),
+            }
+        };
+        
+        if ship.rank() != Rank::Elite {
+            return CommandResult {
+                success: false,
+                message: format!("Only elite crews can name their ship; {} has a {:?} crew", ship.name, ship.rank()),
+                ap_spent: 0,
+                game_state: self.clone(),
+            };
+        }
+        
+        let name = name.trim();
+        if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+            return CommandResult {
+                success: false,
+                message: format!("Names must be 1-{} characters", MAX_NAME_LENGTH),
+                ap_spent: 0,
+                game_state: self.clone(),
+            };
+        }
+        
+        let old_name = ship.name.clone();
+        self.ships.get_mut(ship_id).unwrap().name = name.to_string();
+        
+        CommandResult {
+            success: true,
+            message: format!("{} is now known as {}", old_name, name),
+            ap_spent: 0,
+            game_state: self.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Player, ShipType};
+    
+    fn two_player_game() -> GameState {
+        let mut state = GameState::new();
+        state.players.insert("p1".to_string(), Player::new("p1", "Alpha"));
+        state.players.insert("p2".to_string(), Player::new("p2", "Beta"));
+        state
+    }
+    
+    #[test]
+    fn ranks_add_damage_and_evasion() {
+        let mut ship = Ship::new("ship-1", "Pioneer", ShipType::Frigate, "p1", "earth-1");
+        let ranks: Vec<Rank> = [0, 2, 5, 10].iter()
+            .map(|&battles| {
+                ship.battles = battles;
+                ship.rank()
+            })
+            .collect();
+        assert_eq!(ranks, vec![Rank::Green, Rank::Regular, Rank::Veteran, Rank::Elite]);
+        
+        // Elite: +15% damage dealt, 15% of incoming damage evaded
+        assert_eq!(ship.effective_damage(), 15 * 115 / 100);
+        assert_eq!(ship.evade(100), 85);
+        
+        ship.morale = SHAKEN_MORALE - 1;
+        assert_eq!(ship.effective_damage(), 15 * (115 - SHAKEN_DAMAGE_PENALTY) / 100);
+    }
+    
+    #[test]
+    fn broken_crews_fall_back_under_threat() {
+        let mut state = two_player_game();
+        let steady = state.spawn_ship("p1", ShipType::Frigate, "earth-6", "Steady").unwrap();
+        let shaken = state.spawn_ship("p1", ShipType::Frigate, "earth-6", "Shaken").unwrap();
+        for _ in 0..3 {
+            state.spawn_ship("p2", ShipType::Destroyer, "earth-6", "Hunter");
+        }
+        state.ships.get_mut(&shaken).unwrap().morale = RETREAT_MORALE + 10;
+        
+        // Outnumbered and cut off: -25 each
+        state.process_morale();
+        assert_eq!(state.ships[&steady].morale, MAX_MORALE - 25);
+        assert!(!state.ships[&steady].in_transit());
+        assert_eq!(state.ships[&shaken].morale, RETREAT_MORALE - 15);
+        assert!(state.ships[&shaken].in_transit());
+        assert!(state.take_events().iter().any(|e| e.player_id == "p1" && e.message.contains("Shaken's crew lost their nerve")));
+    }
+    
+    #[test]
+    fn morale_recovers_when_nothing_is_wrong() {
+        let mut state = two_player_game();
+        // An unclaimed shipyard with a ship of ours in it is supplied
+        let ship_id = state.spawn_ship("p1", ShipType::Frigate, "earth-1", "Pioneer").unwrap();
+        state.ships.get_mut(&ship_id).unwrap().morale = 20;
+        
+        state.process_morale();
+        assert_eq!(state.ships[&ship_id].morale, 20 + MORALE_RECOVERY);
+        assert!(!state.ships[&ship_id].in_transit());
+    }
+    
+    #[test]
+    fn only_elite_crews_rename_their_ship() {
+        let mut state = two_player_game();
+        let ship_id = state.spawn_ship("p1", ShipType::Frigate, "earth-1", "Pioneer").unwrap();
+        state.ships.get_mut(&ship_id).unwrap().battles = 9;
+        assert!(!state.rename_ship("p1", &ship_id, "Indomitable").success);
+        
+        state.survived_battle(&ship_id);
+        assert!(state.take_events().iter().any(|e| e.message.contains("now Elite")));
+        assert!(!state.rename_ship("p2", &ship_id, "Stolen").success);
+        assert!(!state.rename_ship("p1", &ship_id, &"x".repeat(MAX_NAME_LENGTH + 1)).success);
+        assert!(state.rename_ship("p1", &ship_id, "Indomitable").success);
+        assert_eq!(state.ships[&ship_id].name, "Indomitable");
+    }
+}
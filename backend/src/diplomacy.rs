@@ -0,0 +1,255 @@
+use serde::{Deserialize, Serialize};
+
+use crate::game::{GameState, CommandResult, GameEvent};
+
+const PROPOSAL_CYCLES: u32 = 2; // Unanswered proposals lapse after this many cycles
+const TRUCE_CYCLES: u32 = 3; // Accepted truces run out after this many cycles
+const BREAK_TRUCE_PENALTY: i32 = 10; // Reputation lost for breaking a truce
+const BREAK_ALLIANCE_PENALTY: i32 = 25; // Reputation lost for betraying an ally
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum TreatyKind {
+    Truce,    // No combat
+    Alliance, // No combat, shared vision, optional shared victory
+}
+
+// One relation between two players; at most one exists per pair
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Treaty {
+    pub kind: TreatyKind,
+    pub proposer: String, // Player ID
+    pub partner: String, // Player ID
+    pub accepted: bool,
+    pub shared_victory: bool,
+    pub expires_cycle: Option<u32>, // None = lasts until broken
+}
+
+impl Treaty {
+    pub fn involves(&self, a: &str, b: &str) -> bool {
+        (self.proposer == a && self.partner == b) || (self.proposer == b && self.partner == a)
+    }
+    
+    pub fn other_party(&self, player_id: &str) -> &str {
+        if self.proposer == player_id { &self.partner } else { &self.proposer }
+    }
+}
+
+impl GameState {
+    // Accepted treaty between two players, if any; any treaty rules out combat
+    pub fn treaty_between(&self, a: &str, b: &str) -> Option<&Treaty> {
+        self.relations.iter().find(|t| t.accepted && t.involves(a, b))
+    }
+    
+    pub fn is_allied(&self, a: &str, b: &str) -> bool {
+        self.treaty_between(a, b).is_some_and(|t| t.kind == TreatyKind::Alliance)
+    }
+    
+    pub fn allies_of(&self, player_id: &str) -> Vec<String> {
+        self.relations.iter()
+            .filter(|t| t.accepted && t.kind == TreatyKind::Alliance)
+            .filter(|t| t.proposer == player_id || t.partner == player_id)
+            .map(|t| t.other_party(player_id).to_string())
+            .collect()
+    }
+    
+    pub fn propose_treaty(&mut self, player_id: &str, target: &str, kind: TreatyKind, shared_victory: bool) -> CommandResult {
+        let partner_id = match self.find_player_id(target) {
+            Some(id) if id == player_id => return CommandResult {
+                success: false,
+                message: "You can't make a treaty with yourself".to_string(),
+                ap_spent: 0,
+                game_state: self.clone(),
+            },
+            Some(id) => id,
+            None => return CommandResult {
+                success: false,
+                message: format!("No commander named {}", target),
+                ap_spent: 0,
+                game_state: self.clone(),
+            }
+        };
+        let partner_name = self.players[&partner_id].name.clone();
+        
+        if let Some(existing) = self.relations.iter().find(|t| t.involves(player_id, &partner_id)) {
+            let message = if existing.accepted {
+                format!("You already have a {:?} with {}; break it first", existing.kind, partner_name)
+            } else if existing.proposer == partner_id {
+                format!("{} has already proposed a {:?}; use 'accept {}'", partner_name, existing.kind, partner_name)
+            } else {
+                format!("You already have a pending proposal to {}", partner_name)
+            };
+            return CommandResult {
+                success: false,
+                message,
+                ap_spent: 0,
+                game_state: self.clone(),
+            };
+        }
+        
+        self.relations.push(Treaty {
+            kind,
+            proposer: player_id.to_string(),
+            partner: partner_id.clone(),
+            accepted: false,
+            shared_victory: kind == TreatyKind::Alliance && shared_victory,
+            expires_cycle: Some(self.cycle_number + PROPOSAL_CYCLES),
+        });
+        
+        let proposer_name = self.players[player_id].name.clone();
+        let terms = if kind == TreatyKind::Alliance && shared_victory { " with shared victory" } else { "" };
+        self.treaty_event(player_id, &partner_id, format!(
+            "{} proposed a {:?}{} to {} (expires cycle {})",
+            proposer_name, kind, terms, partner_name, self.cycle_number + PROPOSAL_CYCLES
+        ));
+        
+        CommandResult {
+            success: true,
+            message: format!("{:?} proposed to {}", kind, partner_name),
+            ap_spent: 0,
+            game_state: self.clone(),
+        }
+    }
+    
+    pub fn accept_treaty(&mut self, player_id: &str, target: &str) -> CommandResult {
+        let proposer_id = self.find_player_id(target).unwrap_or_default();
+        let cycle_number = self.cycle_number;
+        
+        let treaty = match self.relations.iter_mut()
+            .find(|t| !t.accepted && t.proposer == proposer_id && t.partner == player_id) {
+            Some(t) => t,
+            None => return CommandResult {
+                success: false,
+                message: format!("No pending proposal from {}", target),
+                ap_spent: 0,
+                game_state: self.clone(),
+            }
+        };
+        
+        treaty.accepted = true;
+        treaty.expires_cycle = match treaty.kind {
+            TreatyKind::Truce => Some(cycle_number + TRUCE_CYCLES),
+            TreatyKind::Alliance => None,
+        };
+        let kind = treaty.kind;
+        
+        let (a_name, b_name) = (self.players[&proposer_id].name.clone(), self.players[player_id].name.clone());
+        self.treaty_event(&proposer_id, player_id, format!("{} and {} are now in a {:?}", a_name, b_name, kind));
+        
+        CommandResult {
+            success: true,
+            message: format!("{:?} with {} accepted", kind, a_name),
+            ap_spent: 0,
+            game_state: self.clone(),
+        }
+    }
+    
+    // Withdraws or declines a pending proposal for free; breaking an accepted treaty costs reputation
+    pub fn break_treaty(&mut self, player_id: &str, target: &str) -> CommandResult {
+        let other_id = self.find_player_id(target).unwrap_or_default();
+        
+        let index = match self.relations.iter().position(|t| t.involves(player_id, &other_id)) {
+            Some(i) => i,
+            None => return CommandResult {
+                success: false,
+                message: format!("You have no treaty with {}", target),
+                ap_spent: 0,
+                game_state: self.clone(),
+            }
+        };
+        let treaty = self.relations.remove(index);
+        
+        let breaker_name = self.players[player_id].name.clone();
+        let other_name = self.players[&other_id].name.clone();
+        
+        let (message, event) = if treaty.accepted {
+            let penalty = match treaty.kind {
+                TreatyKind::Truce => BREAK_TRUCE_PENALTY,
+                TreatyKind::Alliance => BREAK_ALLIANCE_PENALTY,
+            };
+            self.players.get_mut(player_id).unwrap().reputation -= penalty;
+            (
+                format!("{:?} with {} broken (-{} reputation)", treaty.kind, other_name, penalty),
+                format!("{} broke their {:?} with {}", breaker_name, treaty.kind, other_name),
+            )
+        } else {
+            (
+                format!("{:?} proposal with {} cancelled", treaty.kind, other_name),
+                format!("{} cancelled the {:?} proposal with {}", breaker_name, treaty.kind, other_name),
+            )
+        };
+        self.treaty_event(player_id, &other_id, event);
+        
+        CommandResult {
+            success: true,
+            message,
+            ap_spent: 0,
+            game_state: self.clone(),
+        }
+    }
+    
+    pub fn relations_report(&self, player_id: &str) -> CommandResult {
+        let player = &self.players[player_id];
+        let mut report = format!("=== Relations ===\nReputation: {}\n", player.reputation);
+        
+        let mut any = false;
+        for treaty in self.relations.iter().filter(|t| t.proposer == player_id || t.partner == player_id) {
+            any = true;
+            let other_name = self.players.get(treaty.other_party(player_id))
+                .map(|p| p.name.as_str())
+                .unwrap_or("Unknown");
+            let expiry = treaty.expires_cycle.map(|c| format!(" (expires cycle {})", c)).unwrap_or_default();
+            let shared = if treaty.shared_victory { " [shared victory]" } else { "" };
+            
+            let line = if treaty.accepted {
+                format!("{}: {:?}{}{}\n", other_name, treaty.kind, shared, expiry)
+            } else if treaty.proposer == player_id {
+                format!("{}: {:?} proposed, awaiting reply{}\n", other_name, treaty.kind, expiry)
+            } else {
+                format!("{}: {:?} offered to you{} - 'accept {}'\n", other_name, treaty.kind, expiry, other_name)
+            };
+            report.push_str(&line);
+        }
+        
+        if !any {
+            report.push_str("No treaties\n");
+        }
+        report.push_str("Everyone else: Hostile");
+        
+        CommandResult {
+            success: true,
+            message: report,
+            ap_spent: 0,
+            game_state: self.clone(),
+        }
+    }
+    
+    // Called once per cycle: lapsed proposals and truces are dropped
+    pub fn expire_treaties(&mut self) {
+        let cycle_number = self.cycle_number;
+        let (expired, kept): (Vec<Treaty>, Vec<Treaty>) = std::mem::take(&mut self.relations)
+            .into_iter()
+            .partition(|t| t.expires_cycle.is_some_and(|c| c <= cycle_number));
+        self.relations = kept;
+        
+        for treaty in expired {
+            let a_name = self.players.get(&treaty.proposer).map(|p| p.name.clone()).unwrap_or_default();
+            let b_name = self.players.get(&treaty.partner).map(|p| p.name.clone()).unwrap_or_default();
+            let message = if treaty.accepted {
+                format!("The {:?} between {} and {} has expired", treaty.kind, a_name, b_name)
+            } else {
+                format!("{}'s {:?} proposal to {} went unanswered and lapsed", a_name, treaty.kind, b_name)
+            };
+            self.treaty_event(&treaty.proposer, &treaty.partner, message);
+        }
+    }
+    
+    // Treaty news always goes to both parties
+    fn treaty_event(&mut self, a: &str, b: &str, message: String) {
+        for player_id in [a, b] {
+            self.events.push(GameEvent {
+                player_id: player_id.to_string(),
+                message: message.clone(),
+            });
+        }
+    }
+}
@@ -0,0 +1,351 @@
+use serde::{Deserialize, Serialize};
+
+use crate::game::{GameState, CommandResult, GameEvent, Ship};
+use crate::transit::Transit;
+
+const EVASIVE_DAMAGE_REDUCTION: i32 = 25; // %
+
+// How a ship behaves when hostiles share its sector and its owner isn't around
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Stance {
+    Aggressive, // Opens fire on hostiles in its sector without waiting for orders
+    #[default]
+    Defensive, // Returns fire when attacked
+    Evasive, // Never fires back, dodges some of the damage, and slips away from hostiles
+    Passive, // Never fires at all
+}
+
+// Standing orders checked alongside the stance
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct RulesOfEngagement {
+    pub retreat_below: Option<i32>, // % HP; falls back to the nearest friendly sector below it
+    pub avoid_superior: bool, // Won't open fire on a stronger force
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum RoeRule {
+    RetreatBelow(Option<i32>),
+    AvoidSuperior(bool),
+}
+
+impl Stance {
+    pub fn parse(name: &str) -> Option<Stance> {
+        match name.to_lowercase().as_str() {
+            "aggressive" => Some(Stance::Aggressive),
+            "defensive" => Some(Stance::Defensive),
+            "evasive" => Some(Stance::Evasive),
+            "passive" => Some(Stance::Passive),
+            _ => None,
+        }
+    }
+}
+
+impl Ship {
+    pub fn returns_fire(&self) -> bool {
+        matches!(self.stance, Stance::Aggressive | Stance::Defensive)
+    }
+    
+    // Damage taken after evasive manoeuvres
+    pub fn manoeuvre(&self, damage: i32) -> i32 {
+        if self.stance == Stance::Evasive {
+            damage * (100 - EVASIVE_DAMAGE_REDUCTION) / 100
+        } else {
+            damage
+        }
+    }
+    
+    pub fn orders_summary(&self) -> String {
+        let mut rules = Vec::new();
+        if let Some(percent) = self.roe.retreat_below {
+            rules.push(format!("retreats below {}% HP", percent));
+        }
+        if self.roe.avoid_superior {
+            rules.push("avoids superior forces".to_string());
+        }
+        if rules.is_empty() {
+            format!("{:?}", self.stance)
+        } else {
+            format!("{:?}, {}", self.stance, rules.join(", "))
+        }
+    }
+}
+
+impl GameState {
+    fn is_hostile(&self, a: &str, b: &str) -> bool {
+        a != b && self.treaty_between(a, b).is_none()
+    }
+    
+    // Combined HP plus damage of a player's side in a sector, and of everyone hostile to them
+    fn sector_strengths(&self, sector_id: &str, player_id: &str) -> (i32, i32) {
+        let (mut friendly, mut hostile) = (0, 0);
+        for ship in self.ships.values().filter(|s| s.current_sector == sector_id && !s.in_transit()) {
+            if ship.owner == player_id || self.is_allied(player_id, &ship.owner) {
+                friendly += ship.hp + ship.damage;
+            } else if self.is_hostile(player_id, &ship.owner) {
+                hostile += ship.hp + ship.damage;
+            }
+        }
+        (friendly, hostile)
+    }
+    
+    // Ships the order applies to: one ship, all of ours in a sector, or the whole fleet
+    fn order_targets(&self, player_id: &str, target: &str) -> Vec<String> {
+        let mut ship_ids: Vec<String> = self.ships.values()
+            .filter(|s| s.owner == player_id)
+            .filter(|s| target == "all" || s.id == target || (s.current_sector == target && !s.in_transit()))
+            .map(|s| s.id.clone())
+            .collect();
+        ship_ids.sort();
+        ship_ids
+    }
+    
+    pub fn set_stance(&mut self, player_id: &str, target: &str, stance: Stance) -> CommandResult {
+        let ship_ids = self.order_targets(player_id, target);
+        if ship_ids.is_empty() {
+            return CommandResult {
+                success: false,
+                message: format!("No ships of yours match {}", target),
+                ap_spent: 0,
+                game_state: self.clone(),
+            };
+        }
+        
+        for ship_id in &ship_ids {
+            self.ships.get_mut(ship_id).unwrap().stance = stance;
+        }
+        
+        CommandResult {
+            success: true,
+            message: format!("{} ship(s) set to {:?}", ship_ids.len(), stance),
+            ap_spent: 0,
+            game_state: self.clone(),
+        }
+    }
+    
+    pub fn set_roe(&mut self, player_id: &str, target: &str, rule: RoeRule) -> CommandResult {
+        if let RoeRule::RetreatBelow(Some(percent)) = rule {
+            if !(1..=99).contains(&percent) {
+                return CommandResult {
+                    success: false,
+                    message: "Retreat threshold must be 1-99% HP".to_string(),
+                    ap_spent: 0,
+                    game_state: self.clone(),
+                };
+            }
+        }
+        
+        let ship_ids = self.order_targets(player_id, target);
+        if ship_ids.is_empty() {
+            return CommandResult {
+                success: false,
+                message: format!("No ships of yours match {}", target),
+                ap_spent: 0,
+                game_state: self.clone(),
+            };
+        }
+        
+        for ship_id in &ship_ids {
+            let roe = &mut self.ships.get_mut(ship_id).unwrap().roe;
+            match rule {
+                RoeRule::RetreatBelow(percent) => roe.retreat_below = percent,
+                RoeRule::AvoidSuperior(avoid) => roe.avoid_superior = avoid,
+            }
+        }
+        
+        let summary = self.ships[&ship_ids[0]].orders_summary();
+        CommandResult {
+            success: true,
+            message: format!("Rules of engagement updated for {} ship(s): {}", ship_ids.len(), summary),
+            ap_spent: 0,
+            game_state: self.clone(),
+        }
+    }
+    
+    // Sends a ship off along its route to a sector without spending AP. Garrisons hold their
+    // post, escrowed ships stay put, and a ship without the fuel can't go
+    pub fn withdraw(&mut self, ship_id: &str, destination: &str) -> bool {
+        let ship = match self.ships.get(ship_id) {
+            Some(s) if !s.in_transit() => s.clone(),
+            _ => return false,
+        };
+        let garrisoning = self.sectors.iter().any(|s| s.garrison_ship.as_deref() == Some(ship_id));
+        if garrisoning || self.ship_in_escrow(ship_id) {
+            return false;
+        }
+        let route = match self.route(&ship.current_sector, destination) {
+            Some(r) if ship.fuel() >= Ship::fuel_for(r.len()) => r,
+            _ => return false,
+        };
+        
+        if let Some(sector) = self.sectors.iter_mut().find(|s| s.id == ship.current_sector) {
+            sector.ships_present.retain(|id| id != ship_id);
+        }
+        let ticks_per_hop = ship.ticks_per_hop();
+        self.ships.get_mut(ship_id).unwrap().transit = Some(Transit { route, ticks_per_hop, ticks_to_next: ticks_per_hop });
+        true
+    }
+    
+    // Nearest supplied sector other than the one the ship is in
+    fn nearest_friendly_sector(&self, ship: &Ship) -> Option<String> {
+        let mut supplied: Vec<String> = self.supplied_sectors(&ship.owner).into_iter()
+            .filter(|id| *id != ship.current_sector)
+            .collect();
+        supplied.sort();
+        supplied.into_iter()
+            .filter_map(|id| self.sector_distance(&ship.current_sector, &id).map(|d| (d, id)))
+            .min()
+            .map(|(_, id)| id)
+    }
+    
+    // Retreat rules and evasive stances, for a ship with hostiles in its sector
+    pub fn consider_withdrawal(&mut self, ship_id: &str) {
+        let ship = match self.ships.get(ship_id) {
+            Some(s) if !s.in_transit() && self.players.contains_key(&s.owner) => s.clone(),
+            _ => return,
+        };
+        if self.sector_strengths(&ship.current_sector, &ship.owner).1 == 0 {
+            return;
+        }
+        
+        let hurt = ship.roe.retreat_below.is_some_and(|percent| ship.hp * 100 < ship.max_hp * percent);
+        let reason = if hurt {
+            "is badly damaged"
+        } else if ship.stance == Stance::Evasive {
+            "is evading hostiles"
+        } else {
+            return;
+        };
+        
+        // With nowhere friendly to run to, any way out of the fight will do
+        let destination = match self.nearest_friendly_sector(&ship).or_else(|| self.fallback_sector(&ship)) {
+            Some(d) => d,
+            None => return,
+        };
+        if self.withdraw(ship_id, &destination) {
+            self.events.push(GameEvent {
+                player_id: ship.owner.clone(),
+                message: format!("{} {} and is falling back to {}", ship.name, reason, self.sector_name(&destination)),
+            });
+        }
+    }
+    
+    // Lets every ship in a sector act on its stance and rules of engagement: aggressive ships
+    // open fire on the weakest hostile they can see unless told to avoid a stronger force,
+    // then ships that want out withdraw. Runs when ships arrive and once per cycle
+    pub fn engage(&mut self, sector_id: &str) {
+        let mut ship_ids: Vec<String> = self.ships.values()
+            .filter(|s| s.current_sector == sector_id && !s.in_transit())
+            .map(|s| s.id.clone())
+            .collect();
+        ship_ids.sort();
+        
+        for ship_id in &ship_ids {
+            let ship = match self.ships.get(ship_id) {
+                Some(s) if s.stance == Stance::Aggressive && !s.in_transit() && self.players.contains_key(&s.owner) => s.clone(),
+                _ => continue,
+            };
+            
+            let (friendly, hostile) = self.sector_strengths(sector_id, &ship.owner);
+            if hostile == 0 || (ship.roe.avoid_superior && hostile > friendly) {
+                continue;
+            }
+            
+            let sector = match self.sectors.iter().find(|s| s.id == sector_id) {
+                Some(s) => s,
+                None => return,
+            };
+            let target = self.visible_ships(sector, &ship.owner).into_iter()
+                .filter(|s| self.is_hostile(&ship.owner, &s.owner))
+                .min_by_key(|s| (s.hp, s.id.clone()))
+                .map(|s| s.id.clone());
+            if let Some(target_id) = target {
+                let report = self.resolve_combat(std::slice::from_ref(ship_id), &target_id);
+                self.events.push(GameEvent {
+                    player_id: ship.owner.clone(),
+                    message: format!("{} engaged on its own (aggressive stance):\n{}", ship.name, report),
+                });
+            }
+        }
+        
+        for ship_id in &ship_ids {
+            self.consider_withdrawal(ship_id);
+        }
+    }
+    
+    // Every sector where hostile ships meet. Called once per cycle
+    pub fn process_engagements(&mut self) {
+        let mut sector_ids: Vec<String> = self.sectors.iter()
+            .filter(|s| s.ships_present.len() > 1)
+            .map(|s| s.id.clone())
+            .collect();
+        sector_ids.sort();
+        for sector_id in sector_ids {
+            self.engage(&sector_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Command, Player, ShipType};
+    
+    fn two_player_game() -> GameState {
+        let mut state = GameState::new();
+        state.players.insert("p1".to_string(), Player::new("p1", "Alpha"));
+        state.players.insert("p2".to_string(), Player::new("p2", "Beta"));
+        state
+    }
+    
+    #[test]
+    fn aggressive_ships_fire_on_arrivals() {
+        let mut state = two_player_game();
+        let ship_id = state.spawn_ship("p1", ShipType::Frigate, "earth-1", "Pioneer").unwrap();
+        let picket = state.spawn_ship("p2", ShipType::Destroyer, "earth-2", "Picket").unwrap();
+        assert!(state.set_stance("p2", &picket, Stance::Aggressive).success);
+        state.execute_command("p1", Command::Move { ship_id: ship_id.clone(), sector_id: "earth-2".to_string() });
+        
+        while state.ships[&ship_id].in_transit() {
+            state.process_transit();
+        }
+        let ship = &state.ships[&ship_id];
+        assert_eq!(ship.current_sector, "earth-2");
+        assert_eq!(ship.hp, ship.max_hp - 30);
+        assert!(state.take_events().iter().any(|e| e.player_id == "p2" && e.message.contains("engaged on its own")));
+    }
+    
+    #[test]
+    fn avoiding_superior_forces_holds_fire() {
+        let mut state = two_player_game();
+        let raider = state.spawn_ship("p1", ShipType::Frigate, "earth-6", "Raider").unwrap();
+        let target = state.spawn_ship("p2", ShipType::Destroyer, "earth-6", "Bulwark").unwrap();
+        state.set_stance("p1", &raider, Stance::Aggressive);
+        state.set_roe("p1", &raider, RoeRule::AvoidSuperior(true));
+        
+        state.engage("earth-6");
+        assert_eq!(state.ships[&target].hp, state.ships[&target].max_hp);
+        
+        state.set_roe("p1", &raider, RoeRule::AvoidSuperior(false));
+        state.engage("earth-6");
+        assert!(state.ships[&target].hp < state.ships[&target].max_hp);
+    }
+    
+    #[test]
+    fn damaged_ships_withdraw_after_combat() {
+        let mut state = two_player_game();
+        let ship_id = state.spawn_ship("p1", ShipType::Frigate, "earth-6", "Pioneer").unwrap();
+        let attacker = state.spawn_ship("p2", ShipType::Destroyer, "earth-6", "Hunter").unwrap();
+        assert!(!state.set_roe("p1", &ship_id, RoeRule::RetreatBelow(Some(100))).success);
+        state.set_roe("p1", &ship_id, RoeRule::RetreatBelow(Some(30)));
+        state.ships.get_mut(&ship_id).unwrap().hp = 50;
+        
+        // 20/80 HP left is under 30%
+        state.resolve_combat(std::slice::from_ref(&attacker), &ship_id);
+        let ship = &state.ships[&ship_id];
+        assert_eq!(ship.hp, 20);
+        assert!(ship.in_transit());
+        assert!(state.take_events().iter().any(|e| e.player_id == "p1" && e.message.contains("badly damaged")));
+        assert!(!state.sectors.iter().find(|s| s.id == "earth-6").unwrap().ships_present.contains(&ship_id));
+    }
+}
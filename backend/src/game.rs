@@ -1,16 +1,113 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use chrono::{DateTime, Utc, Duration};
-use uuid::Uuid;
+use std::collections::{HashMap, VecDeque};
+use chrono::{DateTime, Utc};
+
+use crate::diplomacy::{Treaty, TreatyKind};
+use crate::trade::{TradeBundle, TradeOffer};
+use crate::pirates::PIRATE_PLAYER_ID;
+use crate::bots::BotConfig;
+use crate::ships::{Ability, ShipStatsPolicy, ship_class};
+use crate::leaderboard::ScoreSnapshot;
+use crate::maps::{MapDefinition, MapSource};
+use crate::market::Market;
+use crate::notifications::{Alert, EmailCheck, Notification, NotificationKind, NotificationPrefs, NotifyChange};
+use crate::terrain::Terrain;
+use crate::transit::Transit;
+use crate::supply::Supply;
+use crate::crew::{full_morale, MAX_MORALE, CREW_INTEL_ACCURACY};
+use crate::engagement::{RoeRule, RulesOfEngagement, Stance};
+use crate::orders::QueuedOrder;
+use crate::simultaneous::TurnMode;
+use crate::spectator::{MIN_SPECTATOR_DELAY_SECS, SPECTATORS};
+use crate::webpush::PushSubscription;
+use crate::victory::{GameOutcome, VictoryCondition, default_victory_conditions};
 
 // Main game state that holds everything
 #[derive(Serialize, Deserialize, Clone)]
 pub struct GameState {
     pub players: HashMap<String, Player>,
     pub sectors: Vec<Sector>,
+    #[serde(default)]
+    pub start_slots: Vec<String>, // Where newcomers are placed, from the map
     pub ships: HashMap<String, Ship>,
     pub last_cycle: DateTime<Utc>,
     pub cycle_number: u32,
+    #[serde(default)]
+    pub relations: Vec<Treaty>,
+    #[serde(default)]
+    pub trade_offers: Vec<TradeOffer>,
+    #[serde(default)]
+    pub next_trade_id: u32,
+    #[serde(default)]
+    pub orders: Vec<QueuedOrder>, // Every player's queue, in execution order
+    #[serde(default)]
+    pub next_order_id: u32,
+    #[serde(default)]
+    pub market: Market,
+    #[serde(default)]
+    pub settings: GameSettings,
+    #[serde(default)]
+    pub next_pirate_id: u32,
+    #[serde(default)]
+    pub bots: Vec<BotConfig>,
+    #[serde(default)]
+    pub outcome: Option<GameOutcome>, // Set once a victory condition is met
+    #[serde(default = "first_season")]
+    pub season: u32,
+    #[serde(default)]
+    pub sector_holds: HashMap<String, (String, u32)>, // Sector ID -> (controller, consecutive cycles)
+    #[serde(default)]
+    pub score_history: Vec<ScoreSnapshot>,
+    #[serde(default)]
+    pub banned: Vec<String>, // Player IDs refused at connect
+    #[serde(skip)]
+    pub events: Vec<GameEvent>, // Pending per-player notifications, drained by the server
+    #[serde(skip)]
+    pub alerts: Vec<Alert>, // Pending notifications for players who may be offline, drained by the server
+    #[serde(skip)]
+    pub email_checks: Vec<EmailCheck>, // Confirmation codes waiting to be emailed, drained by the server
+}
+
+// Tunables that can differ between games
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct GameSettings {
+    pub name: String, // Shown in the lobby
+    pub map: MapSource, // Standard grid, a map file or a generated map
+    pub map_size: i32, // Standard grid is map_size x map_size, plus the core
+    pub cycle_secs: u64, // Real time between cycles
+    pub spectator_delay_secs: u64, // How far the spectator stream lags behind live play
+    pub ship_stats_policy: ShipStatsPolicy, // Whether ship definition changes reach ships in play
+    pub turn_mode: TurnMode, // Real-time commands, or orders resolved together each cycle
+    pub trade_expiry_cycles: u32, // Open trade offers lapse after this many cycles
+    pub victory_conditions: Vec<VictoryCondition>, // The first one met ends the season
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        Self {
+            name: "Interstellar Command".to_string(),
+            map: MapSource::default(),
+            map_size: 4,
+            cycle_secs: 8 * 60 * 60, // 8 hours
+            spectator_delay_secs: MIN_SPECTATOR_DELAY_SECS,
+            ship_stats_policy: ShipStatsPolicy::default(),
+            turn_mode: TurnMode::default(),
+            trade_expiry_cycles: 3,
+            victory_conditions: default_victory_conditions(),
+        }
+    }
+}
+
+fn first_season() -> u32 {
+    1
+}
+
+// A message for a single player produced outside of their own commands
+#[derive(Serialize, Deserialize, Clone)]
+pub struct GameEvent {
+    pub player_id: String,
+    pub message: String,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -26,6 +123,18 @@ pub struct Player {
     pub owned_ships: Vec<String>, // Ship IDs
     pub command_ships: Vec<String>, // Command ship IDs
     pub garrison_slots: i32, // Available garrison ships
+    #[serde(default)]
+    pub resources: HashMap<Resource, i32>,
+    #[serde(default)]
+    pub kills: u32, // Enemy ships destroyed
+    #[serde(default)]
+    pub debt_cycles: u32, // Consecutive cycles ended in debt
+    #[serde(default)]
+    pub notifications: NotificationPrefs,
+    #[serde(default)]
+    pub inbox: Vec<Notification>, // Kept while offline, delivered on next connect
+    #[serde(default)]
+    pub push_subscriptions: Vec<PushSubscription>, // One per device that allowed Web Push
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -34,6 +143,12 @@ pub struct Sector {
     pub name: String,
     pub position: (i32, i32), // Grid position
     pub planet: String, // "Earth" or "Mars"
+    #[serde(default)]
+    pub links: Vec<String>, // Sector IDs one hop away
+    #[serde(default)]
+    pub traits: Vec<String>, // Free-form tags from the map file
+    #[serde(default)]
+    pub terrain: Vec<Terrain>, // Gameplay modifiers, see terrain.rs
     pub controlled_by: Option<String>, // Player ID
     pub garrison_ship: Option<String>, // Ship ID holding the sector
     pub ships_present: Vec<String>, // All ships currently in this sector
@@ -50,9 +165,23 @@ pub struct Ship {
     pub max_hp: i32,
     pub damage: i32,
     pub ap_cost: i32, // Cost to move one sector
+    #[serde(default)]
+    pub cloaked: bool,
+    #[serde(default)]
+    pub transit: Option<Transit>, // Set while travelling between sectors
+    #[serde(default)]
+    pub fuel_used: i32, // Since the ship last refuelled
+    #[serde(default)]
+    pub battles: u32, // Survived; crews gain rank with experience
+    #[serde(default = "full_morale")]
+    pub morale: i32, // 0-100
+    #[serde(default)]
+    pub stance: Stance,
+    #[serde(default)]
+    pub roe: RulesOfEngagement,
 }
 
-#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub enum ShipType {
     // Tier 1
     ScoutDrone,
@@ -72,13 +201,43 @@ pub enum ShipType {
     Carrier,
 }
 
-#[derive(Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Resource {
+    Ore,
+    Fuel,
+    Components,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub enum Command {
     Move { ship_id: String, sector_id: String },
-    Scan { sector_id: String },
+    Scan { sector_id: String, scanner_id: Option<String> },
     Attack { target_ship_id: String },
+    Cloak { ship_id: String },
+    Decloak { ship_id: String },
+    Propose { player: String, kind: TreatyKind, shared_victory: bool },
+    Accept { player: String },
+    Break { player: String },
+    Relations,
+    Leaderboard,
+    TradeOffer { player: String, give: TradeBundle, want: TradeBundle },
+    TradeAccept { offer_id: u32 },
+    TradeDecline { offer_id: u32 },
+    TradeList,
     DeclareControl { sector_id: String, command_ship_id: String },
     SetGarrison { sector_id: String, garrison_ship_id: String },
+    Build { ship_type: ShipType, sector_id: String, name: Option<String> },
+    Queue { steps: Vec<(String, Command)>, at_cycle: Option<u32> }, // None means next cycle
+    Orders,
+    CancelOrder { order_id: u32 },
+    ReorderOrder { order_id: u32, position: usize },
+    Rename { ship_id: String, name: String },
+    SetStance { target: String, stance: Stance }, // A ship id, a sector id or "all"
+    SetRoe { target: String, rule: RoeRule },
+    Notify { change: Option<NotifyChange> }, // None just shows the current settings
+    Market,
+    Buy { resource: Resource, amount: i32 },
+    Sell { resource: Resource, amount: i32 },
     Status,
     Fleet,
 }
@@ -93,70 +252,116 @@ pub struct CommandResult {
 
 impl GameState {
     pub fn new() -> Self {
-        let mut sectors = Vec::new();
-        
-        // Create Earth sectors (4x4 grid + core)
-        for x in 0..4 {
-            for y in 0..4 {
-                sectors.push(Sector {
-                    id: format!("earth-{}", x * 4 + y + 1),
-                    name: format!("E{}", x * 4 + y + 1),
-                    position: (x, y),
-                    planet: "Earth".to_string(),
-                    controlled_by: None,
-                    garrison_ship: None,
-                    ships_present: Vec::new(),
-                });
+        Self::with_settings(GameSettings::default())
+    }
+    
+    pub fn with_settings(settings: GameSettings) -> Self {
+        let map = match settings.map.load(settings.map_size) {
+            Ok(map) => map,
+            Err(e) => {
+                tracing::warn!("{}; using the standard grid", e);
+                MapDefinition::grid(settings.map_size)
             }
-        }
-        
-        // Earth core
-        sectors.push(Sector {
-            id: "earth-core".to_string(),
-            name: "Earth Core".to_string(),
-            position: (2, 2), // Center position
-            planet: "Earth".to_string(),
-            controlled_by: None,
-            garrison_ship: None,
-            ships_present: Vec::new(),
-        });
+        };
+        let start_slots = map.start_slots.clone();
+        let sectors = map.into_sectors();
         
         Self {
             players: HashMap::new(),
             sectors,
+            start_slots,
             ships: HashMap::new(),
             last_cycle: Utc::now(),
             cycle_number: 0,
+            relations: Vec::new(),
+            trade_offers: Vec::new(),
+            next_trade_id: 0,
+            orders: Vec::new(),
+            next_order_id: 0,
+            market: Market::default(),
+            settings,
+            next_pirate_id: 0,
+            bots: Vec::new(),
+            outcome: None,
+            season: 1,
+            sector_holds: HashMap::new(),
+            score_history: Vec::new(),
+            banned: Vec::new(),
+            events: Vec::new(),
+            alerts: Vec::new(),
+            email_checks: Vec::new(),
         }
     }
     
     pub fn execute_command(&mut self, player_id: &str, command: Command) -> CommandResult {
-        let player = match self.players.get(player_id) {
-            Some(p) => p,
-            None => return CommandResult {
+        if !self.players.contains_key(player_id) {
+            return CommandResult {
                 success: false,
                 message: "Player not found".to_string(),
                 ap_spent: 0,
                 game_state: self.clone(),
-            }
-        };
+            };
+        }
         
-        match command {
+        if self.outcome.is_some() {
+            return CommandResult {
+                success: false,
+                message: format!("Season {} is over; a new season starts next cycle", self.season),
+                ap_spent: 0,
+                game_state: self.clone(),
+            };
+        }
+        
+        // Simultaneous games hold back anything that changes the world until the cycle resolves
+        if self.settings.turn_mode == TurnMode::Simultaneous && command.resolves_at_cycle() {
+            return self.submit_order(player_id, command);
+        }
+        
+        self.apply_command(player_id, command)
+    }
+    
+    // Carries a command out straight away, whatever the turn mode. The player must exist
+    pub fn apply_command(&mut self, player_id: &str, command: Command) -> CommandResult {
+        let result = match command {
             Command::Move { ship_id, sector_id } => self.move_ship(player_id, &ship_id, &sector_id),
-            Command::Scan { sector_id } => self.scan_sector(player_id, &sector_id),
+            Command::Scan { sector_id, scanner_id } => 
+                self.scan_sector(player_id, &sector_id, scanner_id.as_deref()),
             Command::Status => self.player_status(player_id),
             Command::Fleet => self.fleet_status(player_id),
             Command::DeclareControl { sector_id, command_ship_id } => 
                 self.declare_control(player_id, &sector_id, &command_ship_id),
             Command::SetGarrison { sector_id, garrison_ship_id } => 
                 self.set_garrison(player_id, &sector_id, &garrison_ship_id),
-            _ => CommandResult {
-                success: false,
-                message: "Command not implemented yet".to_string(),
-                ap_spent: 0,
-                game_state: self.clone(),
-            }
-        }
+            Command::Attack { target_ship_id } => self.attack_ship(player_id, &target_ship_id),
+            Command::Build { ship_type, sector_id, name } => 
+                self.build_ship(player_id, ship_type, &sector_id, name.as_deref()),
+            Command::Queue { steps, at_cycle } => self.queue_orders(player_id, steps, at_cycle),
+            Command::Orders => self.list_orders(player_id),
+            Command::CancelOrder { order_id } => self.cancel_order(player_id, order_id),
+            Command::ReorderOrder { order_id, position } => self.reorder_order(player_id, order_id, position),
+            Command::Rename { ship_id, name } => self.rename_ship(player_id, &ship_id, &name),
+            Command::SetStance { target, stance } => self.set_stance(player_id, &target, stance),
+            Command::SetRoe { target, rule } => self.set_roe(player_id, &target, rule),
+            Command::Notify { change } => self.notification_settings(player_id, change),
+            Command::Market => self.market_status(player_id),
+            Command::Buy { resource, amount } => self.buy_resource(player_id, resource, amount),
+            Command::Sell { resource, amount } => self.sell_resource(player_id, resource, amount),
+            Command::Cloak { ship_id } => self.set_cloak(player_id, &ship_id, true),
+            Command::Decloak { ship_id } => self.set_cloak(player_id, &ship_id, false),
+            Command::Propose { player, kind, shared_victory } => 
+                self.propose_treaty(player_id, &player, kind, shared_victory),
+            Command::Accept { player } => self.accept_treaty(player_id, &player),
+            Command::Break { player } => self.break_treaty(player_id, &player),
+            Command::Relations => self.relations_report(player_id),
+            Command::Leaderboard => self.leaderboard_report(player_id),
+            Command::TradeOffer { player, give, want } => self.offer_trade(player_id, &player, give, want),
+            Command::TradeAccept { offer_id } => self.accept_trade(player_id, offer_id),
+            Command::TradeDecline { offer_id } => self.decline_trade(player_id, offer_id),
+            Command::TradeList => self.list_trades(player_id),
+        };
+        
+        self.check_victory();
+        result
     }
     
     fn move_ship(&mut self, player_id: &str, ship_id: &str, target_sector_id: &str) -> CommandResult {
@@ -177,12 +382,31 @@ impl GameState {
             }
         };
         
-        // Check if player has enough AP
+        if self.ship_in_escrow(ship_id) {
+            return CommandResult {
+                success: false,
+                message: format!("{} is held in escrow for a trade", ship.name),
+                ap_spent: 0,
+                game_state: self.clone(),
+            };
+        }
+        
+        if let Some(transit) = &ship.transit {
+            return CommandResult {
+                success: false,
+                message: format!("{} is already under way to {}, ETA {}", ship.name, self.sector_name(transit.destination()), self.describe_eta(transit.ticks_left())),
+                ap_spent: 0,
+                game_state: self.clone(),
+            };
+        }
+        
+        // Check if player has enough AP; terrain at either end can change the cost
+        let move_cost = self.move_cost(&ship, target_sector_id);
         let player = self.players.get_mut(player_id).unwrap();
-        if player.current_ap < ship.ap_cost {
+        if player.current_ap < move_cost {
             return CommandResult {
                 success: false,
-                message: format!("Not enough AP. Need {} but have {}", ship.ap_cost, player.current_ap),
+                message: format!("Not enough AP. Need {} but have {}", move_cost, player.current_ap),
                 ap_spent: 0,
                 game_state: self.clone(),
             };
@@ -199,42 +423,64 @@ impl GameState {
             }
         };
         
-        // Remove ship from current sector
+        if ship.current_sector == target_sector_id {
+            return CommandResult {
+                success: false,
+                message: format!("{} is already in {}", ship.name, self.sectors[target_sector].name),
+                ap_spent: 0,
+                game_state: self.clone(),
+            };
+        }
+        
+        let route = match self.route(&ship.current_sector, target_sector_id) {
+            Some(r) => r,
+            None => return CommandResult {
+                success: false,
+                message: format!("There's no route to {}", self.sectors[target_sector].name),
+                ap_spent: 0,
+                game_state: self.clone(),
+            }
+        };
+        
+        let fuel_needed = Ship::fuel_for(route.len());
+        if ship.fuel() < fuel_needed {
+            return CommandResult {
+                success: false,
+                message: format!(
+                    "Not enough fuel. {} needs {} to reach {} but has {}",
+                    ship.name, fuel_needed, self.sectors[target_sector].name, ship.fuel()
+                ),
+                ap_spent: 0,
+                game_state: self.clone(),
+            };
+        }
+        
+        // Leave the current sector; the ship isn't in any sector until it reaches the next one
         if let Some(current_sector) = self.sectors.iter_mut().find(|s| s.id == ship.current_sector) {
             current_sector.ships_present.retain(|id| id != ship_id);
         }
         
-        // Add ship to new sector
-        self.sectors[target_sector].ships_present.push(ship_id.to_string());
-        
-        // Update ship location
-        self.ships.get_mut(ship_id).unwrap().current_sector = target_sector_id.to_string();
+        let hops = route.len();
+        let ticks_per_hop = ship.ticks_per_hop();
+        let transit = Transit { route, ticks_per_hop, ticks_to_next: ticks_per_hop };
+        let eta = self.describe_eta(transit.ticks_left());
+        self.ships.get_mut(ship_id).unwrap().transit = Some(transit);
         
         // Deduct AP
-        self.players.get_mut(player_id).unwrap().current_ap -= ship.ap_cost;
+        self.players.get_mut(player_id).unwrap().current_ap -= move_cost;
         
         CommandResult {
             success: true,
-            message: format!("{} moved to {}", ship.name, self.sectors[target_sector].name),
-            ap_spent: ship.ap_cost,
+            message: format!(
+                "{} set course for {} ({} hop{}), ETA {}",
+                ship.name, self.sectors[target_sector].name, hops, if hops == 1 { "" } else { "s" }, eta
+            ),
+            ap_spent: move_cost,
             game_state: self.clone(),
         }
     }
     
-    fn scan_sector(&mut self, player_id: &str, sector_id: &str) -> CommandResult {
-        const SCAN_COST: i32 = 3;
-        
-        // Check AP
-        let player = self.players.get_mut(player_id).unwrap();
-        if player.current_ap < SCAN_COST {
-            return CommandResult {
-                success: false,
-                message: "Not enough AP for scan (need 3)".to_string(),
-                ap_spent: 0,
-                game_state: self.clone(),
-            };
-        }
-        
+    fn scan_sector(&mut self, player_id: &str, sector_id: &str, scanner_id: Option<&str>) -> CommandResult {
         // Find sector
         let sector = match self.sectors.iter().find(|s| s.id == sector_id) {
             Some(s) => s,
@@ -246,38 +492,242 @@ impl GameState {
             }
         };
         
-        // Build scan report
+        // Pick the scanning ship: an explicit one, or the cheapest (then most accurate) hull in range
+        let player = self.players.get(player_id).unwrap();
+        let scanner = match scanner_id {
+            Some(id) => match self.ships.get(id) {
+                Some(s) if s.owner == player_id => {
+                    // Sensors aren't calibrated until the ship stops somewhere
+                    if let Some(transit) = &s.transit {
+                        return CommandResult {
+                            success: false,
+                            message: format!("{} is under way to {} and can't scan until it arrives", s.name, self.sector_name(transit.destination())),
+                            ap_spent: 0,
+                            game_state: self.clone(),
+                        };
+                    }
+                    let in_range = self.sector_distance(&s.current_sector, sector_id)
+                        .is_some_and(|d| d <= s.ship_type.get_sensor_stats().0);
+                    if !in_range {
+                        return CommandResult {
+                            success: false,
+                            message: format!("{} is out of sensor range of {}", s.name, sector.name),
+                            ap_spent: 0,
+                            game_state: self.clone(),
+                        };
+                    }
+                    if self.scan_blocked(&s.current_sector, sector) {
+                        return CommandResult {
+                            success: false,
+                            message: format!("{} is inside a nebula; only ships within it can scan it", sector.name),
+                            ap_spent: 0,
+                            game_state: self.clone(),
+                        };
+                    }
+                    s
+                }
+                Some(_) => return CommandResult {
+                    success: false,
+                    message: "That's not your ship!".to_string(),
+                    ap_spent: 0,
+                    game_state: self.clone(),
+                },
+                None => return CommandResult {
+                    success: false,
+                    message: "Ship not found".to_string(),
+                    ap_spent: 0,
+                    game_state: self.clone(),
+                }
+            },
+            None => {
+                let best = player.owned_ships.iter()
+                    .filter_map(|id| self.ships.get(id))
+                    .filter(|s| !s.in_transit())
+                    .filter(|s| {
+                        self.sector_distance(&s.current_sector, sector_id)
+                            .is_some_and(|d| d <= s.ship_type.get_sensor_stats().0)
+                    })
+                    .filter(|s| !self.scan_blocked(&s.current_sector, sector))
+                    .min_by_key(|s| {
+                        let (_, cost, accuracy) = s.ship_type.get_sensor_stats();
+                        (cost, -accuracy)
+                    });
+                match best {
+                    Some(s) => s,
+                    None if sector.has_terrain(Terrain::Nebula) => return CommandResult {
+                        success: false,
+                        message: format!("{} is inside a nebula; only ships within it can scan it", sector.name),
+                        ap_spent: 0,
+                        game_state: self.clone(),
+                    },
+                    None => return CommandResult {
+                        success: false,
+                        message: format!("No ships within sensor range of {}", sector.name),
+                        ap_spent: 0,
+                        game_state: self.clone(),
+                    }
+                }
+            }
+        };
+        let (_, scan_cost, accuracy) = scanner.ship_type.get_sensor_stats();
+        let (_, detection, _) = scanner.ship_type.get_stealth_stats();
+        
+        // Check AP
+        if player.current_ap < scan_cost {
+            return CommandResult {
+                success: false,
+                message: format!("Not enough AP for scan (need {})", scan_cost),
+                ap_spent: 0,
+                game_state: self.clone(),
+            };
+        }
+        
+        let mut report = format!("Scanning with {} ({:?})\n", scanner.name, scanner.ship_type);
+        report.push_str(&self.scan_report(sector, player_id, accuracy, detection));
+        
+        // Deduct AP
+        self.players.get_mut(player_id).unwrap().current_ap -= scan_cost;
+        
+        CommandResult {
+            success: true,
+            message: report,
+            ap_spent: scan_cost,
+            game_state: self.clone(),
+        }
+    }
+    
+    // Full sector report as seen by a scanner with the given HP accuracy and detection
+    fn scan_report(&self, sector: &Sector, viewer_id: &str, accuracy: i32, detection: i32) -> String {
         let mut report = format!("=== Sector {} ({}) ===\n", sector.name, sector.planet);
         report.push_str(&format!("Position: ({}, {})\n", sector.position.0, sector.position.1));
+        report.push_str(&format!("Links: {}\n", sector.links.join(", ")));
+        if !sector.terrain.is_empty() {
+            let terrain: Vec<&str> = sector.terrain.iter().map(|t| t.label()).collect();
+            report.push_str(&format!("Terrain: {}\n", terrain.join(", ")));
+        }
+        if !sector.traits.is_empty() {
+            report.push_str(&format!("Traits: {}\n", sector.traits.join(", ")));
+        }
         
         if let Some(controller) = &sector.controlled_by {
-            let controller_name = self.players.get(controller).map(|p| &p.name).unwrap_or(&"Unknown".to_string());
+            let controller_name = self.players.get(controller).map(|p| p.name.as_str()).unwrap_or("Unknown");
             report.push_str(&format!("Controlled by: {}\n", controller_name));
         } else {
             report.push_str("Status: Neutral\n");
         }
         
-        if !sector.ships_present.is_empty() {
+        let detected = self.detected_ships(sector, viewer_id, detection);
+        
+        if !detected.is_empty() {
             report.push_str("\nShips present:\n");
-            for ship_id in &sector.ships_present {
-                if let Some(ship) = self.ships.get(ship_id) {
-                    let owner_name = self.players.get(&ship.owner).map(|p| &p.name).unwrap_or(&"Unknown".to_string());
-                    report.push_str(&format!("- {} ({:?}) [Owner: {}]\n", ship.name, ship.ship_type, owner_name));
-                }
+            for ship in detected {
+                let owner_name = self.owner_name(&ship.owner);
+                // Own ships are always reported exactly
+                let hp = if ship.owner == viewer_id {
+                    format!("{}/{}", ship.hp, ship.max_hp)
+                } else {
+                    ship.estimate_hp(accuracy)
+                };
+                // Good enough sensors also read crew quality
+                let crew = if ship.owner == viewer_id || accuracy >= CREW_INTEL_ACCURACY {
+                    format!(" - {}", ship.crew_summary())
+                } else {
+                    String::new()
+                };
+                report.push_str(&format!("- {} ({:?}) [Owner: {}] HP: {}{}\n", ship.name, ship.ship_type, owner_name, hp, crew));
             }
         } else {
             report.push_str("\nNo ships detected\n");
         }
         
-        // Deduct AP
-        self.players.get_mut(player_id).unwrap().current_ap -= SCAN_COST;
+        let inbound = self.inbound_ships(&sector.id, viewer_id, detection);
+        if !inbound.is_empty() {
+            report.push_str("\nInbound:\n");
+            for ship in inbound {
+                let ticks = ship.transit.as_ref().map_or(0, |t| t.ticks_left());
+                report.push_str(&format!(
+                    "- {} ({:?}) [Owner: {}] ETA {}\n",
+                    ship.name, ship.ship_type, self.owner_name(&ship.owner), self.describe_eta(ticks)
+                ));
+            }
+        }
         
-        CommandResult {
-            success: true,
-            message: report,
-            ap_spent: SCAN_COST,
-            game_state: self.clone(),
+        report
+    }
+    
+    // Ships in a sector that a player can see from their own and allied ships there
+    pub fn visible_ships(&self, sector: &Sector, viewer_id: &str) -> Vec<&Ship> {
+        self.detected_ships(sector, viewer_id, 0)
+    }
+    
+    // Same as visible_ships, boosted by a remote scanner's detection
+    pub fn detected_ships(&self, sector: &Sector, viewer_id: &str, scanner_detection: i32) -> Vec<&Ship> {
+        let detection = sector.ships_present.iter()
+            .filter_map(|id| self.ships.get(id))
+            .filter(|s| s.owner == viewer_id || self.is_allied(viewer_id, &s.owner))
+            .map(|s| s.ship_type.get_stealth_stats().1)
+            .max()
+            .unwrap_or(0)
+            .max(scanner_detection);
+        
+        sector.ships_present.iter()
+            .filter_map(|id| self.ships.get(id))
+            .filter(|s| s.is_visible_to(viewer_id, detection) || self.is_allied(viewer_id, &s.owner))
+            .collect()
+    }
+    
+    // Display name for a ship owner, including the NPC faction. Player IDs double as login
+    // credentials, so anything sent to clients names players through this, never by ID
+    pub fn owner_name(&self, owner_id: &str) -> &str {
+        if owner_id == PIRATE_PLAYER_ID {
+            return "Pirates";
         }
+        self.players.get(owner_id).map(|p| p.name.as_str()).unwrap_or("Unknown")
+    }
+    
+    // Sectors one hop away
+    pub fn adjacent_sectors(&self, sector_id: &str) -> Vec<String> {
+        self.sectors.iter()
+            .find(|s| s.id == sector_id)
+            .map(|s| s.links.clone())
+            .unwrap_or_default()
+    }
+    
+    // Resolve a player by ID or (case-insensitive) name
+    pub fn find_player_id(&self, name_or_id: &str) -> Option<String> {
+        if self.players.contains_key(name_or_id) {
+            return Some(name_or_id.to_string());
+        }
+        self.players.values()
+            .find(|p| p.name.eq_ignore_ascii_case(name_or_id))
+            .map(|p| p.id.clone())
+    }
+    
+    // Hops between two sectors along their links, None if either doesn't exist or
+    // there's no route
+    pub fn sector_distance(&self, from_id: &str, to_id: &str) -> Option<i32> {
+        let links: HashMap<&str, &Vec<String>> = self.sectors.iter()
+            .map(|s| (s.id.as_str(), &s.links))
+            .collect();
+        if !links.contains_key(from_id) || !links.contains_key(to_id) {
+            return None;
+        }
+        
+        let mut distances = HashMap::from([(from_id, 0)]);
+        let mut queue = VecDeque::from([from_id]);
+        while let Some(current) = queue.pop_front() {
+            if current == to_id {
+                return Some(distances[current]);
+            }
+            let next_distance = distances[current] + 1;
+            for next in links[current].iter() {
+                if !distances.contains_key(next.as_str()) {
+                    distances.insert(next.as_str(), next_distance);
+                    queue.push_back(next.as_str());
+                }
+            }
+        }
+        None
     }
     
     fn player_status(&self, player_id: &str) -> CommandResult {
@@ -291,12 +741,21 @@ impl GameState {
             }
         };
         
-        let status = format!(
+        let resources = [Resource::Ore, Resource::Fuel, Resource::Components].iter()
+            .map(|r| format!("{:?} {}", r, player.resources.get(r).copied().unwrap_or(0)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        
+        let income = self.income(player_id);
+        let upkeep = self.upkeep(player_id);
+        let mut status = format!(
             "=== Status ===\n\
             Name: {}\n\
             Level: {} (XP: {})\n\
             AP: {}/{}\n\
             Credits: {}\n\
+            Next cycle: +{} income, -{} upkeep ({:+} credits)\n\
+            Resources: {}\n\
             Reputation: {}\n\
             Ships: {}\n\
             Command Ships: {}\n\
@@ -305,11 +764,16 @@ impl GameState {
             player.level, player.xp,
             player.current_ap, player.max_ap,
             player.credits,
+            income, upkeep, income - upkeep,
+            resources,
             player.reputation,
             player.owned_ships.len(),
             player.command_ships.len(),
             player.garrison_slots
         );
+        if player.debt_cycles > 0 {
+            status.push_str(&format!("\nIn debt for {} cycle(s): AP refill is reduced", player.debt_cycles));
+        }
         
         CommandResult {
             success: true,
@@ -336,12 +800,29 @@ impl GameState {
             if let Some(ship) = self.ships.get(ship_id) {
                 let sector_name = self.sectors.iter()
                     .find(|s| s.id == ship.current_sector)
-                    .map(|s| &s.name)
-                    .unwrap_or(&"Unknown".to_string());
+                    .map(|s| s.name.as_str())
+                    .unwrap_or("Unknown");
+                
+                let location = match &ship.transit {
+                    Some(transit) => format!(
+                        "In transit to {} (next: {}), ETA {}",
+                        self.sector_name(transit.destination()), self.sector_name(&transit.route[0]), self.describe_eta(transit.ticks_left())
+                    ),
+                    None => sector_name.to_string(),
+                };
+                
+                let supply = match self.supply_state(ship) {
+                    _ if ship.in_transit() => "",
+                    Supply::CutOff => " [CUT OFF]",
+                    _ => "",
+                };
                 
                 report.push_str(&format!(
-                    "{} ({:?}) - Location: {} - HP: {}/{}\n",
-                    ship.name, ship.ship_type, sector_name, ship.hp, ship.max_hp
+                    "{} ({:?}) - Location: {} - HP: {}/{} - Fuel: {}/{} - {} - Stance: {}{}{}\n",
+                    ship.name, ship.ship_type, location, ship.hp, ship.max_hp,
+                    ship.fuel(), ship.fuel_capacity(), ship.crew_summary(), ship.orders_summary(),
+                    if ship.cloaked { " [CLOAKED]" } else { "" },
+                    supply
                 ));
             }
         }
@@ -359,7 +840,7 @@ impl GameState {
         
         // Verify command ship ownership and location
         let command_ship = match self.ships.get(command_ship_id) {
-            Some(s) if s.owner == player_id && s.ship_type == ShipType::CommandShip => s.clone(),
+            Some(s) if s.owner == player_id && s.ship_type.has_ability(Ability::ClaimSector) => s.clone(),
             Some(s) if s.owner != player_id => return CommandResult {
                 success: false,
                 message: "That's not your command ship!".to_string(),
@@ -381,7 +862,7 @@ impl GameState {
         };
         
         // Check if command ship is in the target sector
-        if command_ship.current_sector != sector_id {
+        if command_ship.current_sector != sector_id || command_ship.in_transit() {
             return CommandResult {
                 success: false,
                 message: "Command ship must be in the sector to declare control".to_string(),
@@ -402,8 +883,15 @@ impl GameState {
         }
         
         // Declare control
+        let mut lost = None;
         if let Some(sector) = self.sectors.iter_mut().find(|s| s.id == sector_id) {
-            sector.controlled_by = Some(player_id.to_string());
+            lost = sector.controlled_by.replace(player_id.to_string())
+                .filter(|previous| previous != player_id)
+                .map(|previous| (previous, sector.name.clone()));
+        }
+        if let Some((previous, sector_name)) = lost {
+            let message = format!("{} was taken from you by {}", sector_name, self.owner_name(player_id));
+            self.notify(&previous, NotificationKind::SectorLost, message);
         }
         
         // Deduct AP
@@ -420,7 +908,7 @@ impl GameState {
     fn set_garrison(&mut self, player_id: &str, sector_id: &str, garrison_ship_id: &str) -> CommandResult {
         // Verify garrison ship
         let garrison_ship = match self.ships.get(garrison_ship_id) {
-            Some(s) if s.owner == player_id && s.ship_type == ShipType::GarrisonShip => s.clone(),
+            Some(s) if s.owner == player_id && s.ship_type.has_ability(Ability::Garrison) => s.clone(),
             _ => return CommandResult {
                 success: false,
                 message: "Invalid garrison ship".to_string(),
@@ -441,7 +929,7 @@ impl GameState {
         };
         
         // Check if garrison ship is in the sector
-        if garrison_ship.current_sector != sector_id {
+        if garrison_ship.current_sector != sector_id || garrison_ship.in_transit() {
             return CommandResult {
                 success: false,
                 message: "Garrison ship must be in the sector".to_string(),
@@ -463,38 +951,593 @@ impl GameState {
         }
     }
     
+    fn attack_ship(&mut self, player_id: &str, target_ship_id: &str) -> CommandResult {
+        const ATTACK_COST: i32 = 5;
+        
+        let target = match self.ships.get(target_ship_id) {
+            Some(s) if s.owner == player_id => return CommandResult {
+                success: false,
+                message: "You can't attack your own ship!".to_string(),
+                ap_spent: 0,
+                game_state: self.clone(),
+            },
+            Some(s) => s.clone(),
+            None => return CommandResult {
+                success: false,
+                message: "Ship not found".to_string(),
+                ap_spent: 0,
+                game_state: self.clone(),
+            }
+        };
+        
+        if let Some(treaty) = self.treaty_between(player_id, &target.owner) {
+            return CommandResult {
+                success: false,
+                message: format!("You have a {:?} with that ship's owner; break it before attacking", treaty.kind),
+                ap_spent: 0,
+                game_state: self.clone(),
+            };
+        }
+        
+        // Cloaked targets we can't detect look exactly like missing ones
+        let sector = self.sectors.iter().find(|s| s.id == target.current_sector).unwrap();
+        if !self.visible_ships(sector, player_id).iter().any(|s| s.id == target.id) {
+            return CommandResult {
+                success: false,
+                message: "Ship not found".to_string(),
+                ap_spent: 0,
+                game_state: self.clone(),
+            };
+        }
+        
+        // Every ship we have in the target's sector joins the attack
+        let mut attackers: Vec<String> = self.ships.values()
+            .filter(|s| s.owner == player_id && s.current_sector == target.current_sector && !s.in_transit())
+            .map(|s| s.id.clone())
+            .collect();
+        attackers.sort();
+        
+        if attackers.is_empty() {
+            return CommandResult {
+                success: false,
+                message: "You have no ships in that sector".to_string(),
+                ap_spent: 0,
+                game_state: self.clone(),
+            };
+        }
+        
+        let player = self.players.get(player_id).unwrap();
+        if player.current_ap < ATTACK_COST {
+            return CommandResult {
+                success: false,
+                message: format!("Not enough AP. Need {} but have {}", ATTACK_COST, player.current_ap),
+                ap_spent: 0,
+                game_state: self.clone(),
+            };
+        }
+        
+        let report = self.resolve_combat(&attackers, target_ship_id);
+        
+        self.players.get_mut(player_id).unwrap().current_ap -= ATTACK_COST;
+        
+        CommandResult {
+            success: true,
+            message: report,
+            ap_spent: ATTACK_COST,
+            game_state: self.clone(),
+        }
+    }
+    
+    // One exchange of fire: the attackers volley the target, and if it survives it returns
+    // fire on the lead attacker unless its stance says otherwise. Shared by player attacks,
+    // NPC raids and ships engaging on their own.
+    pub fn resolve_combat(&mut self, attackers: &[String], target_ship_id: &str) -> String {
+        let target = self.ships[target_ship_id].clone();
+        let attacker_owner = self.ships[&attackers[0]].owner.clone();
+        let attacker_name = self.owner_name(&attacker_owner).to_string();
+        
+        // Opening fire reveals cloaked attackers
+        let mut total_damage = 0;
+        for id in attackers {
+            let ship = self.ships.get_mut(id).unwrap();
+            ship.cloaked = false;
+            total_damage += ship.effective_damage();
+        }
+        total_damage = target.manoeuvre(target.evade(self.terrain_damage(&target.current_sector, total_damage)));
+        let return_damage = self.ships[&attackers[0]].evade(self.terrain_damage(&target.current_sector, target.effective_damage()));
+        
+        let mut report = format!("=== Attack on {} ===\n", target.name);
+        report.push_str(&format!("{} ship(s) fire for {} damage\n", attackers.len(), total_damage));
+        
+        let target_ship = self.ships.get_mut(target_ship_id).unwrap();
+        target_ship.hp -= total_damage;
+        target_ship.cloaked = false;
+        let target_hp = target_ship.hp;
+        
+        if target_hp <= 0 {
+            self.destroy_ship(target_ship_id);
+            report.push_str(&format!("{} destroyed!\n", target.name));
+            self.record_kill(&attacker_owner);
+            self.pay_bounty(&attacker_owner, &target);
+            if target.owner != PIRATE_PLAYER_ID {
+                self.notify(&target.owner, NotificationKind::UnderAttack, format!("{} was destroyed by {}", target.name, attacker_name));
+            }
+        } else {
+            if !target.returns_fire() {
+                report.push_str(&format!("{} survives with {} HP and holds its fire ({:?} stance)\n", target.name, target_hp, target.stance));
+            } else {
+                // Survivors return fire on the lead attacker
+                let lead = self.ships.get_mut(&attackers[0]).unwrap();
+                lead.hp -= return_damage;
+                let lead_after = lead.clone();
+                report.push_str(&format!("{} survives with {} HP and returns fire on {}\n", target.name, target_hp, lead_after.name));
+                if lead_after.hp <= 0 {
+                    self.destroy_ship(&attackers[0]);
+                    report.push_str(&format!("{} destroyed!\n", lead_after.name));
+                    self.record_kill(&target.owner);
+                    self.pay_bounty(&target.owner, &lead_after);
+                }
+            }
+            if target.owner != PIRATE_PLAYER_ID {
+                self.notify(&target.owner, NotificationKind::UnderAttack, format!("{} was attacked by {} ({} HP left)", target.name, attacker_name, target_hp));
+            }
+        }
+        
+        // Everyone who came through it gains experience
+        for id in attackers.iter().map(|id| id.as_str()).chain([target_ship_id]) {
+            self.survived_battle(id);
+        }
+        
+        // Then anyone whose orders say to get out does
+        for id in attackers.iter().map(|id| id.as_str()).chain([target_ship_id]) {
+            self.consider_withdrawal(id);
+        }
+        
+        // The players involved get the report however they started the fight; spectators
+        // get a copy of every one
+        self.events.push(GameEvent {
+            player_id: SPECTATORS.to_string(),
+            message: format!("{} attacked {} ({}):\n{}", attacker_name, target.name, self.owner_name(&target.owner), report),
+        });
+        
+        report
+    }
+    
+    // Remove a ship from the game and every place that references it
+    pub fn destroy_ship(&mut self, ship_id: &str) {
+        let ship = match self.ships.remove(ship_id) {
+            Some(s) => s,
+            None => return,
+        };
+        
+        for sector in self.sectors.iter_mut() {
+            sector.ships_present.retain(|id| id != ship_id);
+            if sector.garrison_ship.as_deref() == Some(ship_id) {
+                sector.garrison_ship = None;
+            }
+        }
+        
+        if let Some(owner) = self.players.get_mut(&ship.owner) {
+            owner.owned_ships.retain(|id| id != ship_id);
+            owner.command_ships.retain(|id| id != ship_id);
+        }
+    }
+    
+    // Where a newcomer starts: the map's first free start slot, otherwise the empty neutral
+    // sector furthest from every existing ship
+    pub fn open_home_sector(&self) -> Option<String> {
+        let free_slot = self.start_slots.iter()
+            .filter_map(|id| self.sectors.iter().find(|s| &s.id == id))
+            .find(|s| s.controlled_by.is_none() && s.ships_present.is_empty());
+        if let Some(slot) = free_slot {
+            return Some(slot.id.clone());
+        }
+        
+        let occupied: Vec<&str> = self.ships.values().map(|s| s.current_sector.as_str()).collect();
+        self.sectors.iter()
+            .filter(|s| s.controlled_by.is_none() && s.ships_present.is_empty())
+            .max_by_key(|s| {
+                occupied.iter()
+                    .filter_map(|o| self.sector_distance(o, &s.id))
+                    .min()
+                    .unwrap_or(i32::MAX)
+            })
+            .map(|s| s.id.clone())
+    }
+    
+    // Next free "ship-N" ID
+    pub fn next_ship_id(&self) -> String {
+        let highest = self.ships.keys()
+            .filter_map(|id| id.strip_prefix("ship-")?.parse::<u32>().ok())
+            .max()
+            .unwrap_or(0);
+        format!("ship-{}", highest + 1)
+    }
+    
+    // Build a fresh ship for a player and place it in a sector
+    pub fn spawn_ship(&mut self, owner_id: &str, ship_type: ShipType, sector_id: &str, name: &str) -> Option<String> {
+        let ship_id = self.next_ship_id();
+        let sector = self.sectors.iter_mut().find(|s| s.id == sector_id)?;
+        
+        sector.ships_present.push(ship_id.clone());
+        if let Some(player) = self.players.get_mut(owner_id) {
+            player.owned_ships.push(ship_id.clone());
+            if ship_type.has_ability(Ability::ClaimSector) {
+                player.command_ships.push(ship_id.clone());
+            }
+        }
+        self.ships.insert(ship_id.clone(), Ship::new(&ship_id, name, ship_type, owner_id, sector_id));
+        
+        Some(ship_id)
+    }
+    
+    // Hand a ship to another player, keeping owner lists and garrisons consistent
+    pub fn transfer_ship(&mut self, ship_id: &str, new_owner: &str) {
+        let ship = match self.ships.get_mut(ship_id) {
+            Some(s) => s,
+            None => return,
+        };
+        let old_owner = std::mem::replace(&mut ship.owner, new_owner.to_string());
+        let is_command_ship = ship.ship_type.has_ability(Ability::ClaimSector);
+        
+        if let Some(player) = self.players.get_mut(&old_owner) {
+            player.owned_ships.retain(|id| id != ship_id);
+            player.command_ships.retain(|id| id != ship_id);
+        }
+        if let Some(player) = self.players.get_mut(new_owner) {
+            player.owned_ships.push(ship_id.to_string());
+            if is_command_ship {
+                player.command_ships.push(ship_id.to_string());
+            }
+        }
+        
+        // A garrison can't hold a sector for someone else
+        for sector in self.sectors.iter_mut() {
+            if sector.garrison_ship.as_deref() == Some(ship_id) {
+                sector.garrison_ship = None;
+            }
+        }
+    }
+    
+    fn set_cloak(&mut self, player_id: &str, ship_id: &str, enable: bool) -> CommandResult {
+        let ship = match self.ships.get(ship_id) {
+            Some(s) if s.owner == player_id => s.clone(),
+            Some(_) => return CommandResult {
+                success: false,
+                message: "That's not your ship!".to_string(),
+                ap_spent: 0,
+                game_state: self.clone(),
+            },
+            None => return CommandResult {
+                success: false,
+                message: "Ship not found".to_string(),
+                ap_spent: 0,
+                game_state: self.clone(),
+            }
+        };
+        
+        if !enable {
+            self.ships.get_mut(ship_id).unwrap().cloaked = false;
+            return CommandResult {
+                success: true,
+                message: format!("{} decloaked", ship.name),
+                ap_spent: 0,
+                game_state: self.clone(),
+            };
+        }
+        
+        let (_, _, cloak_cost) = ship.ship_type.get_stealth_stats();
+        if !ship.ship_type.has_ability(Ability::Cloak) {
+            return CommandResult {
+                success: false,
+                message: format!("{:?} hulls have no cloaking device", ship.ship_type),
+                ap_spent: 0,
+                game_state: self.clone(),
+            };
+        }
+        if ship.cloaked {
+            return CommandResult {
+                success: false,
+                message: format!("{} is already cloaked", ship.name),
+                ap_spent: 0,
+                game_state: self.clone(),
+            };
+        }
+        
+        // The first cycle of upkeep is paid up front
+        let player = self.players.get_mut(player_id).unwrap();
+        if player.current_ap < cloak_cost {
+            return CommandResult {
+                success: false,
+                message: format!("Not enough AP. Need {} but have {}", cloak_cost, player.current_ap),
+                ap_spent: 0,
+                game_state: self.clone(),
+            };
+        }
+        player.current_ap -= cloak_cost;
+        self.ships.get_mut(ship_id).unwrap().cloaked = true;
+        
+        CommandResult {
+            success: true,
+            message: format!("{} cloaked ({} AP per cycle)", ship.name, cloak_cost),
+            ap_spent: cloak_cost,
+            game_state: self.clone(),
+        }
+    }
+    
     // Process 8-hour cycle
     pub fn process_cycle(&mut self) {
         self.cycle_number += 1;
         self.last_cycle = Utc::now();
         
-        // Add AP to all players
-        for (_, player) in self.players.iter_mut() {
-            player.current_ap = (player.current_ap + 50).min(player.max_ap);
+        // The cycle boundary is also a transit tick
+        self.process_transit();
+        
+        // Queued orders run on the AP that was set aside for them, before the refill
+        self.execute_due_orders();
+        
+        // Income and upkeep come first, since debt cuts the AP refill
+        self.process_upkeep();
+        
+        // Add AP to all players. AP reserved for later orders counts toward the cap, so it
+        // can't be refilled and then refunded on top
+        let reserved: HashMap<String, i32> = self.players.keys()
+            .map(|id| (id.clone(), self.reserved_ap(id)))
+            .collect();
+        for (player_id, player) in self.players.iter_mut() {
+            let cap = (player.max_ap - reserved[player_id]).max(player.current_ap);
+            player.current_ap = (player.current_ap + player.ap_refill()).min(cap);
+        }
+        
+        self.pay_cloak_upkeep();
+        self.expire_treaties();
+        self.expire_trades();
+        self.process_engagements();
+        self.process_terrain();
+        self.process_supply();
+        self.process_morale();
+        self.process_pirates();
+        self.scout_passive_reveal();
+        self.update_sector_holds();
+        self.process_market();
+        self.record_scores();
+        self.check_victory();
+        
+        // Players who are away hear about it through their notifications
+        let mut player_ids: Vec<String> = self.players.keys().cloned().collect();
+        player_ids.sort();
+        for player_id in player_ids {
+            let player = &self.players[&player_id];
+            let message = format!(
+                "Cycle {} complete: you have {}/{} AP and {} credits",
+                self.cycle_number, player.current_ap, player.max_ap, player.credits
+            );
+            self.alert(&player_id, NotificationKind::CycleComplete, message);
         }
     }
+    
+    // Cloaked ships drain their owner's AP every cycle and drop cloak when it runs out
+    fn pay_cloak_upkeep(&mut self) {
+        let mut cloaked: Vec<String> = self.ships.values()
+            .filter(|s| s.cloaked)
+            .map(|s| s.id.clone())
+            .collect();
+        cloaked.sort();
+        
+        for ship_id in cloaked {
+            let ship = self.ships.get_mut(&ship_id).unwrap();
+            let (_, _, cloak_cost) = ship.ship_type.get_stealth_stats();
+            let player = match self.players.get_mut(&ship.owner) {
+                Some(p) => p,
+                None => continue,
+            };
+            
+            if player.current_ap >= cloak_cost {
+                player.current_ap -= cloak_cost;
+            } else {
+                ship.cloaked = false;
+                self.events.push(GameEvent {
+                    player_id: ship.owner.clone(),
+                    message: format!("{} lost its cloak: not enough AP for upkeep", ship.name),
+                });
+            }
+        }
+    }
+    
+    // Passive scanners (scout drones) report the contents of adjacent sectors to their owner every cycle
+    fn scout_passive_reveal(&mut self) {
+        let mut reports: HashMap<String, Vec<String>> = HashMap::new();
+        
+        for ship in self.ships.values().filter(|s| s.ship_type.has_ability(Ability::PassiveScan) && !s.in_transit()) {
+            let (_, _, accuracy) = ship.ship_type.get_sensor_stats();
+            let (_, detection, _) = ship.ship_type.get_stealth_stats();
+            let mut lines = Vec::new();
+            
+            for sector in &self.sectors {
+                if self.sector_distance(&ship.current_sector, &sector.id) != Some(1)
+                    || self.scan_blocked(&ship.current_sector, sector) {
+                    continue;
+                }
+                let contacts: Vec<String> = self.detected_ships(sector, &ship.owner, detection).into_iter()
+                    .filter(|s| s.owner != ship.owner && !self.is_allied(&ship.owner, &s.owner))
+                    .map(|s| format!("{} ({:?}) HP: {}", s.name, s.ship_type, s.estimate_hp(accuracy)))
+                    .collect();
+                if contacts.is_empty() {
+                    lines.push(format!("  {}: clear", sector.name));
+                } else {
+                    lines.push(format!("  {}: {}", sector.name, contacts.join(", ")));
+                }
+            }
+            
+            // Allies share the sweep
+            if !lines.is_empty() {
+                let sweep = format!("{} recon sweep:\n{}", ship.name, lines.join("\n"));
+                for recipient in std::iter::once(ship.owner.clone()).chain(self.allies_of(&ship.owner)) {
+                    reports.entry(recipient).or_default().push(sweep.clone());
+                }
+            }
+        }
+        
+        for (player_id, sweeps) in reports {
+            self.events.push(GameEvent {
+                player_id,
+                message: format!("=== Scout Reports ===\n{}", sweeps.join("\n")),
+            });
+        }
+    }
+    
+    pub fn take_events(&mut self) -> Vec<GameEvent> {
+        std::mem::take(&mut self.events)
+    }
 }
 
+impl Player {
+    // Fresh commander with the standard starting AP and credits
+    pub fn new(id: &str, name: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            name: name.to_string(),
+            current_ap: 50,
+            max_ap: 100,
+            credits: 100,
+            level: 1,
+            xp: 0,
+            reputation: 0,
+            owned_ships: vec![],
+            command_ships: vec![],
+            garrison_slots: 0,
+            resources: HashMap::new(),
+            kills: 0,
+            debt_cycles: 0,
+            notifications: NotificationPrefs::default(),
+            inbox: Vec::new(),
+            push_subscriptions: Vec::new(),
+        }
+    }
+}
+
+impl Resource {
+    pub fn parse(name: &str) -> Option<Resource> {
+        match name.to_lowercase().as_str() {
+            "ore" => Some(Resource::Ore),
+            "fuel" => Some(Resource::Fuel),
+            "components" => Some(Resource::Components),
+            _ => None,
+        }
+    }
+}
+
+// Stats come from the ship catalog (config/ships.json), see ships.rs
 impl ShipType {
     pub fn get_stats(&self) -> (i32, i32, i32) {
         // Returns (max_hp, damage, ap_cost)
-        match self {
-            // Tier 1
-            ShipType::ScoutDrone => (10, 2, 1),
-            ShipType::MiningVessel => (15, 1, 2),
-            ShipType::Interceptor => (20, 5, 1),
-            // Tier 2
-            ShipType::Corvette => (50, 12, 3),
-            ShipType::Frigate => (80, 15, 4),
-            ShipType::SupplyShip => (40, 5, 5),
-            // Tier 3
-            ShipType::Destroyer => (150, 30, 6),
-            ShipType::GarrisonShip => (200, 20, 5),
-            ShipType::Cruiser => (250, 40, 8),
-            // Tier 4
-            ShipType::Battleship => (500, 80, 12),
-            ShipType::CommandShip => (750, 100, 15),
-            ShipType::Carrier => (400, 20, 14),
-        }
-    }
-} 
\ No newline at end of file
+        let class = ship_class(self);
+        (class.hp, class.damage, class.ap_cost)
+    }
+    
+    pub fn get_sensor_stats(&self) -> (i32, i32, i32) {
+        // Returns (sensor_range, scan_cost, hp_accuracy %)
+        let class = ship_class(self);
+        (class.sensor_range, class.scan_cost, class.hp_accuracy)
+    }
+    
+    pub fn get_stealth_stats(&self) -> (i32, i32, i32) {
+        // Returns (stealth, detection, cloak_ap_cost per cycle); stealth 0 means no cloak
+        let class = ship_class(self);
+        (class.stealth, class.detection, class.cloak_ap_cost)
+    }
+}
+
+impl Ship {
+    // Fresh from the yard: full HP, fuel and morale, class stats from the catalog
+    pub fn new(id: &str, name: &str, ship_type: ShipType, owner_id: &str, sector_id: &str) -> Self {
+        let (hp, damage, ap_cost) = ship_type.get_stats();
+        Self {
+            id: id.to_string(),
+            name: name.to_string(),
+            ship_type,
+            owner: owner_id.to_string(),
+            current_sector: sector_id.to_string(),
+            hp,
+            max_hp: hp,
+            damage,
+            ap_cost,
+            cloaked: false,
+            transit: None,
+            fuel_used: 0,
+            battles: 0,
+            morale: MAX_MORALE,
+            stance: Stance::default(),
+            roe: RulesOfEngagement::default(),
+        }
+    }
+    
+    // Cloaked ships are only seen by their owner or a detector that beats their stealth
+    pub fn is_visible_to(&self, viewer_id: &str, detection: i32) -> bool {
+        !self.cloaked || self.owner == viewer_id || detection > self.ship_type.get_stealth_stats().0
+    }
+    
+    // HP as seen by a scanner: exact at 100% accuracy, otherwise a bracket
+    // whose width grows as accuracy drops
+    pub fn estimate_hp(&self, accuracy: i32) -> String {
+        let bracket = self.max_hp * (100 - accuracy.clamp(0, 100)) / 100;
+        if bracket <= 1 {
+            return format!("{}/{}", self.hp, self.max_hp);
+        }
+        let low = self.hp / bracket * bracket;
+        let high = (low + bracket).min(self.max_hp);
+        format!("~{}-{}/{}", low, high, self.max_hp)
+    }
+} 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn two_player_game() -> GameState {
+        let mut state = GameState::new();
+        state.players.insert("p1".to_string(), Player::new("p1", "Alpha"));
+        state.players.insert("p2".to_string(), Player::new("p2", "Beta"));
+        state
+    }
+    
+    #[test]
+    fn scan_needs_a_ship_in_sensor_range() {
+        let mut state = two_player_game();
+        state.spawn_ship("p1", ShipType::Frigate, "earth-1", "Pioneer");
+        
+        let result = state.execute_command("p1", Command::Scan { sector_id: "earth-16".to_string(), scanner_id: None });
+        assert!(!result.success);
+        assert_eq!(result.ap_spent, 0);
+    }
+    
+    #[test]
+    fn scout_drones_scan_further_and_cheaper() {
+        let mut state = two_player_game();
+        state.spawn_ship("p1", ShipType::ScoutDrone, "earth-1", "Eye");
+        state.spawn_ship("p2", ShipType::Frigate, "earth-7", "Target");
+        
+        let result = state.execute_command("p1", Command::Scan { sector_id: "earth-7".to_string(), scanner_id: None });
+        assert!(result.success, "{}", result.message);
+        assert_eq!(result.ap_spent, 1);
+        assert!(result.message.contains("Target"));
+    }
+    
+    #[test]
+    fn ships_in_transit_cannot_scan() {
+        let mut state = two_player_game();
+        let ship_id = state.spawn_ship("p1", ShipType::Frigate, "earth-1", "Pioneer").unwrap();
+        // Keeps the game going after the first command
+        state.spawn_ship("p2", ShipType::Frigate, "earth-16", "Voyager");
+        let moved = state.execute_command("p1", Command::Move { ship_id: ship_id.clone(), sector_id: "earth-4".to_string() });
+        assert!(moved.success, "{}", moved.message);
+        assert!(state.ships[&ship_id].in_transit());
+        
+        let picked = state.execute_command("p1", Command::Scan { sector_id: "earth-2".to_string(), scanner_id: None });
+        assert!(!picked.success, "{}", picked.message);
+        let explicit = state.execute_command("p1", Command::Scan { sector_id: "earth-2".to_string(), scanner_id: Some(ship_id) });
+        assert!(!explicit.success);
+        assert!(explicit.message.contains("under way"), "{}", explicit.message);
+        assert_eq!(explicit.ap_spent, 0);
+    }
+}
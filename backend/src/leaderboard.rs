@@ -0,0 +1,157 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::game::{GameState, CommandResult};
+
+const HISTORY_CYCLES: usize = 100; // Snapshots kept for trends
+
+// One cycle's scores, recorded at the end of process_cycle
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ScoreSnapshot {
+    pub cycle: u32,
+    pub scores: HashMap<String, i64>, // Player ID -> score
+}
+
+// Aggregates only: no ship lists or sector IDs, so fog of war holds. No player ID either,
+// since that's what a client logs in with
+#[derive(Serialize, Clone)]
+pub struct ScoreCard {
+    pub name: String,
+    pub score: i64,
+    pub sectors: usize,
+    pub fleet_value: i64,
+    pub kills: u32,
+    pub credits: i32,
+    pub level: u32,
+    pub trend: i64, // Change since the previous cycle
+    pub history: Vec<(u32, i64)>, // (cycle, score), oldest first
+}
+
+impl GameState {
+    // Territory, fleet, kills, wealth and experience rolled into one number
+    pub fn score(&self, player_id: &str) -> i64 {
+        match self.score_card(player_id) {
+            Some(card) => card.score,
+            None => 0,
+        }
+    }
+    
+    fn score_card(&self, player_id: &str) -> Option<ScoreCard> {
+        let player = self.players.get(player_id)?;
+        
+        let sectors = self.sectors.iter()
+            .filter(|s| s.controlled_by.as_deref() == Some(player_id))
+            .count();
+        // Valued by class, not current HP, so damaged ships don't tank the score
+        let fleet_value: i64 = player.owned_ships.iter()
+            .filter_map(|id| self.ships.get(id))
+            .map(|s| {
+                let (max_hp, damage, _) = s.ship_type.get_stats();
+                (max_hp + damage * 5) as i64
+            })
+            .sum();
+        
+        let score = sectors as i64 * 100
+            + fleet_value
+            + player.kills as i64 * 50
+            + player.credits as i64
+            + player.level as i64 * 50;
+        
+        let history: Vec<(u32, i64)> = self.score_history.iter()
+            .filter_map(|snap| snap.scores.get(player_id).map(|s| (snap.cycle, *s)))
+            .collect();
+        let trend = match history.last() {
+            Some((_, last)) => score - last,
+            None => 0,
+        };
+        
+        Some(ScoreCard {
+            name: player.name.clone(),
+            score,
+            sectors,
+            fleet_value,
+            kills: player.kills,
+            credits: player.credits,
+            level: player.level,
+            trend,
+            history,
+        })
+    }
+    
+    pub fn leaderboard(&self) -> Vec<ScoreCard> {
+        self.ranked_cards().into_iter().map(|(_, card)| card).collect()
+    }
+    
+    // Best first, each card alongside the player it belongs to
+    fn ranked_cards(&self) -> Vec<(&str, ScoreCard)> {
+        let mut cards: Vec<(&str, ScoreCard)> = self.players.keys()
+            .filter_map(|id| Some((id.as_str(), self.score_card(id)?)))
+            .collect();
+        cards.sort_by(|a, b| b.1.score.cmp(&a.1.score).then(a.1.name.cmp(&b.1.name)));
+        cards
+    }
+    
+    pub fn leaderboard_report(&self, player_id: &str) -> CommandResult {
+        let mut report = "=== Leaderboard ===\n".to_string();
+        
+        for (rank, (id, card)) in self.ranked_cards().iter().enumerate() {
+            let marker = if *id == player_id { " <- you" } else { "" };
+            report.push_str(&format!(
+                "{}. {} - {} ({:+}) | {} sectors, fleet {}, {} kills, level {}{}\n",
+                rank + 1, card.name, card.score, card.trend, card.sectors, card.fleet_value, card.kills, card.level, marker
+            ));
+        }
+        
+        CommandResult {
+            success: true,
+            message: report,
+            ap_spent: 0,
+            game_state: self.clone(),
+        }
+    }
+    
+    pub fn record_kill(&mut self, killer_id: &str) {
+        if let Some(player) = self.players.get_mut(killer_id) {
+            player.kills += 1;
+        }
+    }
+    
+    // Called once per cycle
+    pub fn record_scores(&mut self) {
+        let scores = self.players.keys()
+            .map(|id| (id.clone(), self.score(id)))
+            .collect();
+        self.score_history.push(ScoreSnapshot {
+            cycle: self.cycle_number,
+            scores,
+        });
+        
+        if self.score_history.len() > HISTORY_CYCLES {
+            let excess = self.score_history.len() - HISTORY_CYCLES;
+            self.score_history.drain(..excess);
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{GameState, Player, ShipType};
+    
+    #[test]
+    fn public_leaderboard_has_no_player_ids() {
+        let mut state = GameState::new();
+        state.players.insert("secret-alpha".to_string(), Player::new("secret-alpha", "Alpha"));
+        state.players.insert("secret-beta".to_string(), Player::new("secret-beta", "Beta"));
+        state.spawn_ship("secret-alpha", ShipType::Frigate, "earth-1", "Pioneer");
+        
+        let cards = state.leaderboard();
+        assert_eq!(cards[0].name, "Alpha");
+        assert!(!serde_json::to_string(&cards).unwrap().contains("secret"));
+        
+        let report = state.leaderboard_report("secret-beta").message;
+        let marked: Vec<&str> = report.lines().filter(|l| l.ends_with("<- you")).collect();
+        assert_eq!(marked.len(), 1);
+        assert!(marked[0].starts_with("2. Beta"));
+    }
+}
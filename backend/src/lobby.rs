@@ -0,0 +1,171 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::bots;
+use crate::game::{GameState, GameSettings};
+use crate::websocket::GameServer;
+use crate::simultaneous::TurnMode;
+
+const GAMES_DIR: &str = "../data/games";
+const LEGACY_SAVE: &str = "../data/game_state.json"; // Single-game saves from before lobbies
+pub const DEFAULT_GAME_ID: &str = "main";
+pub const MAX_GAMES: usize = 20; // Each game runs its own cycle task and save file
+
+// Every match hosted on this server, keyed by game ID
+pub struct GameRegistry {
+    games: RwLock<HashMap<String, Arc<GameServer>>>,
+}
+
+// What the lobby shows for one game
+#[derive(Serialize)]
+pub struct GameSummary {
+    pub id: String,
+    pub name: String,
+    pub players: usize,
+    pub season: u32,
+    pub cycle_number: u32,
+    pub map_size: i32,
+    pub cycle_secs: u64,
+    pub turn_mode: TurnMode,
+    pub finished: bool,
+}
+
+pub fn save_path(game_id: &str) -> String {
+    format!("{}/{}.json", GAMES_DIR, game_id)
+}
+
+impl GameRegistry {
+    pub fn new() -> Self {
+        Self {
+            games: RwLock::new(HashMap::new()),
+        }
+    }
+    
+    pub async fn get(&self, game_id: &str) -> Option<Arc<GameServer>> {
+        self.games.read().await.get(game_id).cloned()
+    }
+    
+    pub async fn all(&self) -> Vec<Arc<GameServer>> {
+        self.games.read().await.values().cloned().collect()
+    }
+    
+    pub async fn list(&self) -> Vec<GameSummary> {
+        let servers: Vec<Arc<GameServer>> = self.games.read().await.values().cloned().collect();
+        
+        let mut summaries = Vec::new();
+        for server in servers {
+            let state = server.game_state.read().await;
+            summaries.push(GameSummary {
+                id: server.game_id.clone(),
+                name: state.settings.name.clone(),
+                players: state.players.len(),
+                season: state.season,
+                cycle_number: state.cycle_number,
+                map_size: state.settings.map_size,
+                cycle_secs: state.settings.cycle_secs,
+                turn_mode: state.settings.turn_mode,
+                finished: state.outcome.is_some(),
+            });
+        }
+        summaries.sort_by(|a, b| a.name.cmp(&b.name).then(a.id.cmp(&b.id)));
+        summaries
+    }
+    
+    // Registers a game and starts its cycle task and bots
+    pub async fn host(&self, game_id: &str, game_state: GameState) -> Arc<GameServer> {
+        let game_server = Arc::new(GameServer::new(game_id, game_state));
+        self.games.write().await.insert(game_id.to_string(), game_server.clone());
+        
+        let cycle_server = game_server.clone();
+        tokio::spawn(async move {
+            GameServer::run_cycle_task(cycle_server).await;
+        });
+        
+        let bot_ids: Vec<String> = game_server.game_state.read().await.bots.iter()
+            .map(|b| b.player_id.clone())
+            .collect();
+        for bot_id in bot_ids {
+            tokio::spawn(bots::run_bot(game_server.clone(), bot_id));
+        }
+        
+        game_server
+    }
+    
+    pub async fn create(&self, settings: GameSettings) -> Result<Arc<GameServer>, String> {
+        if self.games.read().await.len() >= MAX_GAMES {
+            return Err(format!("This server already hosts the maximum of {} games", MAX_GAMES));
+        }
+        let game_id = Uuid::new_v4().simple().to_string()[..8].to_string();
+        let game_server = self.host(&game_id, GameState::with_settings(settings)).await;
+        
+        let game_state = game_server.game_state.read().await;
+        game_server.save(&game_state).await;
+        drop(game_state);
+        
+        Ok(game_server)
+    }
+    
+    // Resumes every saved game. Returns how many were loaded
+    pub async fn load_saved(&self) -> usize {
+        let mut entries = match tokio::fs::read_dir(GAMES_DIR).await {
+            Ok(e) => e,
+            Err(_) => return 0,
+        };
+        
+        let mut loaded = 0;
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let path = entry.path();
+            let game_id = match (path.extension(), path.file_stem()) {
+                (Some(ext), Some(stem)) if ext == "json" => stem.to_string_lossy().to_string(),
+                _ => continue,
+            };
+            
+            match load_state(&path.to_string_lossy()).await {
+                Ok(mut state) => {
+                    state.apply_ship_catalog();
+                    println!("✅ Loaded game {}", game_id);
+                    self.host(&game_id, state).await;
+                    loaded += 1;
+                }
+                Err(e) => println!("⚠️  Failed to load game {}: {}", game_id, e),
+            }
+        }
+        
+        loaded
+    }
+}
+
+// Older servers kept their only game here; it becomes the default game
+pub async fn load_legacy_state() -> Option<GameState> {
+    load_state(LEGACY_SAVE).await.ok()
+}
+
+async fn load_state(path: &str) -> Result<GameState, String> {
+    let json = tokio::fs::read_to_string(path).await.map_err(|e| e.to_string())?;
+    let mut state: GameState = serde_json::from_str(&json).map_err(|e| e.to_string())?;
+    state.upgrade_map();
+    Ok(state)
+}
+
+impl GameServer {
+    pub async fn save(&self, state: &GameState) {
+        let json = match serde_json::to_string_pretty(state) {
+            Ok(json) => json,
+            Err(e) => {
+                eprintln!("Failed to serialize game {}: {}", self.game_id, e);
+                return;
+            }
+        };
+        
+        if let Err(e) = tokio::fs::create_dir_all(GAMES_DIR).await {
+            eprintln!("Failed to create {}: {}", GAMES_DIR, e);
+            return;
+        }
+        if let Err(e) = tokio::fs::write(save_path(&self.game_id), json).await {
+            eprintln!("Failed to save game {}: {}", self.game_id, e);
+        }
+    }
+}
@@ -1,24 +1,53 @@
+mod admin;
+mod bots;
+mod chat;
+mod crew;
+mod diplomacy;
+mod engagement;
 mod game;
+mod leaderboard;
+mod lobby;
+mod maps;
+mod market;
+mod notifications;
+mod orders;
+mod pirates;
+mod ships;
+mod simultaneous;
+mod spectator;
+mod supply;
+mod terrain;
+mod trade;
+mod transit;
+mod upkeep;
+mod victory;
+mod webpush;
 mod websocket;
 
 use axum::{
     Router,
     routing::{get, post},
-    response::{Html, Json},
-    extract::{ws::WebSocketUpgrade, Path, State},
+    response::{Json, IntoResponse},
+    extract::{ws::WebSocketUpgrade, Path, Query, State},
     http::StatusCode,
 };
 use tower_http::{
     services::ServeDir,
     cors::{CorsLayer, Any},
 };
-use std::{sync::Arc, path::PathBuf, net::SocketAddr};
+use std::{sync::Arc, net::SocketAddr};
 use serde::{Deserialize, Serialize};
-use tracing_subscriber;
 use tokio::net::TcpListener;
 
-use game::{GameState, Player, Ship, ShipType};
-use websocket::GameServer;
+use game::{GameState, GameSettings, Player, ShipType};
+use pirates::PIRATE_PLAYER_ID;
+use lobby::{GameRegistry, GameSummary, DEFAULT_GAME_ID};
+use leaderboard::ScoreCard;
+use maps::MapSource;
+use market::MarketReport;
+use ships::ShipStatsPolicy;
+use simultaneous::TurnMode;
+use spectator::MIN_SPECTATOR_DELAY_SECS;
 use uuid::Uuid;
 
 #[derive(Deserialize)]
@@ -32,183 +61,253 @@ struct RegisterResponse {
     message: String,
 }
 
+#[derive(Deserialize)]
+struct CreateGameRequest {
+    name: String,
+    map_size: Option<i32>, // Defaults to 4, clamped to 3-8
+    cycle_secs: Option<u64>, // Defaults to 8 hours, at least a minute
+    spectator_delay_secs: Option<u64>, // At least a minute, which is the default
+    ship_stats_policy: Option<ShipStatsPolicy>, // Defaults to snapshot
+    map: Option<MapSource>, // Defaults to the standard grid
+    turn_mode: Option<TurnMode>, // Defaults to real time
+}
+
+#[derive(Deserialize)]
+struct LeaderboardQuery {
+    game_id: Option<String>, // Defaults to the main game
+}
+
+#[derive(Serialize)]
+struct CreateGameResponse {
+    game: GameSummary,
+    message: String,
+}
+
 #[tokio::main]
 async fn main() {
     // Initialize logging
     tracing_subscriber::fmt::init();
     
-    // Load or create game state
-    let game_state = load_or_create_game_state().await;
-    let game_server = Arc::new(GameServer::new(game_state));
+    // Ship definitions must be valid before any game loads
+    match ships::load_ship_catalog().await {
+        Ok(count) => println!("✅ Loaded {} ship classes from {}", count, ships::SHIPS_FILE),
+        Err(e) if !std::path::Path::new(ships::SHIPS_FILE).exists() => {
+            println!("⚠️  {}; using built-in ship classes", e);
+        }
+        Err(e) => {
+            eprintln!("❌ Invalid ship definitions: {}", e);
+            std::process::exit(1);
+        }
+    }
+    
+    // Push services need to know who's sending
+    match webpush::load_vapid_keys().await {
+        Ok(true) => println!("🔑 Generated a new VAPID key pair in {}", webpush::VAPID_FILE),
+        Ok(false) => println!("✅ Loaded VAPID keys for Web Push"),
+        Err(e) => println!("⚠️  {}; Web Push is disabled", e),
+    }
     
-    // Start cycle processing task
-    let cycle_server = game_server.clone();
-    tokio::spawn(async move {
-        websocket::GameServer::run_cycle_task(cycle_server).await;
-    });
+    // Resume saved games, each with its own cycle task and bots
+    let registry = Arc::new(GameRegistry::new());
+    let loaded = registry.load_saved().await;
+    println!("✅ Loaded {} saved game(s)", loaded);
+    
+    // There's always a default game to join
+    if registry.get(DEFAULT_GAME_ID).await.is_none() {
+        let game_state = match lobby::load_legacy_state().await {
+            Some(state) => {
+                println!("✅ Migrated existing game state to game '{}'", DEFAULT_GAME_ID);
+                state
+            }
+            None => {
+                println!("📝 Creating new game state");
+                create_new_game_state()
+            }
+        };
+        let game_server = registry.host(DEFAULT_GAME_ID, game_state).await;
+        let game_state = game_server.game_state.read().await;
+        game_server.save(&game_state).await;
+    }
     
     // Set up router
     let app = Router::new()
         // API routes
-        .route("/api/register", post(register_player))
-        .route("/ws/:player_id", get(websocket_handler))
+        .route("/api/games", get(list_games).post(create_game))
+        .route("/api/games/:game_id/join", post(register_player))
+        .route("/api/games/:game_id/market", get(get_market))
+        .route("/api/leaderboard", get(get_leaderboard))
+        .route("/ws/:game_id/spectate", get(spectate_handler))
+        .route("/ws/:game_id/:player_id", get(websocket_handler))
+        .nest("/api/admin", admin::router())
+        .merge(webpush::router())
         // Serve frontend files
         .fallback_service(ServeDir::new("../frontend"))
         .layer(CorsLayer::new().allow_origin(Any).allow_methods(Any).allow_headers(Any))
-        .with_state(game_server);
+        .with_state(registry);
     
     let addr = SocketAddr::from(([0, 0, 0, 0], 8080));
     println!("🚀 Interstellar Command server running at http://{}", addr);
-    println!("📡 WebSocket endpoint: ws://{}/ws/<player_id>", addr);
+    println!("📡 WebSocket endpoint: ws://{}/ws/<game_id>/<player_id>", addr);
     
     let listener = TcpListener::bind(addr).await.unwrap();
     axum::serve(listener, app).await.unwrap();
 }
 
-async fn load_or_create_game_state() -> GameState {
-    // Try to load from file
-    match tokio::fs::read_to_string("../data/game_state.json").await {
-        Ok(json) => {
-            match serde_json::from_str(&json) {
-                Ok(state) => {
-                    println!("✅ Loaded existing game state");
-                    state
-                },
-                Err(e) => {
-                    println!("⚠️  Failed to parse game state: {}", e);
-                    create_new_game_state()
-                }
-            }
-        },
-        Err(_) => {
-            println!("📝 Creating new game state");
-            create_new_game_state()
+fn create_new_game_state() -> GameState {
+    let mut state = GameState::new();
+    
+    // Create two test players for development, each with a starting frigate in one of the
+    // map's start sectors
+    for (player_id, name, ship_name) in [
+        ("test-player-1", "Commander Alpha", "Pioneer"),
+        ("test-player-2", "Commander Beta", "Voyager"),
+    ] {
+        state.players.insert(player_id.to_string(), Player::new(player_id, name));
+        if let Some(home) = state.open_home_sector() {
+            state.spawn_ship(player_id, ShipType::Frigate, &home, ship_name);
         }
     }
+    
+    state
 }
 
-fn create_new_game_state() -> GameState {
-    let mut state = GameState::new();
+async fn list_games(
+    State(registry): State<Arc<GameRegistry>>,
+) -> Json<Vec<GameSummary>> {
+    Json(registry.list().await)
+}
+
+async fn create_game(
+    State(registry): State<Arc<GameRegistry>>,
+    Json(req): Json<CreateGameRequest>,
+) -> Result<Json<CreateGameResponse>, (StatusCode, String)> {
+    let name = req.name.trim();
+    if name.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "A game needs a name".to_string()));
+    }
     
-    // Create two test players for development
-    let player1_id = "test-player-1";
-    let player2_id = "test-player-2";
-    
-    // Player 1
-    state.players.insert(player1_id.to_string(), Player {
-        id: player1_id.to_string(),
-        name: "Commander Alpha".to_string(),
-        current_ap: 50,
-        max_ap: 100,
-        credits: 100,
-        level: 1,
-        xp: 0,
-        reputation: 0,
-        owned_ships: vec!["ship-1".to_string()],
-        command_ships: vec![],
-        garrison_slots: 0,
-    });
-    
-    // Player 1's starting frigate
-    let (hp, damage, ap_cost) = ShipType::Frigate.get_stats();
-    state.ships.insert("ship-1".to_string(), Ship {
-        id: "ship-1".to_string(),
-        name: "Pioneer".to_string(),
-        ship_type: ShipType::Frigate,
-        owner: player1_id.to_string(),
-        current_sector: "earth-1".to_string(),
-        hp,
-        max_hp: hp,
-        damage,
-        ap_cost,
-    });
-    
-    // Player 2
-    state.players.insert(player2_id.to_string(), Player {
-        id: player2_id.to_string(),
-        name: "Commander Beta".to_string(),
-        current_ap: 50,
-        max_ap: 100,
-        credits: 100,
-        level: 1,
-        xp: 0,
-        reputation: 0,
-        owned_ships: vec!["ship-2".to_string()],
-        command_ships: vec![],
-        garrison_slots: 0,
-    });
-    
-    // Player 2's starting frigate
-    state.ships.insert("ship-2".to_string(), Ship {
-        id: "ship-2".to_string(),
-        name: "Voyager".to_string(),
-        ship_type: ShipType::Frigate,
-        owner: player2_id.to_string(),
-        current_sector: "earth-16".to_string(),
-        hp,
-        max_hp: hp,
-        damage,
-        ap_cost,
-    });
-    
-    // Add ships to their sectors
-    state.sectors[0].ships_present.push("ship-1".to_string());
-    state.sectors[15].ships_present.push("ship-2".to_string());
+    let defaults = GameSettings::default();
+    let settings = GameSettings {
+        name: name.to_string(),
+        map_size: req.map_size.unwrap_or(defaults.map_size).clamp(3, 8),
+        cycle_secs: req.cycle_secs.unwrap_or(defaults.cycle_secs).max(60),
+        spectator_delay_secs: req.spectator_delay_secs.unwrap_or(defaults.spectator_delay_secs).max(MIN_SPECTATOR_DELAY_SECS),
+        ship_stats_policy: req.ship_stats_policy.unwrap_or(defaults.ship_stats_policy),
+        map: req.map.unwrap_or(defaults.map),
+        turn_mode: req.turn_mode.unwrap_or(defaults.turn_mode),
+        ..defaults
+    };
     
-    state
+    // Catch a bad map file or generator setting here rather than falling back to the grid
+    if let Err(e) = settings.map.load(settings.map_size) {
+        return Err((StatusCode::BAD_REQUEST, e));
+    }
+    
+    let game_server = registry.create(settings).await
+        .map_err(|e| (StatusCode::TOO_MANY_REQUESTS, e))?;
+    let game = registry.list().await.into_iter()
+        .find(|g| g.id == game_server.game_id)
+        .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "Game was not registered".to_string()))?;
+    
+    Ok(Json(CreateGameResponse {
+        message: format!("Game {} created ({})", game.name, game.id),
+        game,
+    }))
+}
+
+async fn get_leaderboard(
+    Query(query): Query<LeaderboardQuery>,
+    State(registry): State<Arc<GameRegistry>>,
+) -> Result<Json<Vec<ScoreCard>>, StatusCode> {
+    let game_id = query.game_id.as_deref().unwrap_or(DEFAULT_GAME_ID);
+    let game_server = registry.get(game_id).await.ok_or(StatusCode::NOT_FOUND)?;
+    let game_state = game_server.game_state.read().await;
+    Ok(Json(game_state.leaderboard()))
+}
+
+async fn get_market(
+    Path(game_id): Path<String>,
+    State(registry): State<Arc<GameRegistry>>,
+) -> Result<Json<MarketReport>, StatusCode> {
+    let game_server = registry.get(&game_id).await.ok_or(StatusCode::NOT_FOUND)?;
+    let game_state = game_server.game_state.read().await;
+    Ok(Json(game_state.market_report()))
 }
 
 async fn register_player(
-    State(game_server): State<Arc<GameServer>>,
+    Path(game_id): Path<String>,
+    State(registry): State<Arc<GameRegistry>>,
     Json(req): Json<RegisterRequest>,
 ) -> Result<Json<RegisterResponse>, StatusCode> {
+    let game_server = registry.get(&game_id).await.ok_or(StatusCode::NOT_FOUND)?;
     let player_id = Uuid::new_v4().to_string();
     
     let mut game_state = game_server.game_state.write().await;
     
-    // Create new player
-    let player = Player {
-        id: player_id.clone(),
-        name: req.name.clone(),
-        current_ap: 50,
-        max_ap: 100,
-        credits: 100,
-        level: 1,
-        xp: 0,
-        reputation: 0,
-        owned_ships: vec![],
-        command_ships: vec![],
-        garrison_slots: 0,
-    };
-    
-    game_state.players.insert(player_id.clone(), player);
+    // Create new player with a starting frigate, so they aren't counted as eliminated
+    game_state.players.insert(player_id.clone(), Player::new(&player_id, &req.name));
+    if let Some(home) = game_state.open_home_sector() {
+        game_state.spawn_ship(&player_id, ShipType::Frigate, &home, &format!("{} Pioneer", req.name));
+    }
     
     // Save state
-    save_game_state(&game_state).await;
+    game_server.save(&game_state).await;
     
     Ok(Json(RegisterResponse {
         player_id,
-        message: format!("Welcome to Interstellar Command, {}!", req.name),
+        message: format!("Welcome to {}, {}!", game_state.settings.name, req.name),
     }))
 }
 
 async fn websocket_handler(
     ws: WebSocketUpgrade,
-    Path(player_id): Path<String>,
-    State(game_server): State<Arc<GameServer>>,
+    Path((game_id, player_id)): Path<(String, String)>,
+    State(registry): State<Arc<GameRegistry>>,
 ) -> impl axum::response::IntoResponse {
+    let game_server = match registry.get(&game_id).await {
+        Some(g) => g,
+        None => return StatusCode::NOT_FOUND.into_response(),
+    };
+    
+    // The NPC faction's ID is reserved and never a valid login
+    if player_id == PIRATE_PLAYER_ID || game_server.game_state.read().await.banned.contains(&player_id) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    
     ws.on_upgrade(move |socket| async move {
         game_server.handle_websocket(socket, player_id).await;
     })
+    .into_response()
 }
 
-async fn save_game_state(state: &GameState) {
-    match serde_json::to_string_pretty(state) {
-        Ok(json) => {
-            if let Err(e) = tokio::fs::write("../data/game_state.json", json).await {
-                eprintln!("Failed to save game state: {}", e);
-            }
-        }
-        Err(e) => eprintln!("Failed to serialize game state: {}", e),
+async fn spectate_handler(
+    ws: WebSocketUpgrade,
+    Path(game_id): Path<String>,
+    State(registry): State<Arc<GameRegistry>>,
+) -> impl axum::response::IntoResponse {
+    let game_server = match registry.get(&game_id).await {
+        Some(g) => g,
+        None => return StatusCode::NOT_FOUND.into_response(),
+    };
+    
+    ws.on_upgrade(move |socket| async move {
+        game_server.handle_spectator(socket).await;
+    })
+    .into_response()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_players_start_in_the_maps_start_sectors() {
+        let state = create_new_game_state();
+        let homes: Vec<&str> = ["ship-1", "ship-2"].iter()
+            .map(|id| state.ships[*id].current_sector.as_str())
+            .collect();
+        assert_eq!(homes, state.start_slots[..2].iter().map(|s| s.as_str()).collect::<Vec<_>>());
+        assert!(state.sectors.iter().any(|s| s.ships_present.contains(&"ship-2".to_string())));
     }
-} 
\ No newline at end of file
+}
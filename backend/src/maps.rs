@@ -0,0 +1,466 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::f64::consts::PI;
+
+use crate::game::{GameState, Sector};
+use crate::terrain::Terrain;
+
+pub const MAPS_DIR: &str = "../config/maps";
+
+const GENERATED_RINGS: usize = 3; // Rings of sectors between the core and each home
+
+// Where a game's map comes from
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MapSource {
+    #[default]
+    Grid, // map_size x map_size Earth grid around the core
+    File { name: String }, // config/maps/<name>.json
+    Generated { seed: u64, players: u32 }, // Symmetric map with one home per player
+}
+
+// A map as written in a map file
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct MapDefinition {
+    pub name: String,
+    pub sectors: Vec<SectorDefinition>,
+    pub start_slots: Vec<String>, // Sector IDs new commanders start in, in order of preference
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SectorDefinition {
+    pub id: String,
+    pub name: String,
+    pub position: (i32, i32), // Where the map draws it
+    pub planet: String,
+    #[serde(default)]
+    pub links: Vec<String>, // Links work both ways, so each only needs listing once
+    #[serde(default)]
+    pub traits: Vec<String>,
+    #[serde(default)]
+    pub terrain: Vec<Terrain>,
+}
+
+impl MapSource {
+    pub fn load(&self, map_size: i32) -> Result<MapDefinition, String> {
+        match self {
+            MapSource::Grid => Ok(MapDefinition::grid(map_size)),
+            MapSource::File { name } => MapDefinition::load(name),
+            MapSource::Generated { seed, players } => MapDefinition::generate(*seed, *players),
+        }
+    }
+}
+
+impl MapDefinition {
+    pub fn load(name: &str) -> Result<Self, String> {
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(format!("Invalid map name {}", name));
+        }
+        let path = format!("{}/{}.json", MAPS_DIR, name);
+        let json = std::fs::read_to_string(&path).map_err(|e| format!("Can't read {}: {}", path, e))?;
+        let map: MapDefinition = serde_json::from_str(&json).map_err(|e| format!("{}: {}", path, e))?;
+        map.validate().map_err(|e| format!("{}: {}", path, e))?;
+        Ok(map)
+    }
+    
+    // The standard Earth grid. The core sits in a gap between the four central sectors
+    // and links to them, so no two sectors are drawn in the same place
+    pub fn grid(size: i32) -> Self {
+        let half = size / 2;
+        let id = |x: i32, y: i32| format!("earth-{}", x * size + y + 1);
+        let shown = |v: i32| if v >= half { v + 1 } else { v };
+        
+        let mut sectors = Vec::new();
+        for x in 0..size {
+            for y in 0..size {
+                let mut links = Vec::new();
+                if x + 1 < size {
+                    links.push(id(x + 1, y));
+                }
+                if y + 1 < size {
+                    links.push(id(x, y + 1));
+                }
+                sectors.push(SectorDefinition {
+                    id: id(x, y),
+                    name: format!("E{}", x * size + y + 1),
+                    position: (shown(x), shown(y)),
+                    planet: "Earth".to_string(),
+                    links,
+                    traits: Vec::new(),
+                    terrain: Vec::new(),
+                });
+            }
+        }
+        
+        sectors.push(SectorDefinition {
+            id: "earth-core".to_string(),
+            name: "Earth Core".to_string(),
+            position: (half, half),
+            planet: "Earth".to_string(),
+            links: vec![id(half - 1, half - 1), id(half - 1, half), id(half, half - 1), id(half, half)],
+            traits: Vec::new(),
+            terrain: vec![Terrain::TradeHub],
+        });
+        
+        // Opposite corners first, each with a shipyard
+        let last = size - 1;
+        let start_slots = vec![id(0, 0), id(last, last), id(0, last), id(last, 0)];
+        for sector in sectors.iter_mut().filter(|s| start_slots.contains(&s.id)) {
+            sector.terrain.push(Terrain::Shipyard);
+        }
+        Self {
+            name: format!("Earth {}x{}", size, size),
+            sectors,
+            start_slots,
+        }
+    }
+    
+    // Seeded map for 2-8 players. One wedge of sectors is generated and copied around the
+    // core once per player, so every home has the same surroundings and distance to the core
+    pub fn generate(seed: u64, players: u32) -> Result<Self, String> {
+        if !(2..=8).contains(&players) {
+            return Err("Generated maps need 2-8 players".to_string());
+        }
+        let mut rng = SplitMix(seed);
+        
+        // Wedge template: sectors per ring, links inside a ring, and links to the next wedge
+        let ring_sizes: Vec<usize> = (1..=GENERATED_RINGS)
+            .map(|ring| if ring == 1 { 1 } else { ring + rng.below(2) as usize })
+            .collect();
+        let side_links: Vec<Vec<bool>> = ring_sizes.iter()
+            .map(|&n| (1..n).map(|_| rng.below(2) == 0).collect())
+            .collect();
+        let wedge_links: Vec<bool> = (0..GENERATED_RINGS)
+            .map(|ring| ring + 1 == GENERATED_RINGS || rng.below(3) > 0)
+            .collect();
+        // Homes get a shipyard and the inner ring a jump gate; elsewhere, random hazards
+        const HAZARDS: [Terrain; 3] = [Terrain::Nebula, Terrain::AsteroidField, Terrain::IonStorm];
+        let terrain: Vec<Vec<Vec<Terrain>>> = ring_sizes.iter().enumerate()
+            .map(|(ring, &slots)| (0..slots).map(|slot| {
+                if ring == 0 {
+                    vec![Terrain::JumpGate]
+                } else if ring + 1 == GENERATED_RINGS && slot == slots / 2 {
+                    vec![Terrain::Shipyard]
+                } else if rng.below(3) == 0 {
+                    vec![HAZARDS[rng.below(HAZARDS.len() as u64) as usize]]
+                } else {
+                    Vec::new()
+                }
+            }).collect())
+            .collect();
+        
+        let n = players as usize;
+        let wedge_size: usize = ring_sizes.iter().sum();
+        // Sector ID for (wedge, ring, slot); rings count from 0 here
+        let id = |wedge: usize, ring: usize, slot: usize| {
+            let offset: usize = ring_sizes[..ring].iter().sum();
+            format!("earth-{}", (wedge % n) * wedge_size + offset + slot + 1)
+        };
+        
+        // Spread the rings out until every sector lands on its own grid square
+        let mut spacing = 1.0;
+        let positions = loop {
+            let mut positions = HashMap::new();
+            for wedge in 0..n {
+                for (ring, &slots) in ring_sizes.iter().enumerate() {
+                    for slot in 0..slots {
+                        let angle = 2.0 * PI * (wedge as f64 + (slot as f64 + 0.5) / slots as f64) / n as f64 - PI / 2.0;
+                        let radius = spacing * (ring + 1) as f64;
+                        let position = ((radius * angle.cos()).round() as i32, (radius * angle.sin()).round() as i32);
+                        positions.insert(id(wedge, ring, slot), position);
+                    }
+                }
+            }
+            positions.insert("earth-core".to_string(), (0, 0));
+            
+            let distinct: HashSet<&(i32, i32)> = positions.values().collect();
+            if distinct.len() == positions.len() {
+                break positions;
+            }
+            spacing += 0.5;
+        };
+        let min_x = positions.values().map(|p| p.0).min().unwrap_or(0);
+        let min_y = positions.values().map(|p| p.1).min().unwrap_or(0);
+        
+        let mut sectors = vec![SectorDefinition {
+            id: "earth-core".to_string(),
+            name: "Earth Core".to_string(),
+            position: (-min_x, -min_y),
+            planet: "Earth".to_string(),
+            links: (0..n).map(|wedge| id(wedge, 0, 0)).collect(),
+            traits: Vec::new(),
+            terrain: vec![Terrain::TradeHub],
+        }];
+        let mut start_slots = Vec::new();
+        
+        for wedge in 0..n {
+            for (ring, &slots) in ring_sizes.iter().enumerate() {
+                for slot in 0..slots {
+                    let mut links = Vec::new();
+                    // Inwards to the nearest sector of the previous ring keeps the map connected
+                    if ring > 0 {
+                        links.push(id(wedge, ring - 1, slot * ring_sizes[ring - 1] / slots));
+                    }
+                    if slot + 1 < slots && side_links[ring][slot] {
+                        links.push(id(wedge, ring, slot + 1));
+                    }
+                    if slot + 1 == slots && wedge_links[ring] {
+                        links.push(id(wedge + 1, ring, 0));
+                    }
+                    
+                    let sector_id = id(wedge, ring, slot);
+                    let (x, y) = positions[&sector_id];
+                    sectors.push(SectorDefinition {
+                        name: format!("E{}", sector_id.trim_start_matches("earth-")),
+                        id: sector_id,
+                        position: (x - min_x, y - min_y),
+                        planet: "Earth".to_string(),
+                        links,
+                        traits: Vec::new(),
+                        terrain: terrain[ring][slot].clone(),
+                    });
+                }
+            }
+            start_slots.push(id(wedge, GENERATED_RINGS - 1, ring_sizes[GENERATED_RINGS - 1] / 2));
+        }
+        
+        let map = Self {
+            name: format!("Generated {}-player map (seed {})", players, seed),
+            sectors,
+            start_slots,
+        };
+        map.validate()?;
+        Ok(map)
+    }
+    
+    // Unique IDs and positions, links to real sectors, start slots that exist, and every
+    // sector reachable from every other
+    pub fn validate(&self) -> Result<(), String> {
+        if self.sectors.is_empty() {
+            return Err("Map has no sectors".to_string());
+        }
+        
+        let mut ids = HashSet::new();
+        let mut positions = HashMap::new();
+        for sector in &self.sectors {
+            if !ids.insert(sector.id.as_str()) {
+                return Err(format!("Sector {} is defined twice", sector.id));
+            }
+            if let Some(other) = positions.insert(sector.position, sector.id.as_str()) {
+                return Err(format!("Sectors {} and {} share position {:?}", other, sector.id, sector.position));
+            }
+        }
+        
+        for sector in &self.sectors {
+            for link in &sector.links {
+                if link == &sector.id {
+                    return Err(format!("Sector {} links to itself", sector.id));
+                }
+                if !ids.contains(link.as_str()) {
+                    return Err(format!("Sector {} links to unknown sector {}", sector.id, link));
+                }
+            }
+        }
+        
+        if self.start_slots.is_empty() {
+            return Err("Map has no start slots".to_string());
+        }
+        let mut slots = HashSet::new();
+        for slot in &self.start_slots {
+            if !ids.contains(slot.as_str()) {
+                return Err(format!("Start slot {} is not a sector", slot));
+            }
+            if !slots.insert(slot) {
+                return Err(format!("Start slot {} is listed twice", slot));
+            }
+        }
+        
+        let links = self.link_table();
+        let mut reached = HashSet::from([self.sectors[0].id.as_str()]);
+        let mut queue = VecDeque::from([self.sectors[0].id.as_str()]);
+        while let Some(current) = queue.pop_front() {
+            for next in &links[current] {
+                if reached.insert(next.as_str()) {
+                    queue.push_back(next.as_str());
+                }
+            }
+        }
+        if let Some(cut_off) = self.sectors.iter().find(|s| !reached.contains(s.id.as_str())) {
+            return Err(format!("Sector {} can't be reached from {}", cut_off.id, self.sectors[0].id));
+        }
+        
+        Ok(())
+    }
+    
+    // Links in both directions, in the order they were first listed
+    fn link_table(&self) -> HashMap<&str, Vec<String>> {
+        let mut table: HashMap<&str, Vec<String>> = self.sectors.iter()
+            .map(|s| (s.id.as_str(), Vec::new()))
+            .collect();
+        for sector in &self.sectors {
+            for link in &sector.links {
+                for (from, to) in [(sector.id.as_str(), link.as_str()), (link.as_str(), sector.id.as_str())] {
+                    if let Some(list) = table.get_mut(from) {
+                        if !list.iter().any(|l| l == to) {
+                            list.push(to.to_string());
+                        }
+                    }
+                }
+            }
+        }
+        table
+    }
+    
+    pub fn into_sectors(self) -> Vec<Sector> {
+        let mut links = self.link_table();
+        let links: HashMap<String, Vec<String>> = self.sectors.iter()
+            .map(|s| (s.id.clone(), links.remove(s.id.as_str()).unwrap_or_default()))
+            .collect();
+        
+        self.sectors.into_iter().map(|s| Sector {
+            links: links[&s.id].clone(),
+            id: s.id,
+            name: s.name,
+            position: s.position,
+            planet: s.planet,
+            traits: s.traits,
+            terrain: s.terrain,
+            controlled_by: None,
+            garrison_ship: None,
+            ships_present: Vec::new(),
+        }).collect()
+    }
+}
+
+impl GameState {
+    // Saves from before map files have no links or start slots. The standard grid gets its
+    // current layout; anything else links sectors one grid step apart, as before
+    pub fn upgrade_map(&mut self) {
+        if self.sectors.iter().any(|s| !s.links.is_empty()) {
+            return;
+        }
+        
+        let grid = MapDefinition::grid(self.settings.map_size);
+        let same_layout = grid.sectors.len() == self.sectors.len()
+            && grid.sectors.iter().all(|g| self.sectors.iter().any(|s| s.id == g.id));
+        
+        if same_layout {
+            let start_slots = grid.start_slots.clone();
+            for fresh in grid.into_sectors() {
+                if let Some(sector) = self.sectors.iter_mut().find(|s| s.id == fresh.id) {
+                    sector.position = fresh.position;
+                    sector.links = fresh.links;
+                    sector.terrain = fresh.terrain;
+                }
+            }
+            self.start_slots = start_slots;
+        } else {
+            let neighbours: Vec<Vec<String>> = self.sectors.iter().map(|sector| {
+                self.sectors.iter()
+                    .filter(|s| (s.position.0 - sector.position.0).abs() + (s.position.1 - sector.position.1).abs() == 1)
+                    .map(|s| s.id.clone())
+                    .collect()
+            }).collect();
+            for (sector, links) in self.sectors.iter_mut().zip(neighbours) {
+                sector.links = links;
+            }
+        }
+    }
+}
+
+// Small deterministic generator, so a seed always gives the same map
+struct SplitMix(u64);
+
+impl SplitMix {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+    
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    // Hops from one sector to every other, sorted
+    fn distances(map: &MapDefinition, from: &str) -> Vec<usize> {
+        let links = map.link_table();
+        let mut seen = HashMap::from([(from, 0)]);
+        let mut queue = VecDeque::from([from]);
+        while let Some(current) = queue.pop_front() {
+            let hops = seen[current];
+            for next in &links[current] {
+                if !seen.contains_key(next.as_str()) {
+                    seen.insert(next.as_str(), hops + 1);
+                    queue.push_back(next.as_str());
+                }
+            }
+        }
+        let mut hops: Vec<usize> = seen.into_values().collect();
+        hops.sort();
+        hops
+    }
+    
+    #[test]
+    fn same_seed_same_map() {
+        let a = serde_json::to_string(&MapDefinition::generate(42, 4).unwrap()).unwrap();
+        let b = serde_json::to_string(&MapDefinition::generate(42, 4).unwrap()).unwrap();
+        assert_eq!(a, b);
+        
+        let others: HashSet<String> = (0..10)
+            .map(|seed| serde_json::to_string(&MapDefinition::generate(seed, 4).unwrap()).unwrap())
+            .collect();
+        assert!(others.len() > 1, "seeds should vary the map");
+    }
+    
+    #[test]
+    fn generated_maps_are_connected_and_symmetric() {
+        for players in 2..=8 {
+            for seed in 0..20 {
+                let map = MapDefinition::generate(seed, players).unwrap();
+                assert!(map.validate().is_ok());
+                assert_eq!(map.start_slots.len(), players as usize);
+                assert_eq!(distances(&map, "earth-core").len(), map.sectors.len(), "seed {} isn't connected", seed);
+                
+                // Every home sees the same map around it
+                let from_first = distances(&map, &map.start_slots[0]);
+                for home in &map.start_slots {
+                    assert_eq!(distances(&map, home), from_first, "seed {} with {} players", seed, players);
+                }
+            }
+        }
+    }
+    
+    #[test]
+    fn rejects_invalid_player_counts() {
+        assert!(MapDefinition::generate(1, 1).is_err());
+        assert!(MapDefinition::generate(1, 9).is_err());
+        assert!(MapSource::Generated { seed: 1, players: 0 }.load(4).is_err());
+    }
+    
+    #[test]
+    fn rejects_broken_maps() {
+        let mut map = MapDefinition::grid(4);
+        assert!(map.validate().is_ok());
+        
+        map.sectors[0].links.push("nowhere".to_string());
+        assert!(map.validate().is_err());
+        
+        let mut map = MapDefinition::grid(4);
+        let island = map.sectors[1].id.clone();
+        for sector in map.sectors.iter_mut() {
+            sector.links.retain(|l| *l != island);
+        }
+        map.sectors[1].links.clear();
+        assert!(map.validate().unwrap_err().contains("can't be reached"));
+    }
+}
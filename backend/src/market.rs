@@ -0,0 +1,310 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::game::{GameState, CommandResult, Resource};
+use crate::terrain::Terrain;
+
+const PRICE_HISTORY_CYCLES: usize = 100;
+const RECOVERY_PERCENT: i64 = 10; // How far each pool drifts back to its starting depth per cycle
+
+// Starting (stock, credits) for each commodity; the ratio is its opening price
+const BASE_POOLS: [(Resource, i64, i64); 3] = [
+    (Resource::Ore, 1000, 10_000),
+    (Resource::Fuel, 1000, 15_000),
+    (Resource::Components, 500, 15_000),
+];
+
+// The market maker for one commodity. It prices every trade so that stock × credits stays
+// the same, so buying pushes the price up and selling pushes it down
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Pool {
+    pub stock: i64,
+    pub credits: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PricePoint {
+    pub cycle: u32,
+    pub prices: HashMap<Resource, f64>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Market {
+    pub pools: HashMap<Resource, Pool>,
+    pub history: Vec<PricePoint>, // One entry per cycle, oldest first
+}
+
+// What the REST endpoint publishes
+#[derive(Serialize)]
+pub struct MarketReport {
+    pub cycle: u32,
+    pub prices: HashMap<Resource, f64>,
+    pub history: Vec<PricePoint>,
+}
+
+impl Default for Market {
+    fn default() -> Self {
+        Self {
+            pools: BASE_POOLS.iter()
+                .map(|&(resource, stock, credits)| (resource, Pool { stock, credits }))
+                .collect(),
+            history: Vec::new(),
+        }
+    }
+}
+
+impl Pool {
+    // Credits per unit, to the cent
+    pub fn price(&self) -> f64 {
+        (self.credits as f64 * 100.0 / self.stock as f64).round() / 100.0
+    }
+    
+    // Credits to buy this many units, None if the pool can't supply them
+    pub fn buy_cost(&self, amount: i64) -> Option<i64> {
+        if amount >= self.stock {
+            return None;
+        }
+        let remaining = self.stock - amount;
+        Some((self.credits * amount + remaining - 1) / remaining)
+    }
+    
+    // Credits paid for this many units
+    pub fn sell_value(&self, amount: i64) -> i64 {
+        self.credits * amount / (self.stock + amount)
+    }
+}
+
+impl Market {
+    pub fn prices(&self) -> HashMap<Resource, f64> {
+        self.pools.iter().map(|(resource, pool)| (*resource, pool.price())).collect()
+    }
+}
+
+impl GameState {
+    // A trade hub sector where the player has a ship, if any
+    fn trade_hub_for(&self, player_id: &str) -> Option<String> {
+        self.sectors.iter()
+            .filter(|s| s.has_terrain(Terrain::TradeHub))
+            .find(|s| s.ships_present.iter()
+                .filter_map(|id| self.ships.get(id))
+                .any(|ship| ship.owner == player_id))
+            .map(|s| s.name.clone())
+    }
+    
+    pub fn market_report(&self) -> MarketReport {
+        MarketReport {
+            cycle: self.cycle_number,
+            prices: self.market.prices(),
+            history: self.market.history.clone(),
+        }
+    }
+    
+    pub fn market_status(&self, player_id: &str) -> CommandResult {
+        let mut report = "=== Market ===\n".to_string();
+        for &(resource, _, _) in BASE_POOLS.iter() {
+            let pool = &self.market.pools[&resource];
+            let held = self.players.get(player_id)
+                .and_then(|p| p.resources.get(&resource).copied())
+                .unwrap_or(0);
+            let buy_10 = pool.buy_cost(10).map_or("-".to_string(), |c| c.to_string());
+            report.push_str(&format!(
+                "{:?}: {:.2} credits each (buy 10 for {}, sell 10 for {}) - you have {}\n",
+                resource, pool.price(), buy_10, pool.sell_value(10), held
+            ));
+        }
+        
+        let hubs: Vec<&str> = self.sectors.iter()
+            .filter(|s| s.has_terrain(Terrain::TradeHub))
+            .map(|s| s.name.as_str())
+            .collect();
+        match self.trade_hub_for(player_id) {
+            Some(hub) => report.push_str(&format!("Trading at {}", hub)),
+            None if hubs.is_empty() => report.push_str("There are no trade hubs on this map"),
+            None => report.push_str(&format!("Move a ship to a trade hub to trade: {}", hubs.join(", "))),
+        }
+        
+        CommandResult {
+            success: true,
+            message: report,
+            ap_spent: 0,
+            game_state: self.clone(),
+        }
+    }
+    
+    pub fn buy_resource(&mut self, player_id: &str, resource: Resource, amount: i32) -> CommandResult {
+        let hub = match self.trade_hub_for(player_id) {
+            Some(h) => h,
+            None => return CommandResult {
+                success: false,
+                message: "You need a ship at a trade hub to trade".to_string(),
+                ap_spent: 0,
+                game_state: self.clone(),
+            }
+        };
+        
+        let pool = self.market.pools.get(&resource).unwrap();
+        let cost = match pool.buy_cost(amount as i64) {
+            Some(c) if c <= i32::MAX as i64 => c as i32,
+            _ => return CommandResult {
+                success: false,
+                message: format!("The market can't supply {} {:?}", amount, resource),
+                ap_spent: 0,
+                game_state: self.clone(),
+            }
+        };
+        
+        let player = self.players.get_mut(player_id).unwrap();
+        if player.credits < cost {
+            return CommandResult {
+                success: false,
+                message: format!("{} {:?} costs {} credits; you have {}", amount, resource, cost, player.credits),
+                ap_spent: 0,
+                game_state: self.clone(),
+            };
+        }
+        player.credits -= cost;
+        *player.resources.entry(resource).or_insert(0) += amount;
+        
+        let pool = self.market.pools.get_mut(&resource).unwrap();
+        pool.stock -= amount as i64;
+        pool.credits += cost as i64;
+        let price = pool.price();
+        
+        CommandResult {
+            success: true,
+            message: format!("Bought {} {:?} at {} for {} credits; the price is now {:.2}", amount, resource, hub, cost, price),
+            ap_spent: 0,
+            game_state: self.clone(),
+        }
+    }
+    
+    pub fn sell_resource(&mut self, player_id: &str, resource: Resource, amount: i32) -> CommandResult {
+        let hub = match self.trade_hub_for(player_id) {
+            Some(h) => h,
+            None => return CommandResult {
+                success: false,
+                message: "You need a ship at a trade hub to trade".to_string(),
+                ap_spent: 0,
+                game_state: self.clone(),
+            }
+        };
+        
+        let held = self.players[player_id].resources.get(&resource).copied().unwrap_or(0);
+        if held < amount {
+            return CommandResult {
+                success: false,
+                message: format!("You only have {} {:?}", held, resource),
+                ap_spent: 0,
+                game_state: self.clone(),
+            };
+        }
+        
+        let pool = self.market.pools.get_mut(&resource).unwrap();
+        let value = pool.sell_value(amount as i64);
+        pool.stock += amount as i64;
+        pool.credits -= value;
+        let price = pool.price();
+        
+        let player = self.players.get_mut(player_id).unwrap();
+        *player.resources.entry(resource).or_insert(0) -= amount;
+        player.credits += value as i32;
+        
+        CommandResult {
+            success: true,
+            message: format!("Sold {} {:?} at {} for {} credits; the price is now {:.2}", amount, resource, hub, value, price),
+            ap_spent: 0,
+            game_state: self.clone(),
+        }
+    }
+    
+    // Pools slowly refill or drain back toward their starting depth, then the cycle's
+    // prices are recorded. Called once per cycle
+    pub fn process_market(&mut self) {
+        for &(resource, stock, credits) in BASE_POOLS.iter() {
+            let pool = self.market.pools.entry(resource).or_insert(Pool { stock, credits });
+            pool.stock += (stock - pool.stock) * RECOVERY_PERCENT / 100;
+            pool.credits += (credits - pool.credits) * RECOVERY_PERCENT / 100;
+        }
+        
+        let prices = self.market.prices();
+        self.market.history.push(PricePoint {
+            cycle: self.cycle_number,
+            prices,
+        });
+        let excess = self.market.history.len().saturating_sub(PRICE_HISTORY_CYCLES);
+        self.market.history.drain(..excess);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Player, ShipType};
+    
+    fn trader() -> GameState {
+        let mut state = GameState::new();
+        state.players.insert("p1".to_string(), Player::new("p1", "Alpha"));
+        state.players.get_mut("p1").unwrap().credits = 1000;
+        state
+    }
+    
+    #[test]
+    fn rounding_favours_the_pool() {
+        let pool = Pool { stock: 1000, credits: 10_000 };
+        // 101.01 rounds up when buying, 99.01 rounds down when selling
+        assert_eq!(pool.buy_cost(10), Some(102));
+        assert_eq!(pool.sell_value(10), 99);
+        assert_eq!(pool.buy_cost(1000), None);
+        assert_eq!(Pool { stock: 3, credits: 10 }.price(), 3.33);
+    }
+    
+    #[test]
+    fn trading_needs_a_ship_at_a_hub() {
+        let mut state = trader();
+        let ship_id = state.spawn_ship("p1", ShipType::Frigate, "earth-1", "Pioneer").unwrap();
+        let result = state.buy_resource("p1", Resource::Ore, 10);
+        assert!(!result.success);
+        assert!(result.message.contains("trade hub"));
+        
+        state.destroy_ship(&ship_id);
+        state.spawn_ship("p1", ShipType::Frigate, "earth-core", "Pioneer");
+        let result = state.buy_resource("p1", Resource::Ore, 10);
+        assert!(result.success, "{}", result.message);
+        let player = &state.players["p1"];
+        assert_eq!(player.credits, 1000 - 102);
+        assert_eq!(player.resources[&Resource::Ore], 10);
+        let pool = &state.market.pools[&Resource::Ore];
+        assert_eq!((pool.stock, pool.credits), (990, 10_102));
+        
+        assert!(state.sell_resource("p1", Resource::Ore, 10).success);
+        assert_eq!(state.players["p1"].resources[&Resource::Ore], 0);
+        assert!(!state.sell_resource("p1", Resource::Ore, 1).success);
+    }
+    
+    #[test]
+    fn pools_drift_back_toward_their_starting_depth() {
+        let mut state = trader();
+        let pool = state.market.pools.get_mut(&Resource::Ore).unwrap();
+        pool.stock = 500;
+        pool.credits = 20_000;
+        
+        state.process_market();
+        let pool = &state.market.pools[&Resource::Ore];
+        assert_eq!((pool.stock, pool.credits), (550, 19_000));
+        assert_eq!(state.market.history.len(), 1);
+        assert_eq!(state.market.history[0].prices[&Resource::Ore], pool.price());
+    }
+    
+    #[test]
+    fn history_keeps_the_last_cycles_only() {
+        let mut state = trader();
+        for cycle in 1..=PRICE_HISTORY_CYCLES as u32 + 5 {
+            state.cycle_number = cycle;
+            state.process_market();
+        }
+        let history = &state.market.history;
+        assert_eq!(history.len(), PRICE_HISTORY_CYCLES);
+        assert_eq!(history[0].cycle, 6);
+        assert_eq!(history.last().unwrap().cycle, PRICE_HISTORY_CYCLES as u32 + 5);
+    }
+}
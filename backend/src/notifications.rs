@@ -0,0 +1,575 @@
+use axum::extract::ws::{Message, WebSocket};
+use futures_util::SinkExt;
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::time::{timeout, Duration};
+
+use crate::game::{GameState, CommandResult, GameEvent};
+use uuid::Uuid;
+use crate::websocket::GameServer;
+use crate::webpush::{Delivery, send_push};
+
+const INBOX_LIMIT: usize = 50; // Per player; the oldest notifications are dropped past this
+const DELIVERY_TIMEOUT_SECS: u64 = 10; // Per webhook call or email
+const DEFAULT_SMTP_FROM: &str = "command@interstellar.local";
+const WEBHOOK_HOSTS_VAR: &str = "WEBHOOK_HOSTS"; // Comma-separated host:port list webhooks may target
+
+// Events worth telling a player about while they're away
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    UnderAttack,
+    SectorLost,
+    CycleComplete,
+    TradeOffer,
+}
+
+impl NotificationKind {
+    pub const ALL: [NotificationKind; 4] = [
+        NotificationKind::UnderAttack,
+        NotificationKind::SectorLost,
+        NotificationKind::CycleComplete,
+        NotificationKind::TradeOffer,
+    ];
+    
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "attack" | "under_attack" => Some(NotificationKind::UnderAttack),
+            "sector" | "sector_lost" => Some(NotificationKind::SectorLost),
+            "cycle" | "cycle_complete" => Some(NotificationKind::CycleComplete),
+            "trade" | "trade_offer" => Some(NotificationKind::TradeOffer),
+            _ => None,
+        }
+    }
+    
+    // What players type to switch it on or off
+    fn keyword(&self) -> &'static str {
+        match self {
+            NotificationKind::UnderAttack => "attack",
+            NotificationKind::SectorLost => "sector",
+            NotificationKind::CycleComplete => "cycle",
+            NotificationKind::TradeOffer => "trade",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Notification {
+    pub kind: NotificationKind,
+    pub message: String,
+    pub cycle: u32,
+    pub sent_at: DateTime<Utc>,
+}
+
+// A notification on its way to a player, drained by the server alongside events
+#[derive(Clone)]
+pub struct Alert {
+    pub player_id: String,
+    pub notification: Notification,
+}
+
+// What a player wants to hear about while they're offline, and where else to send it
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct NotificationPrefs {
+    pub kinds: Vec<NotificationKind>,
+    pub webhook: Option<String>, // http:// URL on a host in WEBHOOK_HOSTS; gets a JSON POST per notification
+    pub email: Option<String>, // Confirmed address, sent through the SMTP relay in SMTP_RELAY
+    pub pending_email: Option<EmailCheck>, // Address waiting for its confirmation code
+}
+
+impl Default for NotificationPrefs {
+    fn default() -> Self {
+        Self {
+            kinds: NotificationKind::ALL.to_vec(),
+            webhook: None,
+            email: None,
+            pending_email: None,
+        }
+    }
+}
+
+// An address and the code that was mailed to it. Nothing else goes to an address until
+// its owner proves they read it
+#[derive(Serialize, Deserialize, Clone)]
+pub struct EmailCheck {
+    pub address: String,
+    pub code: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum NotifyChange {
+    Enable(NotificationKind),
+    Disable(NotificationKind),
+    Webhook(Option<String>),
+    Email(Option<String>),
+    ConfirmEmail(String),
+}
+
+// What a webhook receives
+#[derive(Serialize)]
+struct WebhookPayload<'a> {
+    game_id: &'a str,
+    player_id: &'a str,
+    player_name: &'a str,
+    #[serde(flatten)]
+    notification: &'a Notification,
+}
+
+// Everything waiting for a player when they connect
+#[derive(Serialize)]
+struct InboxUpdate {
+    #[serde(rename = "type")]
+    msg_type: String,
+    notifications: Vec<Notification>,
+}
+
+impl GameState {
+    // Queues a notification without showing anything to players who are online
+    pub fn alert(&mut self, player_id: &str, kind: NotificationKind, message: String) {
+        if !self.players.contains_key(player_id) {
+            return;
+        }
+        self.alerts.push(Alert {
+            player_id: player_id.to_string(),
+            notification: Notification {
+                kind,
+                message,
+                cycle: self.cycle_number,
+                sent_at: Utc::now(),
+            },
+        });
+    }
+    
+    // An event for players who are online that is also kept for them if they aren't
+    pub fn notify(&mut self, player_id: &str, kind: NotificationKind, message: String) {
+        self.events.push(GameEvent {
+            player_id: player_id.to_string(),
+            message: message.clone(),
+        });
+        self.alert(player_id, kind, message);
+    }
+    
+    pub fn take_alerts(&mut self) -> Vec<Alert> {
+        std::mem::take(&mut self.alerts)
+    }
+    
+    // Files a notification in an offline player's inbox if they want it. Returns whether it was
+    pub fn file_notification(&mut self, alert: &Alert) -> bool {
+        let player = match self.players.get_mut(&alert.player_id) {
+            Some(p) => p,
+            None => return false,
+        };
+        if !player.notifications.kinds.contains(&alert.notification.kind) {
+            return false;
+        }
+        player.inbox.push(alert.notification.clone());
+        let excess = player.inbox.len().saturating_sub(INBOX_LIMIT);
+        player.inbox.drain(..excess);
+        true
+    }
+    
+    pub fn take_inbox(&mut self, player_id: &str) -> Vec<Notification> {
+        self.players.get_mut(player_id).map_or_else(Vec::new, |p| std::mem::take(&mut p.inbox))
+    }
+    
+    pub fn take_email_checks(&mut self) -> Vec<EmailCheck> {
+        std::mem::take(&mut self.email_checks)
+    }
+    
+    pub fn notification_settings(&mut self, player_id: &str, change: Option<NotifyChange>) -> CommandResult {
+        let pending = self.players.get(player_id).and_then(|p| p.notifications.pending_email.clone());
+        let error = match &change {
+            Some(NotifyChange::Webhook(Some(url))) => match webhook_host(url) {
+                None => Some("Webhooks must be plain http:// URLs, e.g. a local relay".to_string()),
+                Some(host) if !webhook_host_allowed(&host, &std::env::var(WEBHOOK_HOSTS_VAR).unwrap_or_default()) =>
+                    Some(format!("Webhooks can't go to {} on this server; ask the operator to allow it", host)),
+                Some(_) => None,
+            },
+            Some(NotifyChange::Email(Some(address))) if !address.contains('@') || address.contains(['<', '>', '\r', '\n']) =>
+                Some(format!("{} isn't an email address", address)),
+            Some(NotifyChange::ConfirmEmail(code)) => match &pending {
+                Some(check) if check.code.eq_ignore_ascii_case(code) => None,
+                Some(_) => Some("That isn't the confirmation code that was emailed to you".to_string()),
+                None => Some("There's no email address waiting to be confirmed".to_string()),
+            },
+            _ => None,
+        };
+        if let Some(message) = error {
+            return CommandResult {
+                success: false,
+                message,
+                ap_spent: 0,
+                game_state: self.clone(),
+            };
+        }
+        
+        let prefs = &mut self.players.get_mut(player_id).unwrap().notifications;
+        match change {
+            Some(NotifyChange::Enable(kind)) if !prefs.kinds.contains(&kind) => prefs.kinds.push(kind),
+            Some(NotifyChange::Disable(kind)) => prefs.kinds.retain(|k| *k != kind),
+            Some(NotifyChange::Webhook(url)) => prefs.webhook = url,
+            Some(NotifyChange::Email(None)) => {
+                prefs.email = None;
+                prefs.pending_email = None;
+            }
+            Some(NotifyChange::Email(Some(address))) => {
+                let check = EmailCheck {
+                    address,
+                    code: Uuid::new_v4().simple().to_string()[..6].to_uppercase(),
+                };
+                prefs.pending_email = Some(check.clone());
+                self.email_checks.push(check);
+            }
+            Some(NotifyChange::ConfirmEmail(_)) => prefs.email = prefs.pending_email.take().map(|c| c.address),
+            _ => {}
+        }
+        let prefs = &self.players[player_id].notifications;
+        
+        let mut report = "=== Notifications ===\n".to_string();
+        for kind in NotificationKind::ALL {
+            let state = if prefs.kinds.contains(&kind) { "on" } else { "off" };
+            report.push_str(&format!("{:?} ({}): {}\n", kind, kind.keyword(), state));
+        }
+        report.push_str(&format!("Webhook: {}\n", prefs.webhook.as_deref().unwrap_or("none")));
+        report.push_str(&format!("Email: {}\n", prefs.email.as_deref().unwrap_or("none")));
+        if let Some(check) = &prefs.pending_email {
+            report.push_str(&format!("A code was emailed to {}; enter it with: notify email confirm <code>\n", check.address));
+        }
+        report.push_str("Kept in your inbox while you're offline and delivered when you next connect");
+        
+        CommandResult {
+            success: true,
+            message: report,
+            ap_spent: 0,
+            game_state: self.clone(),
+        }
+    }
+}
+
+impl GameServer {
+    pub fn set_online(&self, player_id: &str, online: bool) {
+        let mut sockets = self.online.lock().unwrap();
+        let count = sockets.entry(player_id.to_string()).or_insert(0);
+        if online {
+            *count += 1;
+        } else {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                sockets.remove(player_id);
+            }
+        }
+    }
+    
+    fn is_online(&self, player_id: &str) -> bool {
+        self.online.lock().unwrap().contains_key(player_id)
+    }
+    
+    // Players who are connected already saw these as they happened. Everyone else gets
+    // them in their inbox, pushed to their devices, and through their webhook or email if
+    // they set one up
+    pub fn deliver_alerts(&self, game_state: &mut GameState) {
+        for check in game_state.take_email_checks() {
+            let subject = format!("[{}] Confirm your email address", game_state.settings.name);
+            let body = format!("Your confirmation code is {}\nEnter it in the game with: notify email confirm {}", check.code, check.code);
+            tokio::spawn(async move {
+                if let Err(e) = send_email(&check.address, &subject, &body).await {
+                    eprintln!("Confirmation email to {} failed: {}", check.address, e);
+                }
+            });
+        }
+        
+        let allowed_hosts = std::env::var(WEBHOOK_HOSTS_VAR).unwrap_or_default();
+        for alert in game_state.take_alerts() {
+            if self.is_online(&alert.player_id) || !game_state.file_notification(&alert) {
+                continue;
+            }
+            
+            let player = &game_state.players[&alert.player_id];
+            let mut prefs = player.notifications.clone();
+            // The operator may have narrowed the list since the webhook was set
+            prefs.webhook = prefs.webhook
+                .filter(|url| webhook_host(url).is_some_and(|host| webhook_host_allowed(&host, &allowed_hosts)));
+            let payload = serde_json::to_string(&WebhookPayload {
+                game_id: &self.game_id,
+                player_id: &alert.player_id,
+                player_name: &player.name,
+                notification: &alert.notification,
+            }).unwrap();
+            let subject = format!("[{}] {}", game_state.settings.name, alert.notification.message.lines().next().unwrap_or(""));
+            let body = alert.notification.message.clone();
+            let subscriptions = player.push_subscriptions.clone();
+            let game_name = game_state.settings.name.clone();
+            let game_id = self.game_id.clone();
+            let shared_state = self.game_state.clone();
+            
+            tokio::spawn(async move {
+                for subscription in subscriptions {
+                    match send_push(&subscription, &game_name, &game_id, &alert.notification).await {
+                        Ok(Delivery::Sent) => {}
+                        Ok(Delivery::Gone) => {
+                            shared_state.write().await.forget_subscription(&alert.player_id, &subscription.endpoint);
+                        }
+                        Err(e) => eprintln!("Push to {} failed: {}", subscription.endpoint, e),
+                    }
+                }
+                if let Some(url) = prefs.webhook {
+                    if let Err(e) = post_webhook(&url, &payload).await {
+                        eprintln!("Webhook to {} failed: {}", url, e);
+                    }
+                }
+                if let Some(address) = prefs.email {
+                    if let Err(e) = send_email(&address, &subject, &body).await {
+                        eprintln!("Email to {} failed: {}", address, e);
+                    }
+                }
+            });
+        }
+    }
+    
+    pub async fn send_inbox(&self, sender: &mut futures_util::stream::SplitSink<WebSocket, Message>, player_id: &str) {
+        let mut game_state = self.game_state.write().await;
+        let notifications = game_state.take_inbox(player_id);
+        if notifications.is_empty() {
+            return;
+        }
+        
+        let update = InboxUpdate {
+            msg_type: "inbox".to_string(),
+            notifications,
+        };
+        let _ = sender.send(Message::Text(serde_json::to_string(&update).unwrap())).await;
+        self.save(&game_state).await;
+    }
+}
+
+// The host:port a plain http:// URL points at, with the port filled in
+fn webhook_host(url: &str) -> Option<String> {
+    let rest = url.strip_prefix("http://")?;
+    let host = rest.split('/').next().unwrap_or("");
+    if host.is_empty() || host.contains('@') {
+        return None;
+    }
+    Some(if host.contains(':') { host.to_lowercase() } else { format!("{}:80", host.to_lowercase()) })
+}
+
+// Players choose their own webhook URLs, so the server only calls hosts its operator listed.
+// Otherwise anyone could make it POST to its own loopback or a cloud metadata endpoint
+fn webhook_host_allowed(host: &str, allowed: &str) -> bool {
+    allowed.split(',')
+        .map(|entry| entry.trim().to_lowercase())
+        .filter(|entry| !entry.is_empty())
+        .any(|entry| entry == host || format!("{}:80", entry) == host)
+}
+
+// POSTs JSON to a plain http:// URL
+async fn post_webhook(url: &str, json: &str) -> Result<(), String> {
+    let address = webhook_host(url).ok_or("not an http:// URL")?;
+    let rest = &url["http://".len()..];
+    let (host, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    
+    let request = format!(
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        path, host, json.len(), json
+    );
+    let exchange = async {
+        let mut stream = TcpStream::connect(&address).await.map_err(|e| e.to_string())?;
+        stream.write_all(request.as_bytes()).await.map_err(|e| e.to_string())?;
+        let mut status = String::new();
+        BufReader::new(stream).read_line(&mut status).await.map_err(|e| e.to_string())?;
+        match status.split_whitespace().nth(1) {
+            Some(code) if code.starts_with('2') => Ok(()),
+            _ => Err(format!("unexpected response: {}", status.trim())),
+        }
+    };
+    timeout(Duration::from_secs(DELIVERY_TIMEOUT_SECS), exchange).await.map_err(|_| "timed out".to_string())?
+}
+
+// Sends a plain-text email through the relay at SMTP_RELAY (host:port), which is expected to
+// accept mail without authentication, like a local relay or mock. No relay, no email
+async fn send_email(to: &str, subject: &str, body: &str) -> Result<(), String> {
+    let relay = match std::env::var("SMTP_RELAY") {
+        Ok(relay) if !relay.is_empty() => relay,
+        _ => return Ok(()),
+    };
+    let from = std::env::var("SMTP_FROM").unwrap_or_else(|_| DEFAULT_SMTP_FROM.to_string());
+    send_email_via(&relay, &from, to, subject, body).await
+}
+
+async fn send_email_via(relay: &str, from: &str, to: &str, subject: &str, body: &str) -> Result<(), String> {
+    
+    // Lines starting with a dot are escaped so they can't end the message early
+    let body: Vec<String> = body.lines()
+        .map(|line| if line.starts_with('.') { format!(".{}", line) } else { line.to_string() })
+        .collect();
+    let message = format!(
+        "From: <{}>\r\nTo: <{}>\r\nSubject: {}\r\nContent-Type: text/plain; charset=utf-8\r\n\r\n{}\r\n.\r\n",
+        from, to, subject.replace(['\r', '\n'], " "), body.join("\r\n")
+    );
+    
+    let exchange = async {
+        let stream = TcpStream::connect(relay).await.map_err(|e| e.to_string())?;
+        let (reader, mut writer) = stream.into_split();
+        let mut reader = BufReader::new(reader);
+        
+        smtp_reply(&mut reader, "220").await?;
+        for (command, expected) in [
+            ("HELO interstellar-command\r\n".to_string(), "250"),
+            (format!("MAIL FROM:<{}>\r\n", from), "250"),
+            (format!("RCPT TO:<{}>\r\n", to), "250"),
+            ("DATA\r\n".to_string(), "354"),
+            (message, "250"),
+            ("QUIT\r\n".to_string(), "221"),
+        ] {
+            writer.write_all(command.as_bytes()).await.map_err(|e| e.to_string())?;
+            smtp_reply(&mut reader, expected).await?;
+        }
+        Ok(())
+    };
+    timeout(Duration::from_secs(DELIVERY_TIMEOUT_SECS), exchange).await.map_err(|_| "timed out".to_string())?
+}
+
+// Reads one SMTP reply, which may span several lines, and checks its code
+async fn smtp_reply<R: AsyncRead + Unpin>(reader: &mut BufReader<R>, expected: &str) -> Result<(), String> {
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await.map_err(|e| e.to_string())? == 0 {
+            return Err("connection closed".to_string());
+        }
+        if !line.starts_with(expected) {
+            return Err(format!("expected {}, got: {}", expected, line.trim()));
+        }
+        // "250-" continues, "250 " ends
+        if line.as_bytes().get(3) != Some(&b'-') {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Player;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+    
+    #[test]
+    fn webhooks_only_reach_listed_hosts() {
+        let allowed = "relay.local, hooks.example.com:8080";
+        assert!(webhook_host_allowed(&webhook_host("http://relay.local/hook").unwrap(), allowed));
+        assert!(webhook_host_allowed(&webhook_host("http://HOOKS.example.com:8080/x").unwrap(), allowed));
+        assert!(!webhook_host_allowed(&webhook_host("http://hooks.example.com/x").unwrap(), allowed));
+        assert!(!webhook_host_allowed(&webhook_host("http://169.254.169.254/latest").unwrap(), allowed));
+        assert!(!webhook_host_allowed(&webhook_host("http://relay.local/").unwrap(), ""));
+        assert!(webhook_host("http://user@relay.local/").is_none());
+        assert!(webhook_host("https://relay.local/").is_none());
+    }
+    
+    #[test]
+    fn email_is_used_only_once_confirmed() {
+        let mut state = GameState::new();
+        state.players.insert("p1".to_string(), Player::new("p1", "Alpha"));
+        
+        let result = state.notification_settings("p1", Some(NotifyChange::Email(Some("alpha@example.com".to_string()))));
+        assert!(result.success);
+        assert!(state.players["p1"].notifications.email.is_none());
+        let checks = state.take_email_checks();
+        assert_eq!(checks.len(), 1);
+        assert_eq!(checks[0].address, "alpha@example.com");
+        
+        let wrong = state.notification_settings("p1", Some(NotifyChange::ConfirmEmail("nope".to_string())));
+        assert!(!wrong.success);
+        assert!(state.players["p1"].notifications.email.is_none());
+        
+        let code = checks[0].code.to_lowercase();
+        assert!(state.notification_settings("p1", Some(NotifyChange::ConfirmEmail(code))).success);
+        let prefs = &state.players["p1"].notifications;
+        assert_eq!(prefs.email.as_deref(), Some("alpha@example.com"));
+        assert!(prefs.pending_email.is_none());
+    }
+    
+    #[tokio::test]
+    async fn webhook_posts_json() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 1024];
+            while !String::from_utf8_lossy(&request).ends_with("{\"ok\":true}") {
+                let n = stream.read(&mut buf).await.unwrap();
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..n]);
+            }
+            stream.write_all(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n").await.unwrap();
+            String::from_utf8(request).unwrap()
+        });
+        
+        post_webhook(&format!("http://{}/hook", address), "{\"ok\":true}").await.unwrap();
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /hook HTTP/1.1\r\n"));
+        assert!(request.contains("Content-Type: application/json"));
+    }
+    
+    #[tokio::test]
+    async fn webhook_rejects_error_status() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 1024];
+            let _ = stream.read(&mut buf).await;
+            let _ = stream.write_all(b"HTTP/1.1 500 Internal Server Error\r\nContent-Length: 0\r\n\r\n").await;
+        });
+        
+        assert!(post_webhook(&format!("http://{}/", address), "{}").await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn email_goes_through_the_relay() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let relay = listener.local_addr().unwrap().to_string();
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (reader, mut writer) = stream.into_split();
+            let mut reader = BufReader::new(reader);
+            let mut transcript = String::new();
+            writer.write_all(b"220 mock ready\r\n").await.unwrap();
+            for reply in ["250-mock\r\n250 OK\r\n", "250 OK\r\n", "250 OK\r\n", "354 go ahead\r\n"] {
+                let mut line = String::new();
+                reader.read_line(&mut line).await.unwrap();
+                transcript.push_str(&line);
+                writer.write_all(reply.as_bytes()).await.unwrap();
+            }
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).await.unwrap();
+                transcript.push_str(&line);
+                if line == ".\r\n" {
+                    break;
+                }
+            }
+            writer.write_all(b"250 queued\r\n").await.unwrap();
+            let mut line = String::new();
+            reader.read_line(&mut line).await.unwrap();
+            transcript.push_str(&line);
+            writer.write_all(b"221 bye\r\n").await.unwrap();
+            transcript
+        });
+        
+        send_email_via(&relay, "command@test", "alpha@example.com", "Under attack", "Hold the line\n.hidden").await.unwrap();
+        let transcript = server.await.unwrap();
+        assert!(transcript.contains("MAIL FROM:<command@test>\r\n"));
+        assert!(transcript.contains("RCPT TO:<alpha@example.com>\r\n"));
+        assert!(transcript.contains("Subject: Under attack\r\n"));
+        assert!(transcript.contains("\r\n..hidden\r\n"));
+        assert!(transcript.ends_with("QUIT\r\n"));
+    }
+}
@@ -0,0 +1,354 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+use crate::game::{GameState, Command, CommandResult, GameEvent};
+use crate::simultaneous::TurnMode;
+
+const MAX_QUEUED_ORDERS: usize = 10; // Per player
+
+// An order waiting for a cycle boundary. Its AP is set aside when it's queued
+#[derive(Serialize, Deserialize, Clone)]
+pub struct QueuedOrder {
+    pub id: u32,
+    pub player_id: String,
+    pub text: String, // As the player typed it
+    pub command: Command,
+    pub reserved_ap: i32,
+    pub due_cycle: u32, // Runs when this cycle starts
+}
+
+impl Command {
+    // Only orders that act on ships and sectors make sense to schedule
+    pub fn is_queueable(&self) -> bool {
+        matches!(self,
+            Command::Move { .. } | Command::Scan { .. } | Command::Attack { .. } | Command::Build { .. }
+            | Command::DeclareControl { .. } | Command::SetGarrison { .. }
+            | Command::Cloak { .. } | Command::Decloak { .. })
+    }
+    
+    // The command as a player would type it
+    pub fn describe(&self) -> String {
+        match self {
+            Command::Move { ship_id, sector_id } => format!("move {} {}", ship_id, sector_id),
+            Command::Scan { sector_id, scanner_id: Some(scanner) } => format!("scan {} {}", sector_id, scanner),
+            Command::Scan { sector_id, scanner_id: None } => format!("scan {}", sector_id),
+            Command::Attack { target_ship_id } => format!("attack {}", target_ship_id),
+            Command::Build { ship_type, sector_id, name: Some(name) } => format!("build {:?} {} {}", ship_type, sector_id, name),
+            Command::Build { ship_type, sector_id, name: None } => format!("build {:?} {}", ship_type, sector_id),
+            Command::DeclareControl { sector_id, command_ship_id } => format!("declare {} {}", sector_id, command_ship_id),
+            Command::SetGarrison { sector_id, garrison_ship_id } => format!("garrison {} {}", sector_id, garrison_ship_id),
+            Command::Cloak { ship_id } => format!("cloak {}", ship_id),
+            Command::Decloak { ship_id } => format!("decloak {}", ship_id),
+            other => format!("{:?}", other),
+        }
+    }
+}
+
+impl GameState {
+    fn player_orders<'a>(&'a self, player_id: &'a str) -> impl Iterator<Item = &'a QueuedOrder> {
+        self.orders.iter().filter(move |o| o.player_id == player_id)
+    }
+    
+    // AP set aside for a player's queued orders. It still counts toward their AP cap
+    pub fn reserved_ap(&self, player_id: &str) -> i32 {
+        self.player_orders(player_id).map(|o| o.reserved_ap).sum()
+    }
+    
+    // Gives back an order's AP, never past the cap. Returns how much was refunded
+    fn refund_ap(&mut self, player_id: &str, amount: i32) -> i32 {
+        let player = match self.players.get_mut(player_id) {
+            Some(p) => p,
+            None => return 0,
+        };
+        let refunded = amount.min(player.max_ap - player.current_ap).max(0);
+        player.current_ap += refunded;
+        refunded
+    }
+    
+    // Queues a plan of one or more steps. Each step is tried out, in order, on a copy of the
+    // game where this player's earlier orders have already run; that checks the plan holds
+    // together and tells us exactly how much AP to set aside
+    pub fn queue_orders(&mut self, player_id: &str, steps: Vec<(String, Command)>, at_cycle: Option<u32>) -> CommandResult {
+        let due_cycle = at_cycle.unwrap_or(self.cycle_number + 1);
+        if due_cycle <= self.cycle_number {
+            return CommandResult {
+                success: false,
+                message: format!("Cycle {} has already started; orders can only be queued for later cycles", due_cycle),
+                ap_spent: 0,
+                game_state: self.clone(),
+            };
+        }
+        
+        if let Some((text, _)) = steps.iter().find(|(_, c)| !c.is_queueable()) {
+            return CommandResult {
+                success: false,
+                message: format!("Can't queue \"{}\": only move, scan, attack, build, declare, garrison, cloak and decloak can be queued", text),
+                ap_spent: 0,
+                game_state: self.clone(),
+            };
+        }
+        
+        let queued = self.player_orders(player_id).count();
+        if queued + steps.len() > MAX_QUEUED_ORDERS {
+            return CommandResult {
+                success: false,
+                message: format!("You can have at most {} queued orders ({} already queued)", MAX_QUEUED_ORDERS, queued),
+                ap_spent: 0,
+                game_state: self.clone(),
+            };
+        }
+        
+        let mut projection = self.clone();
+        let mut earlier: Vec<QueuedOrder> = projection.player_orders(player_id)
+            .filter(|o| o.due_cycle <= due_cycle)
+            .cloned()
+            .collect();
+        earlier.sort_by_key(|o| o.due_cycle);
+        for order in earlier {
+            projection.run_order(&order);
+        }
+        
+        let mut reservations = Vec::new();
+        for (text, command) in &steps {
+            let result = projection.apply_command(player_id, command.clone());
+            if !result.success {
+                return CommandResult {
+                    success: false,
+                    message: format!("Can't queue \"{}\": {}", text, result.message),
+                    ap_spent: 0,
+                    game_state: self.clone(),
+                };
+            }
+            reservations.push(result.ap_spent);
+        }
+        
+        let reserved: i32 = reservations.iter().sum();
+        let mut report = format!("Queued for cycle {}:\n", due_cycle);
+        for ((text, command), reserved_ap) in steps.into_iter().zip(reservations) {
+            self.next_order_id += 1;
+            report.push_str(&format!("#{} {} ({} AP reserved)\n", self.next_order_id, text, reserved_ap));
+            self.orders.push(QueuedOrder {
+                id: self.next_order_id,
+                player_id: player_id.to_string(),
+                text,
+                command,
+                reserved_ap,
+                due_cycle,
+            });
+        }
+        self.players.get_mut(player_id).unwrap().current_ap -= reserved;
+        
+        CommandResult {
+            success: true,
+            message: report,
+            ap_spent: reserved,
+            game_state: self.clone(),
+        }
+    }
+    
+    pub fn list_orders(&self, player_id: &str) -> CommandResult {
+        let orders: Vec<&QueuedOrder> = self.player_orders(player_id).collect();
+        if orders.is_empty() {
+            return CommandResult {
+                success: true,
+                message: "No queued orders".to_string(),
+                ap_spent: 0,
+                game_state: self.clone(),
+            };
+        }
+        
+        let mut report = "=== Queued Orders ===\n".to_string();
+        for (position, order) in orders.iter().enumerate() {
+            report.push_str(&format!(
+                "{}. #{} {} - cycle {} ({} AP reserved)\n",
+                position + 1, order.id, order.text, order.due_cycle, order.reserved_ap
+            ));
+        }
+        let reserved: i32 = orders.iter().map(|o| o.reserved_ap).sum();
+        report.push_str(&format!("Total reserved: {} AP", reserved));
+        
+        CommandResult {
+            success: true,
+            message: report,
+            ap_spent: 0,
+            game_state: self.clone(),
+        }
+    }
+    
+    pub fn cancel_order(&mut self, player_id: &str, order_id: u32) -> CommandResult {
+        let index = match self.orders.iter().position(|o| o.id == order_id && o.player_id == player_id) {
+            Some(i) => i,
+            None => return CommandResult {
+                success: false,
+                message: format!("You have no queued order #{}", order_id),
+                ap_spent: 0,
+                game_state: self.clone(),
+            }
+        };
+        
+        let order = self.orders.remove(index);
+        let refunded = self.refund_ap(player_id, order.reserved_ap);
+        
+        CommandResult {
+            success: true,
+            message: format!("Cancelled #{} {}; {} AP refunded", order.id, order.text, refunded),
+            ap_spent: 0,
+            game_state: self.clone(),
+        }
+    }
+    
+    // Moves an order to a new place (1 = first) in the player's own queue
+    pub fn reorder_order(&mut self, player_id: &str, order_id: u32, position: usize) -> CommandResult {
+        let index = match self.orders.iter().position(|o| o.id == order_id && o.player_id == player_id) {
+            Some(i) => i,
+            None => return CommandResult {
+                success: false,
+                message: format!("You have no queued order #{}", order_id),
+                ap_spent: 0,
+                game_state: self.clone(),
+            }
+        };
+        
+        let count = self.player_orders(player_id).count();
+        if position == 0 || position > count {
+            return CommandResult {
+                success: false,
+                message: format!("Position must be between 1 and {}", count),
+                ap_spent: 0,
+                game_state: self.clone(),
+            };
+        }
+        
+        let order = self.orders.remove(index);
+        // Insert before whichever of our orders currently holds that position, or after our last
+        let mut ours = self.orders.iter().enumerate()
+            .filter(|(_, o)| o.player_id == player_id)
+            .map(|(i, _)| i);
+        let insert_at = match ours.nth(position - 1) {
+            Some(i) => i,
+            None => self.orders.iter().rposition(|o| o.player_id == player_id).map_or(self.orders.len(), |i| i + 1),
+        };
+        let text = order.text.clone();
+        self.orders.insert(insert_at, order);
+        
+        CommandResult {
+            success: true,
+            message: format!("#{} {} is now order {} of {}", order_id, text, position, count),
+            ap_spent: 0,
+            game_state: self.clone(),
+        }
+    }
+    
+    // Hands back an order's reserved AP and carries it out
+    fn run_order(&mut self, order: &QueuedOrder) -> CommandResult {
+        let player = match self.players.get_mut(&order.player_id) {
+            Some(p) => p,
+            None => return CommandResult {
+                success: false,
+                message: "Player not found".to_string(),
+                ap_spent: 0,
+                game_state: self.clone(),
+            }
+        };
+        player.current_ap += order.reserved_ap;
+        
+        // The season can end part way through a cycle's orders
+        if self.outcome.is_some() {
+            return CommandResult {
+                success: false,
+                message: format!("Season {} is over", self.season),
+                ap_spent: 0,
+                game_state: self.clone(),
+            };
+        }
+        self.apply_command(&order.player_id, order.command.clone())
+    }
+    
+    // Takes an order off the books without running it
+    pub fn drop_order(&mut self, order: &QueuedOrder, reason: &str) {
+        self.refund_ap(&order.player_id, order.reserved_ap);
+        self.events.push(GameEvent {
+            player_id: order.player_id.clone(),
+            message: format!("Order #{} {} failed, AP refunded: {}", order.id, order.text, reason),
+        });
+    }
+    
+    // Runs every order due this cycle
+    pub fn execute_due_orders(&mut self) {
+        let (due, waiting): (Vec<QueuedOrder>, Vec<QueuedOrder>) = std::mem::take(&mut self.orders)
+            .into_iter()
+            .partition(|o| o.due_cycle <= self.cycle_number);
+        self.orders = waiting;
+        
+        if self.settings.turn_mode == TurnMode::Simultaneous {
+            self.resolve_simultaneous(due);
+        } else {
+            self.run_interleaved(due);
+        }
+    }
+    
+    // Players take turns one order at a time, and who goes first rotates each cycle, so no
+    // one's whole plan lands before anyone else's
+    pub fn run_interleaved(&mut self, due: Vec<QueuedOrder>) {
+        if due.is_empty() {
+            return;
+        }
+        
+        let mut player_ids: Vec<String> = due.iter().map(|o| o.player_id.clone()).collect();
+        player_ids.sort();
+        player_ids.dedup();
+        let start = self.cycle_number as usize % player_ids.len();
+        player_ids.rotate_left(start);
+        
+        let mut queues: Vec<VecDeque<QueuedOrder>> = player_ids.iter()
+            .map(|id| due.iter().filter(|o| &o.player_id == id).cloned().collect())
+            .collect();
+        
+        while queues.iter().any(|q| !q.is_empty()) {
+            for queue in queues.iter_mut() {
+                let order = match queue.pop_front() {
+                    Some(o) => o,
+                    None => continue,
+                };
+                let result = self.run_order(&order);
+                let outcome = if result.success { "done" } else { "failed, AP refunded" };
+                self.events.push(GameEvent {
+                    player_id: order.player_id.clone(),
+                    message: format!("Order #{} {} ({}):\n{}", order.id, order.text, outcome, result.message),
+                });
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{Command, GameState, Player, ShipType};
+    
+    #[test]
+    fn reserved_ap_counts_toward_the_cap() {
+        let mut state = GameState::new();
+        state.players.insert("p1".to_string(), Player::new("p1", "Alpha"));
+        let ship_id = state.spawn_ship("p1", ShipType::Frigate, "earth-1", "Pioneer").unwrap();
+        
+        let step = Command::Move { ship_id: ship_id.clone(), sector_id: "earth-2".to_string() };
+        let result = state.execute_command("p1", Command::Queue {
+            steps: vec![(format!("move {} earth-2", ship_id), step)],
+            at_cycle: Some(50),
+        });
+        assert!(result.success, "{}", result.message);
+        let reserved = state.reserved_ap("p1");
+        assert!(reserved > 0);
+        
+        for _ in 0..3 {
+            state.process_cycle();
+        }
+        let order_id = state.orders[0].id;
+        state.execute_command("p1", Command::CancelOrder { order_id });
+        
+        let player = &state.players["p1"];
+        assert!(player.current_ap <= player.max_ap);
+        assert_eq!(state.reserved_ap("p1"), 0);
+    }
+}
@@ -0,0 +1,170 @@
+use crate::game::{GameState, GameEvent, Ship, ShipType};
+
+// Owner ID of every NPC ship. Never registered as a player, so it can't log in or be traded with
+pub const PIRATE_PLAYER_ID: &str = "npc-pirates";
+
+const GRACE_CYCLES: u32 = 2; // No raiders before this cycle
+const MAX_PIRATES: usize = 8;
+
+impl GameState {
+    // NPC turn, run once per cycle: spawn, advance on weak targets, then raid
+    pub fn process_pirates(&mut self) {
+        self.spawn_pirates();
+        self.move_pirates();
+        self.pirate_raids();
+    }
+    
+    fn pirate_ships(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.ships.values()
+            .filter(|s| s.owner == PIRATE_PLAYER_ID)
+            .map(|s| s.id.clone())
+            .collect();
+        ids.sort();
+        ids
+    }
+    
+    // Tension rises with claimed territory and the number of warships on the map
+    fn map_tension(&self) -> usize {
+        let controlled = self.sectors.iter().filter(|s| s.controlled_by.is_some()).count();
+        let player_ships = self.ships.values().filter(|s| s.owner != PIRATE_PLAYER_ID).count();
+        controlled + player_ships / 4
+    }
+    
+    fn spawn_pirates(&mut self) {
+        if self.cycle_number < GRACE_CYCLES {
+            return;
+        }
+        
+        let cap = (1 + self.cycle_number as usize / 4 + self.map_tension() / 2).min(MAX_PIRATES);
+        if self.pirate_ships().len() >= cap {
+            return;
+        }
+        
+        // Only uncontrolled sectors with no player ships in them
+        let candidates: Vec<usize> = self.sectors.iter().enumerate()
+            .filter(|(_, s)| s.controlled_by.is_none())
+            .filter(|(_, s)| s.ships_present.iter().all(|id| {
+                self.ships.get(id).is_none_or(|ship| ship.owner == PIRATE_PLAYER_ID)
+            }))
+            .map(|(i, _)| i)
+            .collect();
+        if candidates.is_empty() {
+            return;
+        }
+        let sector_index = candidates[(roll(self.cycle_number as u64) % candidates.len() as u64) as usize];
+        
+        // Raiders get heavier as the game goes on
+        let ship_type = match self.cycle_number {
+            0..=5 => ShipType::Interceptor,
+            6..=11 => ShipType::Corvette,
+            12..=17 => ShipType::Frigate,
+            _ => ShipType::Destroyer,
+        };
+        
+        self.next_pirate_id += 1;
+        let ship_id = format!("pirate-{}", self.next_pirate_id);
+        let name = format!("Raider {}", self.next_pirate_id);
+        let ship = Ship::new(&ship_id, &name, ship_type, PIRATE_PLAYER_ID, &self.sectors[sector_index].id);
+        self.ships.insert(ship_id.clone(), ship);
+        self.sectors[sector_index].ships_present.push(ship_id);
+    }
+    
+    // Each raider takes one step towards the weakest player-held position
+    fn move_pirates(&mut self) {
+        for ship_id in self.pirate_ships() {
+            let current = self.ships[&ship_id].current_sector.clone();
+            
+            let target = match self.weakest_player_sector(&current) {
+                Some(t) if t != current => t,
+                _ => continue,
+            };
+            let distance = self.sector_distance(&current, &target).unwrap_or(0);
+            
+            let next = match self.adjacent_sectors(&current).into_iter()
+                .find(|s| self.sector_distance(s, &target).is_some_and(|d| d < distance)) {
+                Some(n) => n,
+                None => continue,
+            };
+            
+            if let Some(sector) = self.sectors.iter_mut().find(|s| s.id == current) {
+                sector.ships_present.retain(|id| id != &ship_id);
+            }
+            if let Some(sector) = self.sectors.iter_mut().find(|s| s.id == next) {
+                sector.ships_present.push(ship_id.clone());
+            }
+            self.ships.get_mut(&ship_id).unwrap().current_sector = next;
+        }
+    }
+    
+    // Sectors a player controls or has ships in, scored by defending HP; nearest wins ties
+    fn weakest_player_sector(&self, from: &str) -> Option<String> {
+        self.sectors.iter()
+            .filter_map(|sector| {
+                let defence: i32 = sector.ships_present.iter()
+                    .filter_map(|id| self.ships.get(id))
+                    .filter(|s| s.owner != PIRATE_PLAYER_ID)
+                    .map(|s| s.hp)
+                    .sum();
+                if sector.controlled_by.is_none() && defence == 0 {
+                    return None;
+                }
+                let distance = self.sector_distance(from, &sector.id)?;
+                Some((defence, distance, sector.id.clone()))
+            })
+            .min()
+            .map(|(_, _, id)| id)
+    }
+    
+    // Raiders sharing a sector with player ships gang up on the weakest one they can see
+    fn pirate_raids(&mut self) {
+        for sector_index in 0..self.sectors.len() {
+            let sector = &self.sectors[sector_index];
+            let mut raiders: Vec<String> = sector.ships_present.iter()
+                .filter(|id| self.ships.get(*id).is_some_and(|s| s.owner == PIRATE_PLAYER_ID))
+                .cloned()
+                .collect();
+            raiders.sort();
+            
+            if raiders.is_empty() {
+                continue;
+            }
+            
+            let target = self.visible_ships(sector, PIRATE_PLAYER_ID).into_iter()
+                .filter(|s| s.owner != PIRATE_PLAYER_ID)
+                .min_by_key(|s| (s.hp, s.id.clone()))
+                .map(|s| s.id.clone());
+            if let Some(target) = target {
+                self.resolve_combat(&raiders, &target);
+            }
+        }
+    }
+    
+    // Destroying a raider pays credits and XP scaled by its hull
+    pub fn pay_bounty(&mut self, killer_id: &str, destroyed: &Ship) {
+        if destroyed.owner != PIRATE_PLAYER_ID {
+            return;
+        }
+        let player = match self.players.get_mut(killer_id) {
+            Some(p) => p,
+            None => return,
+        };
+        
+        let credits = destroyed.max_hp / 2;
+        let xp = (destroyed.max_hp / 5) as u32;
+        player.credits += credits;
+        player.xp += xp;
+        
+        self.events.push(GameEvent {
+            player_id: killer_id.to_string(),
+            message: format!("Bounty for {}: +{} credits, +{} XP", destroyed.name, credits, xp),
+        });
+    }
+}
+
+// Cheap deterministic scramble so spawns move around the map
+fn roll(seed: u64) -> u64 {
+    let mut x = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
@@ -0,0 +1,209 @@
+use serde::{Deserialize, Serialize};
+use std::sync::{LazyLock, RwLock};
+
+use crate::game::{GameState, ShipType};
+
+pub const SHIPS_FILE: &str = "../config/ships.json";
+
+// Compiled-in copy of the shipped definitions, used when the file is missing
+const BUILTIN_SHIPS: &str = include_str!("../../config/ships.json");
+
+static CATALOG: LazyLock<RwLock<ShipCatalog>> = LazyLock::new(|| {
+    RwLock::new(ShipCatalog::parse(BUILTIN_SHIPS).expect("built-in ship definitions are invalid"))
+});
+
+const ALL_CLASSES: [ShipType; 12] = [
+    ShipType::ScoutDrone, ShipType::MiningVessel, ShipType::Interceptor,
+    ShipType::Corvette, ShipType::Frigate, ShipType::SupplyShip,
+    ShipType::Destroyer, ShipType::GarrisonShip, ShipType::Cruiser,
+    ShipType::Battleship, ShipType::CommandShip, ShipType::Carrier,
+];
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Ability {
+    Cloak, // Can cloak (needs stealth and a cloak AP cost)
+    PassiveScan, // Reports adjacent sectors every cycle
+    ClaimSector, // Can declare control of a sector
+    Garrison, // Can hold a sector as its garrison
+    Mine, // Extracts ore every cycle
+    Supply, // Refuels and supplies friendly ships in its sector
+}
+
+// One ship class as defined in the data file
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ShipClass {
+    pub class: ShipType,
+    pub tier: u32,
+    pub hp: i32,
+    pub damage: i32,
+    pub ap_cost: i32,
+    pub cost: i32, // Credits to build
+    pub sensor_range: i32,
+    pub scan_cost: i32,
+    pub hp_accuracy: i32, // %
+    pub stealth: i32, // 0 means no cloak
+    pub detection: i32,
+    pub cloak_ap_cost: i32, // Per cycle
+    pub cargo: i32,
+    // Newer than the original file format, so older files still load: they get a typical
+    // speed and range, and no upkeep
+    #[serde(default = "default_speed")]
+    pub speed: i32, // Hops per cycle
+    #[serde(default = "default_fuel_capacity")]
+    pub fuel_capacity: i32, // Hops of travel on a full tank
+    #[serde(default)]
+    pub upkeep: i32, // Credits per cycle
+    #[serde(default)]
+    pub abilities: Vec<Ability>,
+}
+
+fn default_speed() -> i32 {
+    4
+}
+
+fn default_fuel_capacity() -> i32 {
+    10
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ShipCatalog {
+    pub ships: Vec<ShipClass>,
+}
+
+// What happens to ships already in play when their class definition changes
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ShipStatsPolicy {
+    #[default]
+    Snapshot, // Ships keep the stats they were built with
+    Rederive, // Ships pick up the new stats; damage taken carries over proportionally
+}
+
+impl ShipCatalog {
+    pub fn parse(json: &str) -> Result<Self, String> {
+        let catalog: ShipCatalog = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        catalog.validate()?;
+        Ok(catalog)
+    }
+    
+    // Every class defined exactly once, with stats the rules can work with
+    fn validate(&self) -> Result<(), String> {
+        for class in &ALL_CLASSES {
+            let count = self.ships.iter().filter(|s| &s.class == class).count();
+            if count != 1 {
+                return Err(format!("{:?} is defined {} times, expected once", class, count));
+            }
+        }
+        
+        for ship in &self.ships {
+            let problem = if !(1..=4).contains(&ship.tier) {
+                Some("tier must be 1-4")
+            } else if ship.hp <= 0 {
+                Some("hp must be positive")
+            } else if ship.damage < 0 || ship.cost < 0 || ship.cargo < 0 || ship.upkeep < 0 {
+                Some("damage, cost, cargo and upkeep can't be negative")
+            } else if ship.ap_cost < 1 || ship.scan_cost < 0 {
+                Some("ap_cost must be at least 1 and scan_cost can't be negative")
+            } else if ship.speed < 1 || ship.fuel_capacity < 1 {
+                Some("speed and fuel_capacity must be at least 1")
+            } else if ship.sensor_range < 0 {
+                Some("sensor_range can't be negative")
+            } else if [ship.hp_accuracy, ship.stealth, ship.detection].iter().any(|v| !(0..=100).contains(v)) {
+                Some("hp_accuracy, stealth and detection must be 0-100")
+            } else if ship.abilities.contains(&Ability::Cloak) && (ship.stealth == 0 || ship.cloak_ap_cost <= 0) {
+                Some("cloak needs stealth and a cloak_ap_cost")
+            } else {
+                None
+            };
+            if let Some(problem) = problem {
+                return Err(format!("{:?}: {}", ship.class, problem));
+            }
+        }
+        
+        Ok(())
+    }
+}
+
+// Looks up a class in the active catalog
+pub fn ship_class(ship_type: &ShipType) -> ShipClass {
+    let catalog = CATALOG.read().unwrap();
+    catalog.ships.iter()
+        .find(|s| &s.class == ship_type)
+        .cloned()
+        .expect("validated catalog defines every class")
+}
+
+// Reads and validates the ship file, then makes it the active catalog. A bad file
+// leaves the current definitions in place
+pub async fn load_ship_catalog() -> Result<usize, String> {
+    let json = match tokio::fs::read_to_string(SHIPS_FILE).await {
+        Ok(json) => json,
+        Err(e) => return Err(format!("Can't read {}: {}", SHIPS_FILE, e)),
+    };
+    let catalog = ShipCatalog::parse(&json).map_err(|e| format!("{}: {}", SHIPS_FILE, e))?;
+    let count = catalog.ships.len();
+    *CATALOG.write().unwrap() = catalog;
+    Ok(count)
+}
+
+impl GameState {
+    // Applies the active catalog to ships in play, following the game's policy.
+    // Returns how many ships changed
+    pub fn apply_ship_catalog(&mut self) -> usize {
+        if self.settings.ship_stats_policy == ShipStatsPolicy::Snapshot {
+            return 0;
+        }
+        
+        let mut changed = 0;
+        for ship in self.ships.values_mut() {
+            let (max_hp, damage, ap_cost) = ship.ship_type.get_stats();
+            if (ship.max_hp, ship.damage, ship.ap_cost) == (max_hp, damage, ap_cost) {
+                continue;
+            }
+            ship.hp = (ship.hp * max_hp / ship.max_hp.max(1)).clamp(1, max_hp);
+            ship.max_hp = max_hp;
+            ship.damage = damage;
+            ship.ap_cost = ap_cost;
+            changed += 1;
+        }
+        changed
+    }
+}
+
+impl ShipType {
+    // Case-insensitive class name, with or without dashes or underscores ("mining-vessel")
+    pub fn parse(name: &str) -> Option<ShipType> {
+        let wanted: String = name.chars().filter(|c| *c != '-' && *c != '_').collect();
+        ALL_CLASSES.iter()
+            .find(|c| format!("{:?}", c).eq_ignore_ascii_case(&wanted))
+            .cloned()
+    }
+    
+    pub fn has_ability(&self, ability: Ability) -> bool {
+        ship_class(self).abilities.contains(&ability)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn files_without_newer_fields_still_load() {
+        let mut json: serde_json::Value = serde_json::from_str(BUILTIN_SHIPS).unwrap();
+        for ship in json["ships"].as_array_mut().unwrap() {
+            let ship = ship.as_object_mut().unwrap();
+            ship.remove("speed");
+            ship.remove("fuel_capacity");
+            ship.remove("upkeep");
+        }
+        
+        let catalog = ShipCatalog::parse(&json.to_string()).unwrap();
+        let frigate = catalog.ships.iter().find(|s| s.class == ShipType::Frigate).unwrap();
+        assert_eq!((frigate.speed, frigate.fuel_capacity, frigate.upkeep), (4, 10, 0));
+    }
+}
@@ -0,0 +1,264 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::game::{GameState, Command, CommandResult};
+use crate::orders::QueuedOrder;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TurnMode {
+    #[default]
+    RealTime, // Commands take effect as soon as they're sent
+    Simultaneous, // Orders are collected during the cycle and resolved together when it ends
+}
+
+impl Command {
+    // In a simultaneous game these wait for the end of the cycle; scans still report straight away
+    pub fn resolves_at_cycle(&self) -> bool {
+        self.is_queueable() && !matches!(self, Command::Scan { .. })
+    }
+}
+
+impl GameState {
+    fn player_name(&self, player_id: &str) -> String {
+        self.players.get(player_id).map_or_else(|| player_id.to_string(), |p| p.name.clone())
+    }
+    
+    // Checks an order the same way as if it ran now, then holds it for the end of the cycle
+    pub fn submit_order(&mut self, player_id: &str, command: Command) -> CommandResult {
+        let result = self.queue_orders(player_id, vec![(command.describe(), command)], None);
+        if !result.success {
+            return result;
+        }
+        
+        CommandResult {
+            success: true,
+            message: format!("{}Orders from every player resolve together when the cycle ends", result.message),
+            ap_spent: result.ap_spent,
+            game_state: self.clone(),
+        }
+    }
+    
+    // Resolves a cycle's orders in phases: movement, then combat, then control, then the rest.
+    // Within a phase players take turns as usual
+    pub fn resolve_simultaneous(&mut self, due: Vec<QueuedOrder>) {
+        let mut moves = Vec::new();
+        let mut attacks = Vec::new();
+        let mut controls = Vec::new();
+        let mut rest = Vec::new();
+        for order in due {
+            match order.command {
+                Command::Move { .. } => moves.push(order),
+                Command::Attack { .. } => attacks.push(order),
+                Command::DeclareControl { .. } | Command::SetGarrison { .. } => controls.push(order),
+                _ => rest.push(order),
+            }
+        }
+        
+        let moves = self.resolve_head_on(moves);
+        self.run_interleaved(moves);
+        self.run_interleaved(attacks);
+        let controls = self.resolve_contested(controls);
+        self.run_interleaved(controls);
+        self.run_interleaved(rest);
+    }
+    
+    // Hostile ships swapping sectors meet in the middle. The side with more HP plus firepower
+    // on that lane gets through; on a tie neither does
+    fn resolve_head_on(&mut self, moves: Vec<QueuedOrder>) -> Vec<QueuedOrder> {
+        // Where each move starts from, following any earlier moves of the same ship
+        let mut positions: HashMap<String, String> = HashMap::new();
+        let mut legs = Vec::new();
+        for order in &moves {
+            if let Command::Move { ship_id, sector_id } = &order.command {
+                let from = match positions.get(ship_id) {
+                    Some(p) => p.clone(),
+                    None => match self.ships.get(ship_id) {
+                        Some(s) => s.current_sector.clone(),
+                        None => String::new(),
+                    },
+                };
+                positions.insert(ship_id.clone(), sector_id.clone());
+                legs.push((order.player_id.clone(), ship_id.clone(), from, sector_id.clone()));
+            }
+        }
+        
+        let strength = |player: &str, from: &str, to: &str| -> i32 {
+            legs.iter()
+                .filter(|(p, _, f, t)| p == player && f == from && t == to)
+                .filter_map(|(_, ship_id, _, _)| self.ships.get(ship_id))
+                .map(|s| s.hp + s.damage)
+                .sum()
+        };
+        
+        let mut blocked = Vec::new();
+        for (index, (player, _, from, to)) in legs.iter().enumerate() {
+            let ours = strength(player, from, to);
+            let opponent = legs.iter()
+                .filter(|(p, _, f, t)| p != player && f == to && t == from)
+                .filter(|(p, _, _, _)| self.treaty_between(player, p).is_none())
+                .map(|(p, _, _, _)| (p.clone(), strength(p, to, from)))
+                .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)));
+            if let Some((opponent, theirs)) = opponent {
+                if theirs >= ours {
+                    blocked.push((index, opponent));
+                }
+            }
+        }
+        
+        let mut passed = Vec::new();
+        for (index, order) in moves.into_iter().enumerate() {
+            match blocked.iter().find(|(i, _)| *i == index) {
+                Some((_, opponent)) => {
+                    let reason = format!("met {}'s ships head-on and was turned back", self.player_name(opponent));
+                    self.drop_order(&order, &reason);
+                }
+                None => passed.push(order),
+            }
+        }
+        passed
+    }
+    
+    // When several players try to claim the same sector, whoever has the most HP there
+    // after combat takes it; on a tie nobody does
+    fn resolve_contested(&mut self, controls: Vec<QueuedOrder>) -> Vec<QueuedOrder> {
+        let mut claims: HashMap<String, Vec<String>> = HashMap::new();
+        for order in &controls {
+            if let Command::DeclareControl { sector_id, .. } = &order.command {
+                let claimants = claims.entry(sector_id.clone()).or_default();
+                if !claimants.contains(&order.player_id) {
+                    claimants.push(order.player_id.clone());
+                }
+            }
+        }
+        
+        let mut winners: HashMap<String, Option<String>> = HashMap::new();
+        for (sector_id, claimants) in claims.iter().filter(|(_, c)| c.len() > 1) {
+            let hp_in_sector = |player: &str| -> i32 {
+                self.ships.values()
+                    .filter(|s| s.owner == player && &s.current_sector == sector_id && !s.in_transit())
+                    .map(|s| s.hp)
+                    .sum()
+            };
+            let mut standings: Vec<(i32, &String)> = claimants.iter().map(|p| (hp_in_sector(p), p)).collect();
+            standings.sort_by_key(|s| std::cmp::Reverse(s.0));
+            let winner = if standings[0].0 > standings[1].0 { Some(standings[0].1.clone()) } else { None };
+            winners.insert(sector_id.clone(), winner);
+        }
+        
+        let mut kept = Vec::new();
+        for order in controls {
+            let contested = match &order.command {
+                Command::DeclareControl { sector_id, .. } => winners.get(sector_id),
+                _ => None,
+            };
+            match contested {
+                Some(Some(winner)) if *winner == order.player_id => kept.push(order),
+                Some(Some(winner)) => {
+                    let reason = format!("{} had the stronger claim", self.player_name(winner));
+                    self.drop_order(&order, &reason);
+                }
+                Some(None) => self.drop_order(&order, "rival claims were evenly matched, so nobody took the sector"),
+                None => kept.push(order),
+            }
+        }
+        kept
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diplomacy::{Treaty, TreatyKind};
+    use crate::game::{Player, ShipType};
+    
+    fn two_player_game() -> GameState {
+        let mut state = GameState::new();
+        state.players.insert("p1".to_string(), Player::new("p1", "Alpha"));
+        state.players.insert("p2".to_string(), Player::new("p2", "Beta"));
+        state
+    }
+    
+    fn order(id: u32, player_id: &str, command: Command) -> QueuedOrder {
+        QueuedOrder {
+            id,
+            player_id: player_id.to_string(),
+            text: command.describe(),
+            command,
+            reserved_ap: 10,
+            due_cycle: 1,
+        }
+    }
+    
+    fn swap(state: &mut GameState, ours: ShipType, theirs: ShipType) -> Vec<String> {
+        let a = state.spawn_ship("p1", ours, "earth-1", "Alpha One").unwrap();
+        let b = state.spawn_ship("p2", theirs, "earth-2", "Beta One").unwrap();
+        let moves = vec![
+            order(1, "p1", Command::Move { ship_id: a, sector_id: "earth-2".to_string() }),
+            order(2, "p2", Command::Move { ship_id: b, sector_id: "earth-1".to_string() }),
+        ];
+        state.resolve_head_on(moves).into_iter().map(|o| o.player_id).collect()
+    }
+    
+    #[test]
+    fn head_on_the_stronger_side_gets_through() {
+        let mut state = two_player_game();
+        assert_eq!(swap(&mut state, ShipType::Destroyer, ShipType::Interceptor), vec!["p1"]);
+        
+        let mut state = two_player_game();
+        assert_eq!(swap(&mut state, ShipType::Interceptor, ShipType::Destroyer), vec!["p2"]);
+    }
+    
+    #[test]
+    fn head_on_a_tie_turns_both_back() {
+        let mut state = two_player_game();
+        state.players.get_mut("p1").unwrap().current_ap = 40;
+        assert!(swap(&mut state, ShipType::Frigate, ShipType::Frigate).is_empty());
+        // Both reservations come back
+        assert_eq!(state.players["p1"].current_ap, 50);
+        assert_eq!(state.players["p2"].current_ap, 60);
+    }
+    
+    #[test]
+    fn treaty_partners_pass_each_other() {
+        let mut state = two_player_game();
+        state.relations.push(Treaty {
+            kind: TreatyKind::Truce,
+            proposer: "p1".to_string(),
+            partner: "p2".to_string(),
+            accepted: true,
+            shared_victory: false,
+            expires_cycle: None,
+        });
+        assert_eq!(swap(&mut state, ShipType::Destroyer, ShipType::Interceptor), vec!["p1", "p2"]);
+    }
+    
+    fn claims(state: &mut GameState, ours: ShipType, theirs: ShipType) -> Vec<String> {
+        let a = state.spawn_ship("p1", ours, "earth-2", "Alpha One").unwrap();
+        let b = state.spawn_ship("p2", theirs, "earth-2", "Beta One").unwrap();
+        let controls = vec![
+            order(1, "p1", Command::DeclareControl { sector_id: "earth-2".to_string(), command_ship_id: a }),
+            order(2, "p2", Command::DeclareControl { sector_id: "earth-2".to_string(), command_ship_id: b }),
+        ];
+        state.resolve_contested(controls).into_iter().map(|o| o.player_id).collect()
+    }
+    
+    #[test]
+    fn contested_claims_go_to_the_most_hp() {
+        let mut state = two_player_game();
+        assert_eq!(claims(&mut state, ShipType::Destroyer, ShipType::Interceptor), vec!["p1"]);
+        
+        let mut state = two_player_game();
+        assert!(claims(&mut state, ShipType::Frigate, ShipType::Frigate).is_empty());
+    }
+    
+    #[test]
+    fn refunds_stay_within_the_cap() {
+        let mut state = two_player_game();
+        let max_ap = state.players["p1"].max_ap;
+        state.players.get_mut("p1").unwrap().current_ap = max_ap - 1;
+        
+        swap(&mut state, ShipType::Frigate, ShipType::Frigate);
+        assert_eq!(state.players["p1"].current_ap, max_ap);
+    }
+}
@@ -0,0 +1,248 @@
+use axum::extract::ws::{Message, WebSocket};
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tokio::time::{Duration, Instant};
+use chrono::{DateTime, Utc};
+
+use std::collections::HashMap;
+
+use crate::game::{GameState, GameEvent, Resource, Sector, Ship};
+use crate::websocket::{GameServer, Outbound};
+
+// Outbound recipient, and event player ID, for the spectator stream. Player sockets never
+// match it, since it isn't a valid player ID
+pub const SPECTATORS: &str = "*spectators*";
+
+// The stream can't be watched live; otherwise a player could spectate their own game in a
+// second tab and see every cloaked ship
+pub const MIN_SPECTATOR_DELAY_SECS: u64 = 60;
+
+#[derive(Deserialize)]
+struct SpectatorMessage {
+    #[serde(rename = "type")]
+    msg_type: String,
+}
+
+// The whole, unfiltered world: every ship with its true HP, cloaked or not. Commanders
+// appear by name only, in ship owners and sector controllers too
+#[derive(Serialize)]
+struct SpectatorWorld {
+    #[serde(rename = "type")]
+    msg_type: String,
+    season: u32,
+    cycle_number: u32,
+    players: Vec<SpectatorPlayer>,
+    sectors: Vec<Sector>,
+    ships: Vec<Ship>,
+}
+
+#[derive(Serialize)]
+struct SpectatorPlayer {
+    name: String,
+    current_ap: i32,
+    max_ap: i32,
+    credits: i32,
+    level: u32,
+    xp: u32,
+    reputation: i32,
+    kills: u32,
+    ship_count: usize,
+    resources: HashMap<Resource, i32>,
+}
+
+// A combat report or notice, labelled with who it concerned
+#[derive(Serialize)]
+struct SpectatorEvent {
+    #[serde(rename = "type")]
+    msg_type: String,
+    player_name: Option<String>,
+    message: String,
+    at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+struct SpectatorNotice {
+    #[serde(rename = "type")]
+    msg_type: String,
+    message: String,
+}
+
+impl GameServer {
+    pub async fn handle_spectator(&self, socket: WebSocket) {
+        let (mut sender, mut receiver) = socket.split();
+        let mut broadcast_rx = self.broadcast_tx.subscribe();
+        
+        let (delay_secs, welcome) = {
+            let game_state = self.game_state.read().await;
+            (game_state.settings.spectator_delay_secs.max(MIN_SPECTATOR_DELAY_SECS), spectator_world(&game_state))
+        };
+        let delay = Duration::from_secs(delay_secs);
+        
+        let notice = SpectatorNotice {
+            msg_type: "spectate_welcome".to_string(),
+            message: format!("Spectating with a {} second delay", delay_secs),
+        };
+        let _ = sender.send(Message::Text(serde_json::to_string(&notice).unwrap())).await;
+        
+        // Messages are buffered off the broadcast channel straight away, so a long delay
+        // never makes this receiver lag behind and drop the stream
+        let (delayed_tx, mut delayed_rx) = mpsc::unbounded_channel::<(Instant, String)>();
+        let reply_tx = delayed_tx.clone();
+        let _ = delayed_tx.send((Instant::now(), welcome));
+        
+        let buffer_task = tokio::spawn(async move {
+            while let Ok(msg) = broadcast_rx.recv().await {
+                let for_spectators = match msg.recipient.as_deref() {
+                    None => true,
+                    Some(r) => r == SPECTATORS,
+                };
+                if for_spectators && delayed_tx.send((Instant::now(), msg.payload)).is_err() {
+                    break;
+                }
+            }
+        });
+        
+        let forward_task = tokio::spawn(async move {
+            while let Some((received, payload)) = delayed_rx.recv().await {
+                tokio::time::sleep_until(received + delay).await;
+                if sender.send(Message::Text(payload)).await.is_err() {
+                    break;
+                }
+            }
+        });
+        
+        // Spectators are read-only; the only thing they may send is a ping
+        while let Some(Ok(msg)) = receiver.next().await {
+            match msg {
+                Message::Text(text) => {
+                    let msg_type = serde_json::from_str::<SpectatorMessage>(&text)
+                        .map(|m| m.msg_type)
+                        .unwrap_or_default();
+                    if msg_type != "ping" {
+                        // Only this spectator hears about it, and without the delay
+                        let notice = SpectatorNotice {
+                            msg_type: "error".to_string(),
+                            message: "Spectators can't send commands or chat".to_string(),
+                        };
+                        let due = Instant::now().checked_sub(delay).unwrap_or_else(Instant::now);
+                        let _ = reply_tx.send((due, serde_json::to_string(&notice).unwrap()));
+                    }
+                }
+                Message::Close(_) => break,
+                _ => {}
+            }
+        }
+        
+        buffer_task.abort();
+        forward_task.abort();
+    }
+    
+    // Fresh picture of the world for spectators
+    pub fn spectate_world(&self, game_state: &GameState) {
+        let _ = self.broadcast_tx.send(Outbound {
+            recipient: Some(SPECTATORS.to_string()),
+            payload: spectator_world(game_state),
+            disconnect: false,
+        });
+    }
+    
+    pub fn spectate_event(&self, game_state: &GameState, player_id: Option<&str>, message: &str) {
+        let update = SpectatorEvent {
+            msg_type: "spectate_event".to_string(),
+            player_name: player_id.map(|p| game_state.owner_name(p).to_string()),
+            message: message.to_string(),
+            at: Utc::now(),
+        };
+        self.send_to(Some(SPECTATORS), &update);
+    }
+    
+    // Spectators see every player's private notices too
+    pub fn spectate_events(&self, game_state: &GameState, events: &[GameEvent]) {
+        for event in events {
+            let player_id = Some(event.player_id.as_str()).filter(|id| *id != SPECTATORS);
+            self.spectate_event(game_state, player_id, &event.message);
+        }
+    }
+}
+
+fn spectator_world(game_state: &GameState) -> String {
+    let name_of = |id: &str| game_state.owner_name(id).to_string();
+    
+    let mut players: Vec<SpectatorPlayer> = game_state.players.values()
+        .map(|p| SpectatorPlayer {
+            name: p.name.clone(),
+            current_ap: p.current_ap,
+            max_ap: p.max_ap,
+            credits: p.credits,
+            level: p.level,
+            xp: p.xp,
+            reputation: p.reputation,
+            kills: p.kills,
+            ship_count: p.owned_ships.len(),
+            resources: p.resources.clone(),
+        })
+        .collect();
+    players.sort_by(|a, b| a.name.cmp(&b.name));
+    let sectors: Vec<Sector> = game_state.sectors.iter()
+        .map(|s| Sector {
+            controlled_by: s.controlled_by.as_deref().map(name_of),
+            ..s.clone()
+        })
+        .collect();
+    let mut ships: Vec<Ship> = game_state.ships.values()
+        .map(|s| Ship {
+            owner: name_of(&s.owner),
+            ..s.clone()
+        })
+        .collect();
+    ships.sort_by(|a, b| a.id.cmp(&b.id));
+    
+    let world = SpectatorWorld {
+        msg_type: "spectate_world".to_string(),
+        season: game_state.season,
+        cycle_number: game_state.cycle_number,
+        players,
+        sectors,
+        ships,
+    };
+    serde_json::to_string(&world).unwrap()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Player, ShipType};
+    use crate::pirates::PIRATE_PLAYER_ID;
+    
+    #[test]
+    fn world_names_commanders_without_their_ids() {
+        let mut state = GameState::new();
+        state.players.insert("secret-alpha".to_string(), Player::new("secret-alpha", "Alpha"));
+        state.spawn_ship("secret-alpha", ShipType::Frigate, "earth-1", "Pioneer");
+        state.sectors[0].controlled_by = Some("secret-alpha".to_string());
+        
+        let world = spectator_world(&state);
+        assert!(!world.contains("secret-alpha"));
+        assert!(world.contains(r#""owner":"Alpha""#));
+        assert!(world.contains(r#""controlled_by":"Alpha""#));
+    }
+    
+    #[test]
+    fn combat_reports_reach_spectators_by_name() {
+        let mut state = GameState::new();
+        state.players.insert("secret-alpha".to_string(), Player::new("secret-alpha", "Alpha"));
+        let ours = state.spawn_ship("secret-alpha", ShipType::Destroyer, "earth-1", "Hammer").unwrap();
+        let raider = Ship::new("pirate-1", "Raider 1", ShipType::Interceptor, PIRATE_PLAYER_ID, "earth-1");
+        state.ships.insert("pirate-1".to_string(), raider);
+        state.sectors[0].ships_present.push("pirate-1".to_string());
+        
+        let report = state.resolve_combat(&[ours], "pirate-1");
+        let events = state.take_events();
+        let event = events.iter().find(|e| e.player_id == SPECTATORS).expect("no spectator report");
+        assert!(event.message.starts_with("Alpha attacked Raider 1 (Pirates):\n"));
+        assert!(event.message.ends_with(&report));
+        assert!(!event.message.contains("secret-alpha"));
+    }
+}
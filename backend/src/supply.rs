@@ -0,0 +1,195 @@
+use std::collections::HashSet;
+
+use crate::game::{GameState, GameEvent, Resource, Sector, Ship};
+use crate::ships::{Ability, ship_class};
+use crate::terrain::Terrain;
+
+const FUEL_PER_HOP: i32 = 1; // Also units of the Fuel resource, which refuels ships away from supply
+const ATTRITION_PERCENT: i32 = 10; // Of max HP lost each cycle while cut off from supply
+
+#[derive(PartialEq)]
+pub enum Supply {
+    Supplied, // Refuels here
+    InRange, // One hop from supply: no attrition, but no refuelling without a supply ship
+    CutOff,
+}
+
+impl Ship {
+    pub fn fuel_capacity(&self) -> i32 {
+        ship_class(&self.ship_type).fuel_capacity
+    }
+    
+    pub fn fuel(&self) -> i32 {
+        (self.fuel_capacity() - self.fuel_used).max(0)
+    }
+    
+    pub fn fuel_for(hops: usize) -> i32 {
+        hops as i32 * FUEL_PER_HOP
+    }
+}
+
+impl GameState {
+    // Sectors in a player's supply network. Supply starts from their own or an ally's
+    // territory and any unclaimed shipyard they have a ship at, and supply ships within a hop
+    // of it carry it on to their own sector, so a chain of them can reach far from home
+    pub fn supplied_sectors(&self, player_id: &str) -> HashSet<String> {
+        let friendly = |owner: &str| owner == player_id || self.is_allied(player_id, owner);
+        let has_ship = |sector: &Sector, ability: Option<Ability>| sector.ships_present.iter()
+            .filter_map(|id| self.ships.get(id))
+            .any(|s| s.owner == player_id && ability.is_none_or(|a| s.ship_type.has_ability(a)));
+        
+        let mut supplied: HashSet<String> = self.sectors.iter()
+            .filter(|s| match &s.controlled_by {
+                Some(controller) => friendly(controller),
+                None => s.has_terrain(Terrain::Shipyard) && has_ship(s, None),
+            })
+            .map(|s| s.id.clone())
+            .collect();
+        
+        let mut depots: Vec<&Sector> = self.sectors.iter()
+            .filter(|s| !supplied.contains(&s.id) && has_ship(s, Some(Ability::Supply)))
+            .collect();
+        loop {
+            let (linked, rest): (Vec<&Sector>, Vec<&Sector>) = depots.into_iter()
+                .partition(|s| s.links.iter().any(|l| supplied.contains(l)));
+            if linked.is_empty() {
+                break;
+            }
+            supplied.extend(linked.into_iter().map(|s| s.id.clone()));
+            depots = rest;
+        }
+        supplied
+    }
+    
+    // A player's own supply ship refuels their ships in its sector even when it's out of
+    // touch with the rest of the network; it just can't keep them from being cut off
+    fn supply_ship_at(&self, sector_id: &str, player_id: &str) -> bool {
+        self.sectors.iter()
+            .find(|s| s.id == sector_id)
+            .is_some_and(|s| s.ships_present.iter()
+                .filter_map(|id| self.ships.get(id))
+                .any(|ship| ship.owner == player_id && ship.ship_type.has_ability(Ability::Supply)))
+    }
+    
+    pub fn supply_state(&self, ship: &Ship) -> Supply {
+        let supplied = self.supplied_sectors(&ship.owner);
+        if supplied.contains(&ship.current_sector) {
+            return Supply::Supplied;
+        }
+        let in_range = self.adjacent_sectors(&ship.current_sector).iter().any(|id| supplied.contains(id));
+        if in_range { Supply::InRange } else { Supply::CutOff }
+    }
+    
+    // Ships away from supply top up from their owner's stock of the Fuel resource, bought
+    // on the market or traded for
+    fn refuel_from_stock(&mut self, ship_id: &str) {
+        let ship = &self.ships[ship_id];
+        let (name, owner, wanted) = (ship.name.clone(), ship.owner.clone(), ship.fuel_used);
+        let stock = match self.players.get_mut(&owner) {
+            Some(player) => player.resources.entry(Resource::Fuel).or_insert(0),
+            None => return,
+        };
+        let taken = wanted.min(*stock);
+        if taken <= 0 {
+            return;
+        }
+        *stock -= taken;
+        self.ships.get_mut(ship_id).unwrap().fuel_used -= taken;
+        self.events.push(GameEvent {
+            player_id: owner,
+            message: format!("{} took on {} fuel from your stores", name, taken),
+        });
+    }
+    
+    // Refuels supplied ships and any sharing a sector with a supply ship, and wears down ones
+    // that are cut off. Called once per cycle
+    pub fn process_supply(&mut self) {
+        let mut ship_ids: Vec<String> = self.ships.values()
+            .filter(|s| !s.in_transit() && self.players.contains_key(&s.owner))
+            .map(|s| s.id.clone())
+            .collect();
+        ship_ids.sort();
+        
+        // Decide everything before changing anything, so the order ships are handled in doesn't matter
+        let states: Vec<(String, Supply, bool)> = ship_ids.into_iter()
+            .map(|id| {
+                let ship = &self.ships[&id];
+                let state = self.supply_state(ship);
+                let refuels = state == Supply::Supplied || self.supply_ship_at(&ship.current_sector, &ship.owner);
+                (id, state, refuels)
+            })
+            .collect();
+        
+        for (ship_id, state, refuels) in states {
+            if refuels {
+                self.ships.get_mut(&ship_id).unwrap().fuel_used = 0;
+            } else {
+                self.refuel_from_stock(&ship_id);
+            }
+            
+            if state == Supply::CutOff {
+                let ship = self.ships[&ship_id].clone();
+                let damage = (ship.max_hp * ATTRITION_PERCENT / 100).max(1);
+                let hp = ship.hp - damage;
+                let message = if hp <= 0 {
+                    self.destroy_ship(&ship_id);
+                    format!("{} was lost: cut off from supply for too long", ship.name)
+                } else {
+                    self.ships.get_mut(&ship_id).unwrap().hp = hp;
+                    format!("{} is cut off from supply and lost {} HP ({} HP left)", ship.name, damage, hp)
+                };
+                self.events.push(GameEvent {
+                    player_id: ship.owner.clone(),
+                    message,
+                });
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{Command, GameState, Player, Resource, ShipType};
+    
+    #[test]
+    fn fuel_burns_per_hop_and_tops_up_from_stock() {
+        let mut state = GameState::new();
+        state.players.insert("p1".to_string(), Player::new("p1", "Alpha"));
+        let ship_id = state.spawn_ship("p1", ShipType::Frigate, "earth-1", "Pioneer").unwrap();
+        
+        let result = state.execute_command("p1", Command::Move { ship_id: ship_id.clone(), sector_id: "earth-3".to_string() });
+        assert!(result.success, "{}", result.message);
+        assert_eq!(state.ships[&ship_id].fuel_used, 0);
+        
+        let ticks_per_hop = state.ships[&ship_id].ticks_per_hop();
+        for _ in 0..ticks_per_hop {
+            state.process_transit();
+        }
+        assert_eq!(state.ships[&ship_id].fuel_used, 1);
+        
+        state.players.get_mut("p1").unwrap().resources.insert(Resource::Fuel, 5);
+        state.refuel_from_stock(&ship_id);
+        assert_eq!(state.ships[&ship_id].fuel_used, 0);
+        assert_eq!(state.players["p1"].resources[&Resource::Fuel], 4);
+    }
+    
+    #[test]
+    fn supply_ships_refuel_their_sector_even_when_cut_off() {
+        let mut state = GameState::new();
+        state.players.insert("p1".to_string(), Player::new("p1", "Alpha"));
+        state.spawn_ship("p1", ShipType::SupplyShip, "earth-6", "Tender");
+        let refuelled = state.spawn_ship("p1", ShipType::Frigate, "earth-6", "Pioneer").unwrap();
+        let stranded = state.spawn_ship("p1", ShipType::Frigate, "earth-7", "Voyager").unwrap();
+        for ship_id in [&refuelled, &stranded] {
+            state.ships.get_mut(ship_id).unwrap().fuel_used = 3;
+        }
+        assert!(state.supplied_sectors("p1").is_empty());
+        
+        state.process_supply();
+        let ship = &state.ships[&refuelled];
+        assert_eq!(ship.fuel_used, 0);
+        assert!(ship.hp < ship.max_hp, "still cut off, so attrition applies");
+        assert_eq!(state.ships[&stranded].fuel_used, 3);
+    }
+}
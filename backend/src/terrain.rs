@@ -0,0 +1,218 @@
+use serde::{Deserialize, Serialize};
+
+use crate::game::{GameState, CommandResult, GameEvent, Sector, Ship, ShipType, Resource};
+use crate::ships::{Ability, ship_class};
+
+const ASTEROID_MOVE_PENALTY: i32 = 2; // Extra AP to move into an asteroid field
+const JUMP_GATE_AP_COST: i32 = 1; // Moving from one jump gate to another
+const ASTEROID_COVER: i32 = 25; // % less damage from fire inside an asteroid field
+const ION_STORM_DAMAGE_BONUS: i32 = 25; // % more damage with shields down in an ion storm
+const ION_STORM_DAMAGE: i32 = 10; // Per cycle, to every ship in the storm
+const MINING_YIELD: i32 = 5; // Ore per miner per cycle
+const ASTEROID_MINING_MULTIPLIER: i32 = 3;
+const BUILD_AP_COST: i32 = 5;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Terrain {
+    Nebula, // Can't be scanned from outside, and hides its ship count
+    AsteroidField, // Mining bonus and cover, but costs extra AP to enter
+    IonStorm, // Damages every ship inside each cycle; shields are down in combat
+    Shipyard, // Ships can be built here
+    JumpGate, // Cheap travel to any other jump gate
+    TradeHub, // Resources can be bought and sold on the market here
+}
+
+impl Terrain {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Terrain::Nebula => "Nebula",
+            Terrain::AsteroidField => "Asteroid field",
+            Terrain::IonStorm => "Ion storm",
+            Terrain::Shipyard => "Shipyard",
+            Terrain::JumpGate => "Jump gate",
+            Terrain::TradeHub => "Trade hub",
+        }
+    }
+}
+
+impl Sector {
+    pub fn has_terrain(&self, terrain: Terrain) -> bool {
+        self.terrain.contains(&terrain)
+    }
+}
+
+impl GameState {
+    fn sector_has_terrain(&self, sector_id: &str, terrain: Terrain) -> bool {
+        self.sectors.iter().any(|s| s.id == sector_id && s.has_terrain(terrain))
+    }
+    
+    // AP for a ship to move into a sector
+    pub fn move_cost(&self, ship: &Ship, target_sector_id: &str) -> i32 {
+        if self.sector_has_terrain(&ship.current_sector, Terrain::JumpGate)
+            && self.sector_has_terrain(target_sector_id, Terrain::JumpGate) {
+            return JUMP_GATE_AP_COST;
+        }
+        if self.sector_has_terrain(target_sector_id, Terrain::AsteroidField) {
+            return ship.ap_cost + ASTEROID_MOVE_PENALTY;
+        }
+        ship.ap_cost
+    }
+    
+    // Nebulae can only be seen into from the inside
+    pub fn scan_blocked(&self, from_sector_id: &str, target: &Sector) -> bool {
+        target.has_terrain(Terrain::Nebula) && target.id != from_sector_id
+    }
+    
+    // Ship count a player sees on the map; nebulae hide theirs from anyone without a ship inside
+    pub fn shown_ship_count(&self, sector: &Sector, player_id: &str) -> usize {
+        let inside = sector.ships_present.iter()
+            .filter_map(|id| self.ships.get(id))
+            .any(|s| s.owner == player_id);
+        if sector.has_terrain(Terrain::Nebula) && !inside {
+            return 0;
+        }
+        self.visible_ships(sector, player_id).len()
+    }
+    
+    // Damage after the sector's terrain has had its say
+    pub fn terrain_damage(&self, sector_id: &str, damage: i32) -> i32 {
+        let mut percent = 100;
+        if self.sector_has_terrain(sector_id, Terrain::AsteroidField) {
+            percent -= ASTEROID_COVER;
+        }
+        if self.sector_has_terrain(sector_id, Terrain::IonStorm) {
+            percent += ION_STORM_DAMAGE_BONUS;
+        }
+        damage * percent / 100
+    }
+    
+    // Ion storms wear ships down and miners bring in ore. Called once per cycle
+    pub fn process_terrain(&mut self) {
+        let mut ship_ids: Vec<String> = self.ships.keys().cloned().collect();
+        ship_ids.sort();
+        
+        for ship_id in ship_ids {
+            let ship = self.ships[&ship_id].clone();
+            if ship.in_transit() {
+                continue;
+            }
+            let sector = match self.sectors.iter().find(|s| s.id == ship.current_sector) {
+                Some(s) => s.clone(),
+                None => continue,
+            };
+            
+            if sector.has_terrain(Terrain::IonStorm) {
+                let hp = ship.hp - ION_STORM_DAMAGE;
+                let message = if hp <= 0 {
+                    self.destroy_ship(&ship_id);
+                    format!("{} was torn apart by the ion storm in {}", ship.name, sector.name)
+                } else {
+                    self.ships.get_mut(&ship_id).unwrap().hp = hp;
+                    format!("{} took {} damage from the ion storm in {} ({} HP left)", ship.name, ION_STORM_DAMAGE, sector.name, hp)
+                };
+                self.events.push(GameEvent {
+                    player_id: ship.owner.clone(),
+                    message,
+                });
+                if hp <= 0 {
+                    continue;
+                }
+            }
+            
+            if ship.ship_type.has_ability(Ability::Mine) {
+                let ore = if sector.has_terrain(Terrain::AsteroidField) {
+                    MINING_YIELD * ASTEROID_MINING_MULTIPLIER
+                } else {
+                    MINING_YIELD
+                };
+                if let Some(owner) = self.players.get_mut(&ship.owner) {
+                    *owner.resources.entry(Resource::Ore).or_insert(0) += ore;
+                }
+            }
+        }
+    }
+    
+    pub fn build_ship(&mut self, player_id: &str, ship_type: ShipType, sector_id: &str, name: Option<&str>) -> CommandResult {
+        let sector = match self.sectors.iter().find(|s| s.id == sector_id) {
+            Some(s) => s,
+            None => return CommandResult {
+                success: false,
+                message: "Sector not found".to_string(),
+                ap_spent: 0,
+                game_state: self.clone(),
+            }
+        };
+        
+        if !sector.has_terrain(Terrain::Shipyard) {
+            return CommandResult {
+                success: false,
+                message: format!("{} has no shipyard", sector.name),
+                ap_spent: 0,
+                game_state: self.clone(),
+            };
+        }
+        
+        // The yard must be ours, or unclaimed with one of our ships there to crew it
+        let has_ship_there = sector.ships_present.iter()
+            .filter_map(|id| self.ships.get(id))
+            .any(|s| s.owner == player_id);
+        let usable = match &sector.controlled_by {
+            Some(controller) => controller == player_id,
+            None => has_ship_there,
+        };
+        if !usable {
+            return CommandResult {
+                success: false,
+                message: format!("You need to control {} or have a ship there to use its shipyard", sector.name),
+                ap_spent: 0,
+                game_state: self.clone(),
+            };
+        }
+        
+        let cost = ship_class(&ship_type).cost;
+        let player = self.players.get(player_id).unwrap();
+        if player.current_ap < BUILD_AP_COST {
+            return CommandResult {
+                success: false,
+                message: format!("Not enough AP. Need {} but have {}", BUILD_AP_COST, player.current_ap),
+                ap_spent: 0,
+                game_state: self.clone(),
+            };
+        }
+        if player.credits < cost {
+            return CommandResult {
+                success: false,
+                message: format!("A {:?} costs {} credits; you have {}", ship_type, cost, player.credits),
+                ap_spent: 0,
+                game_state: self.clone(),
+            };
+        }
+        
+        let sector_name = sector.name.clone();
+        let name = match name {
+            Some(n) => n.to_string(),
+            None => format!("{} {:?}", player.name, ship_type),
+        };
+        let ship_id = match self.spawn_ship(player_id, ship_type.clone(), sector_id, &name) {
+            Some(id) => id,
+            None => return CommandResult {
+                success: false,
+                message: "Sector not found".to_string(),
+                ap_spent: 0,
+                game_state: self.clone(),
+            }
+        };
+        
+        let player = self.players.get_mut(player_id).unwrap();
+        player.credits -= cost;
+        player.current_ap -= BUILD_AP_COST;
+        
+        CommandResult {
+            success: true,
+            message: format!("{} ({:?}, {}) launched at {} for {} credits", name, ship_type, ship_id, sector_name, cost),
+            ap_spent: BUILD_AP_COST,
+            game_state: self.clone(),
+        }
+    }
+}
@@ -0,0 +1,491 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::game::{GameState, CommandResult, GameEvent, Resource};
+use crate::notifications::NotificationKind;
+
+const MAX_TRADE_AMOUNT: i32 = 1_000_000; // Per item on one side of a trade
+
+// One side of a trade
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct TradeBundle {
+    pub credits: i32,
+    pub resources: HashMap<Resource, i32>,
+    pub ships: Vec<String>, // Ship IDs
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TradeOffer {
+    pub id: u32,
+    pub from: String, // Player ID, whose `give` side sits in escrow
+    pub to: String, // Player ID
+    pub give: TradeBundle,
+    pub want: TradeBundle,
+    pub expires_cycle: u32,
+}
+
+impl TradeBundle {
+    // Parses tokens like `credits:100`, `ore:5` or a bare ship ID
+    pub fn parse(tokens: &[&str]) -> Result<Self, String> {
+        let mut bundle = TradeBundle::default();
+        
+        for token in tokens {
+            let (kind, amount) = match token.split_once(':') {
+                Some((kind, amount)) => (kind.to_lowercase(), amount),
+                None => {
+                    bundle.ships.push(token.to_string());
+                    continue;
+                }
+            };
+            let amount: i32 = match amount.parse() {
+                Ok(a) if a > 0 => a,
+                _ => return Err(format!("Invalid amount in {}", token)),
+            };
+            
+            let total = if kind == "credits" || kind == "cr" {
+                &mut bundle.credits
+            } else if let Some(resource) = Resource::parse(&kind) {
+                bundle.resources.entry(resource).or_insert(0)
+            } else {
+                return Err(format!("Unknown trade item: {}", kind));
+            };
+            *total = total.checked_add(amount)
+                .filter(|t| *t <= MAX_TRADE_AMOUNT)
+                .ok_or("amount too large")?;
+        }
+        
+        Ok(bundle)
+    }
+    
+    // Bundles built in code rather than parsed get the same ceiling
+    fn check_limits(&self) -> Result<(), String> {
+        let amounts = std::iter::once(self.credits).chain(self.resources.values().copied());
+        for amount in amounts {
+            if !(0..=MAX_TRADE_AMOUNT).contains(&amount) {
+                return Err("amount too large".to_string());
+            }
+        }
+        Ok(())
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.credits == 0 && self.resources.is_empty() && self.ships.is_empty()
+    }
+    
+    pub fn describe(&self, state: &GameState) -> String {
+        let mut items = Vec::new();
+        if self.credits > 0 {
+            items.push(format!("{} credits", self.credits));
+        }
+        let mut resources: Vec<_> = self.resources.iter().collect();
+        resources.sort_by_key(|(r, _)| format!("{:?}", r));
+        for (resource, amount) in resources {
+            items.push(format!("{} {:?}", amount, resource));
+        }
+        for ship_id in &self.ships {
+            match state.ships.get(ship_id) {
+                Some(ship) => items.push(format!("{} ({:?}, {})", ship.name, ship.ship_type, ship.id)),
+                None => items.push(format!("{} (lost)", ship_id)),
+            }
+        }
+        
+        if items.is_empty() { "nothing".to_string() } else { items.join(", ") }
+    }
+}
+
+impl GameState {
+    pub fn ship_in_escrow(&self, ship_id: &str) -> bool {
+        self.trade_offers.iter().any(|o| o.give.ships.iter().any(|s| s == ship_id))
+    }
+    
+    // Checks that a player can hand over a bundle right now
+    fn can_afford(&self, player_id: &str, bundle: &TradeBundle) -> Result<(), String> {
+        let player = &self.players[player_id];
+        
+        if player.credits < bundle.credits {
+            return Err(format!("{} doesn't have {} credits", player.name, bundle.credits));
+        }
+        for (resource, amount) in &bundle.resources {
+            if player.resources.get(resource).copied().unwrap_or(0) < *amount {
+                return Err(format!("{} doesn't have {} {:?}", player.name, amount, resource));
+            }
+        }
+        for ship_id in &bundle.ships {
+            match self.ships.get(ship_id) {
+                Some(s) if s.owner == player_id => {}
+                _ => return Err(format!("{} doesn't own ship {}", player.name, ship_id)),
+            }
+        }
+        
+        Ok(())
+    }
+    
+    pub fn offer_trade(&mut self, player_id: &str, target: &str, give: TradeBundle, want: TradeBundle) -> CommandResult {
+        let partner_id = match self.find_player_id(target) {
+            Some(id) if id == player_id => return CommandResult {
+                success: false,
+                message: "You can't trade with yourself".to_string(),
+                ap_spent: 0,
+                game_state: self.clone(),
+            },
+            Some(id) => id,
+            None => return CommandResult {
+                success: false,
+                message: format!("No commander named {}", target),
+                ap_spent: 0,
+                game_state: self.clone(),
+            }
+        };
+        
+        if give.is_empty() && want.is_empty() {
+            return CommandResult {
+                success: false,
+                message: "A trade needs something on at least one side".to_string(),
+                ap_spent: 0,
+                game_state: self.clone(),
+            };
+        }
+        
+        let mut problem = give.check_limits().and(want.check_limits())
+            .and_then(|_| self.can_afford(player_id, &give)).err();
+        if let Some(ship_id) = give.ships.iter().find(|s| self.ship_in_escrow(s)) {
+            problem = Some(format!("{} is already promised in another trade", ship_id));
+        }
+        if let Some(message) = problem {
+            return CommandResult {
+                success: false,
+                message,
+                ap_spent: 0,
+                game_state: self.clone(),
+            };
+        }
+        
+        // Escrow: credits and resources leave the offerer now, ships stay put but are locked
+        let player = self.players.get_mut(player_id).unwrap();
+        player.credits -= give.credits;
+        for (resource, amount) in &give.resources {
+            *player.resources.get_mut(resource).unwrap() -= amount;
+        }
+        
+        self.next_trade_id += 1;
+        let offer = TradeOffer {
+            id: self.next_trade_id,
+            from: player_id.to_string(),
+            to: partner_id.clone(),
+            give,
+            want,
+            expires_cycle: self.cycle_number + self.settings.trade_expiry_cycles,
+        };
+        
+        let summary = format!(
+            "Trade #{}: {} gives {} for {} (expires cycle {})",
+            offer.id, self.players[player_id].name, offer.give.describe(self), offer.want.describe(self), offer.expires_cycle
+        );
+        self.notify(&partner_id, NotificationKind::TradeOffer, format!("{} - 'trade accept {}' or 'trade decline {}'", summary, offer.id, offer.id));
+        self.trade_offers.push(offer);
+        
+        CommandResult {
+            success: true,
+            message: summary,
+            ap_spent: 0,
+            game_state: self.clone(),
+        }
+    }
+    
+    pub fn accept_trade(&mut self, player_id: &str, offer_id: u32) -> CommandResult {
+        let index = match self.trade_offers.iter().position(|o| o.id == offer_id && o.to == player_id) {
+            Some(i) => i,
+            None => return CommandResult {
+                success: false,
+                message: format!("No trade #{} waiting for you", offer_id),
+                ap_spent: 0,
+                game_state: self.clone(),
+            }
+        };
+        
+        // Escrowed ships can still be destroyed; that voids the offer
+        let offer = self.trade_offers[index].clone();
+        if let Err(e) = self.can_afford(&offer.from, &TradeBundle { ships: offer.give.ships.clone(), ..Default::default() }) {
+            self.trade_offers.remove(index);
+            self.refund_escrow(&offer);
+            self.trade_event(&offer, format!("Trade #{} voided: {}", offer.id, e));
+            return CommandResult {
+                success: false,
+                message: format!("Trade #{} can no longer be completed", offer.id),
+                ap_spent: 0,
+                game_state: self.clone(),
+            };
+        }
+        
+        let mut problem = self.can_afford(player_id, &offer.want)
+            .and_then(|_| self.check_balances(player_id, &offer)).err();
+        if let Some(ship_id) = offer.want.ships.iter().find(|s| self.ship_in_escrow(s)) {
+            problem = Some(format!("{} is promised in another trade", ship_id));
+        }
+        if let Some(message) = problem {
+            return CommandResult {
+                success: false,
+                message,
+                ap_spent: 0,
+                game_state: self.clone(),
+            };
+        }
+        
+        // Everything is validated, so the swap below can't fail halfway
+        self.trade_offers.remove(index);
+        let recipient = self.players.get_mut(player_id).unwrap();
+        recipient.credits = recipient.credits - offer.want.credits + offer.give.credits;
+        for (resource, amount) in &offer.give.resources {
+            *recipient.resources.entry(*resource).or_insert(0) += amount;
+        }
+        for (resource, amount) in &offer.want.resources {
+            *recipient.resources.get_mut(resource).unwrap() -= amount;
+        }
+        
+        let offerer = self.players.get_mut(&offer.from).unwrap();
+        offerer.credits += offer.want.credits;
+        for (resource, amount) in &offer.want.resources {
+            *offerer.resources.entry(*resource).or_insert(0) += amount;
+        }
+        
+        for ship_id in &offer.give.ships {
+            self.transfer_ship(ship_id, player_id);
+        }
+        for ship_id in &offer.want.ships {
+            self.transfer_ship(ship_id, &offer.from);
+        }
+        
+        let summary = format!(
+            "Trade #{} complete: {} received {}, {} received {}",
+            offer.id,
+            self.players[player_id].name, offer.give.describe(self),
+            self.players[&offer.from].name, offer.want.describe(self)
+        );
+        self.events.push(GameEvent {
+            player_id: offer.from.clone(),
+            message: summary.clone(),
+        });
+        
+        CommandResult {
+            success: true,
+            message: summary,
+            ap_spent: 0,
+            game_state: self.clone(),
+        }
+    }
+    
+    // The recipient declines, or the offerer withdraws; either way the escrow goes back
+    pub fn decline_trade(&mut self, player_id: &str, offer_id: u32) -> CommandResult {
+        let index = match self.trade_offers.iter()
+            .position(|o| o.id == offer_id && (o.to == player_id || o.from == player_id)) {
+            Some(i) => i,
+            None => return CommandResult {
+                success: false,
+                message: format!("No trade #{} involving you", offer_id),
+                ap_spent: 0,
+                game_state: self.clone(),
+            }
+        };
+        
+        let offer = self.trade_offers.remove(index);
+        self.refund_escrow(&offer);
+        
+        let verb = if offer.from == player_id { "withdrawn" } else { "declined" };
+        let message = format!("Trade #{} {} by {}", offer.id, verb, self.players[player_id].name);
+        let other = if offer.from == player_id { &offer.to } else { &offer.from };
+        self.events.push(GameEvent {
+            player_id: other.clone(),
+            message: message.clone(),
+        });
+        
+        CommandResult {
+            success: true,
+            message,
+            ap_spent: 0,
+            game_state: self.clone(),
+        }
+    }
+    
+    pub fn list_trades(&self, player_id: &str) -> CommandResult {
+        let mut report = "=== Trade Offers ===\n".to_string();
+        
+        let offers: Vec<&TradeOffer> = self.trade_offers.iter()
+            .filter(|o| o.from == player_id || o.to == player_id)
+            .collect();
+        if offers.is_empty() {
+            report.push_str("No open offers\n");
+        }
+        
+        for offer in offers {
+            let (direction, other) = if offer.from == player_id { ("to", &offer.to) } else { ("from", &offer.from) };
+            let other_name = self.players.get(other).map(|p| p.name.as_str()).unwrap_or("Unknown");
+            report.push_str(&format!(
+                "#{} {} {}: gives {} for {} (expires cycle {})\n",
+                offer.id, direction, other_name, offer.give.describe(self), offer.want.describe(self), offer.expires_cycle
+            ));
+        }
+        
+        CommandResult {
+            success: true,
+            message: report,
+            ap_spent: 0,
+            game_state: self.clone(),
+        }
+    }
+    
+    // Called once per cycle
+    pub fn expire_trades(&mut self) {
+        let cycle_number = self.cycle_number;
+        let (expired, kept): (Vec<TradeOffer>, Vec<TradeOffer>) = std::mem::take(&mut self.trade_offers)
+            .into_iter()
+            .partition(|o| o.expires_cycle <= cycle_number);
+        self.trade_offers = kept;
+        
+        for offer in expired {
+            self.refund_escrow(&offer);
+            self.trade_event(&offer, format!("Trade #{} expired; escrow returned", offer.id));
+        }
+    }
+    
+    // Whether both sides can take what they're owed without their balances overflowing
+    fn check_balances(&self, player_id: &str, offer: &TradeOffer) -> Result<(), String> {
+        let sides = [(player_id, &offer.give), (offer.from.as_str(), &offer.want)];
+        for (receiver_id, bundle) in sides {
+            let receiver = &self.players[receiver_id];
+            let mut fits = receiver.credits.checked_add(bundle.credits).is_some();
+            for (resource, amount) in &bundle.resources {
+                fits &= receiver.resources.get(resource).copied().unwrap_or(0).checked_add(*amount).is_some();
+            }
+            if !fits {
+                return Err("amount too large".to_string());
+            }
+        }
+        Ok(())
+    }
+    
+    fn refund_escrow(&mut self, offer: &TradeOffer) {
+        if let Some(player) = self.players.get_mut(&offer.from) {
+            player.credits += offer.give.credits;
+            for (resource, amount) in &offer.give.resources {
+                *player.resources.entry(*resource).or_insert(0) += amount;
+            }
+        }
+    }
+    
+    fn trade_event(&mut self, offer: &TradeOffer, message: String) {
+        for player_id in [&offer.from, &offer.to] {
+            self.events.push(GameEvent {
+                player_id: player_id.clone(),
+                message: message.clone(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Player, ShipType};
+    
+    fn two_traders() -> GameState {
+        let mut state = GameState::new();
+        state.players.insert("p1".to_string(), Player::new("p1", "Alpha"));
+        state.players.insert("p2".to_string(), Player::new("p2", "Beta"));
+        state.players.get_mut("p2").unwrap().resources.insert(Resource::Ore, 10);
+        state
+    }
+    
+    #[test]
+    fn parses_bundles() {
+        let bundle = TradeBundle::parse(&["credits:40", "cr:10", "ore:5", "ship-3"]).unwrap();
+        assert_eq!(bundle.credits, 50);
+        assert_eq!(bundle.resources[&Resource::Ore], 5);
+        assert_eq!(bundle.ships, vec!["ship-3".to_string()]);
+        
+        assert!(TradeBundle::parse(&["credits:0"]).is_err());
+        assert!(TradeBundle::parse(&["credits:-5"]).is_err());
+        assert!(TradeBundle::parse(&["gold:5"]).is_err());
+    }
+    
+    #[test]
+    fn rejects_amounts_that_overflow() {
+        assert_eq!(TradeBundle::parse(&["credits:2147483647", "credits:1"]).unwrap_err(), "amount too large");
+        assert_eq!(TradeBundle::parse(&["ore:2147483647", "ore:1"]).unwrap_err(), "amount too large");
+        assert!(TradeBundle::parse(&["credits:1000001"]).is_err());
+    }
+    
+    #[test]
+    fn offering_escrows_credits() {
+        let mut state = two_traders();
+        let give = TradeBundle::parse(&["credits:60"]).unwrap();
+        let want = TradeBundle::parse(&["ore:4"]).unwrap();
+        
+        assert!(state.offer_trade("p1", "Beta", give, want).success);
+        assert_eq!(state.players["p1"].credits, 40);
+        assert_eq!(state.trade_offers.len(), 1);
+        
+        let too_much = TradeBundle::parse(&["credits:60"]).unwrap();
+        assert!(!state.offer_trade("p1", "Beta", too_much, TradeBundle::default()).success);
+        assert_eq!(state.players["p1"].credits, 40);
+    }
+    
+    #[test]
+    fn accepting_swaps_both_sides() {
+        let mut state = two_traders();
+        let ship_id = state.spawn_ship("p1", ShipType::Interceptor, "earth-1", "Gift").unwrap();
+        let give = TradeBundle::parse(&["credits:60", &ship_id]).unwrap();
+        let want = TradeBundle::parse(&["ore:4"]).unwrap();
+        state.offer_trade("p1", "Beta", give, want);
+        let offer_id = state.trade_offers[0].id;
+        
+        assert!(!state.accept_trade("p1", offer_id).success, "only the recipient can accept");
+        let result = state.accept_trade("p2", offer_id);
+        assert!(result.success, "{}", result.message);
+        assert_eq!(state.players["p1"].credits, 40);
+        assert_eq!(state.players["p1"].resources[&Resource::Ore], 4);
+        assert_eq!(state.players["p2"].credits, 160);
+        assert_eq!(state.players["p2"].resources[&Resource::Ore], 6);
+        assert_eq!(state.ships[&ship_id].owner, "p2");
+        assert!(state.trade_offers.is_empty());
+    }
+    
+    #[test]
+    fn accepting_refuses_a_balance_overflow() {
+        let mut state = two_traders();
+        state.offer_trade("p1", "Beta", TradeBundle::parse(&["credits:50"]).unwrap(), TradeBundle::default());
+        state.players.get_mut("p2").unwrap().credits = i32::MAX - 10;
+        let offer_id = state.trade_offers[0].id;
+        
+        let result = state.accept_trade("p2", offer_id);
+        assert!(!result.success);
+        assert_eq!(result.message, "amount too large");
+        assert_eq!(state.players["p2"].credits, i32::MAX - 10);
+        assert_eq!(state.trade_offers.len(), 1);
+    }
+    
+    #[test]
+    fn declining_returns_the_escrow() {
+        let mut state = two_traders();
+        state.offer_trade("p1", "Beta", TradeBundle::parse(&["credits:60"]).unwrap(), TradeBundle::default());
+        let offer_id = state.trade_offers[0].id;
+        
+        assert!(state.decline_trade("p2", offer_id).success);
+        assert_eq!(state.players["p1"].credits, 100);
+        assert!(state.trade_offers.is_empty());
+    }
+    
+    #[test]
+    fn expired_offers_return_the_escrow() {
+        let mut state = two_traders();
+        state.offer_trade("p1", "Beta", TradeBundle::parse(&["credits:60"]).unwrap(), TradeBundle::default());
+        
+        state.cycle_number += state.settings.trade_expiry_cycles - 1;
+        state.expire_trades();
+        assert_eq!(state.trade_offers.len(), 1);
+        
+        state.cycle_number += 1;
+        state.expire_trades();
+        assert!(state.trade_offers.is_empty());
+        assert_eq!(state.players["p1"].credits, 100);
+    }
+}
@@ -0,0 +1,274 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+use crate::game::{GameState, GameEvent, Ship};
+use crate::pirates::PIRATE_PLAYER_ID;
+use crate::ships::ship_class;
+use crate::terrain::Terrain;
+
+pub const TRANSIT_TICKS_PER_CYCLE: u32 = 8; // Ships in transit advance this often each cycle
+
+// A ship on its way somewhere. It isn't in any sector until it reaches the next one on its route
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Transit {
+    pub route: Vec<String>, // Sectors still to reach; the last one is the destination
+    pub ticks_per_hop: u32,
+    pub ticks_to_next: u32, // Until it reaches the first sector on the route
+}
+
+impl Transit {
+    pub fn destination(&self) -> &str {
+        self.route.last().map_or("", |s| s.as_str())
+    }
+    
+    pub fn ticks_left(&self) -> u32 {
+        self.ticks_to_next + self.route.len().saturating_sub(1) as u32 * self.ticks_per_hop
+    }
+}
+
+impl Ship {
+    pub fn in_transit(&self) -> bool {
+        self.transit.is_some()
+    }
+    
+    // Faster classes cover more hops per cycle
+    pub fn ticks_per_hop(&self) -> u32 {
+        let speed = ship_class(&self.ship_type).speed.max(1) as u32;
+        TRANSIT_TICKS_PER_CYCLE.div_ceil(speed)
+    }
+}
+
+impl GameState {
+    // Sectors along the shortest route, not counting the start. Jump gates lead straight
+    // to one another
+    pub fn route(&self, from_id: &str, to_id: &str) -> Option<Vec<String>> {
+        let is_gate = |id: &str| self.sectors.iter().any(|s| s.id == id && s.has_terrain(Terrain::JumpGate));
+        if is_gate(from_id) && is_gate(to_id) {
+            return Some(vec![to_id.to_string()]);
+        }
+        
+        let links: HashMap<&str, &Vec<String>> = self.sectors.iter()
+            .map(|s| (s.id.as_str(), &s.links))
+            .collect();
+        if !links.contains_key(from_id) || !links.contains_key(to_id) {
+            return None;
+        }
+        
+        let mut came_from: HashMap<&str, &str> = HashMap::new();
+        let mut queue = VecDeque::from([from_id]);
+        while let Some(current) = queue.pop_front() {
+            if current == to_id {
+                let mut route = vec![to_id.to_string()];
+                let mut step = to_id;
+                while let Some(previous) = came_from.get(step) {
+                    if *previous == from_id {
+                        break;
+                    }
+                    route.push(previous.to_string());
+                    step = previous;
+                }
+                route.reverse();
+                return Some(route);
+            }
+            for next in links[current].iter() {
+                if next != from_id && !came_from.contains_key(next.as_str()) {
+                    came_from.insert(next.as_str(), current);
+                    queue.push_back(next.as_str());
+                }
+            }
+        }
+        None
+    }
+    
+    pub fn sector_name(&self, sector_id: &str) -> String {
+        self.sectors.iter().find(|s| s.id == sector_id).map_or_else(|| sector_id.to_string(), |s| s.name.clone())
+    }
+    
+    // Roughly how long a number of transit ticks takes in real time
+    pub fn describe_eta(&self, ticks: u32) -> String {
+        let secs = ticks as u64 * self.settings.cycle_secs / TRANSIT_TICKS_PER_CYCLE as u64;
+        if secs >= 60 * 60 {
+            format!("~{}h {}m", secs / 3600, secs % 3600 / 60)
+        } else if secs >= 60 {
+            format!("~{}m", secs / 60)
+        } else {
+            format!("~{}s", secs)
+        }
+    }
+    
+    // Ships heading for a sector that a scanner with this detection can make out
+    pub fn inbound_ships(&self, sector_id: &str, viewer_id: &str, detection: i32) -> Vec<&Ship> {
+        let mut inbound: Vec<&Ship> = self.ships.values()
+            .filter(|s| s.transit.as_ref().is_some_and(|t| t.destination() == sector_id))
+            .filter(|s| s.owner != viewer_id && !self.is_allied(viewer_id, &s.owner))
+            .filter(|s| s.is_visible_to(viewer_id, detection))
+            .collect();
+        inbound.sort_by_key(|s| (s.transit.as_ref().map_or(0, |t| t.ticks_left()), s.id.clone()));
+        inbound
+    }
+    
+    // Advances every ship in transit by one tick. A ship reaching a waypoint held by armed
+    // hostiles is stopped there. Ships in the sectors it reaches then act on their stances.
+    // Returns whether any ship reached a sector
+    pub fn process_transit(&mut self) -> bool {
+        let mut ship_ids: Vec<String> = self.ships.iter()
+            .filter(|(_, s)| s.in_transit())
+            .map(|(id, _)| id.clone())
+            .collect();
+        ship_ids.sort();
+        
+        let mut reached = false;
+        let mut contacts: Vec<String> = Vec::new();
+        for ship_id in ship_ids {
+            let ship = self.ships.get_mut(&ship_id).unwrap();
+            let transit = ship.transit.as_mut().unwrap();
+            transit.ticks_to_next = transit.ticks_to_next.saturating_sub(1);
+            if transit.ticks_to_next > 0 {
+                continue;
+            }
+            
+            reached = true;
+            let sector_id = transit.route.remove(0);
+            transit.ticks_to_next = transit.ticks_per_hop;
+            let arrived = transit.route.is_empty();
+            ship.current_sector = sector_id.clone();
+            // Fuel burns hop by hop, so a ship stopped short only pays for the hops it made
+            ship.fuel_used += Ship::fuel_for(1);
+            let ship = ship.clone();
+            
+            let interceptors: Vec<String> = match self.sectors.iter().find(|s| s.id == sector_id) {
+                Some(sector) if !arrived => sector.ships_present.iter()
+                    .filter_map(|id| self.ships.get(id))
+                    .filter(|s| s.owner != ship.owner && s.damage > 0)
+                    .filter(|s| self.treaty_between(&ship.owner, &s.owner).is_none())
+                    .map(|s| s.owner.clone())
+                    .collect(),
+                _ => Vec::new(),
+            };
+            if !arrived && interceptors.is_empty() {
+                continue;
+            }
+            
+            // The journey ends here, one way or the other
+            self.ships.get_mut(&ship_id).unwrap().transit = None;
+            if !contacts.contains(&sector_id) {
+                contacts.push(sector_id.clone());
+            }
+            let sector_name = match self.sectors.iter_mut().find(|s| s.id == sector_id) {
+                Some(sector) => {
+                    sector.ships_present.push(ship_id.clone());
+                    sector.name.clone()
+                }
+                None => sector_id.clone(),
+            };
+            
+            if arrived {
+                self.events.push(GameEvent {
+                    player_id: ship.owner.clone(),
+                    message: format!("{} arrived at {}", ship.name, sector_name),
+                });
+                continue;
+            }
+            
+            let destination = self.sector_name(ship.transit.as_ref().map_or("", |t| t.destination()));
+            let interceptor = interceptors[0].clone();
+            self.events.push(GameEvent {
+                player_id: ship.owner.clone(),
+                message: format!(
+                    "{} was intercepted at {} by {}'s ships and stopped short of {}",
+                    ship.name, sector_name, self.owner_name(&interceptor), destination
+                ),
+            });
+            let mut notified: Vec<String> = Vec::new();
+            for owner in interceptors {
+                if owner == PIRATE_PLAYER_ID || notified.contains(&owner) {
+                    continue;
+                }
+                self.events.push(GameEvent {
+                    player_id: owner.clone(),
+                    message: format!("Your ships at {} intercepted {}'s {}", sector_name, self.owner_name(&ship.owner), ship.name),
+                });
+                notified.push(owner);
+            }
+        }
+        
+        // Only once everyone has moved, so a ship lost in one fight doesn't vanish mid-tick
+        contacts.sort();
+        for sector_id in &contacts {
+            self.engage(sector_id);
+        }
+        reached
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Command, Player, ShipType};
+    
+    fn two_player_game() -> GameState {
+        let mut state = GameState::new();
+        state.players.insert("p1".to_string(), Player::new("p1", "Alpha"));
+        state.players.insert("p2".to_string(), Player::new("p2", "Beta"));
+        state
+    }
+    
+    fn run_until_stopped(state: &mut GameState, ship_id: &str) {
+        for _ in 0..100 {
+            if !state.ships[ship_id].in_transit() {
+                return;
+            }
+            state.process_transit();
+        }
+        panic!("{} never stopped", ship_id);
+    }
+    
+    #[test]
+    fn ships_travel_hop_by_hop() {
+        let mut state = two_player_game();
+        let ship_id = state.spawn_ship("p1", ShipType::Frigate, "earth-1", "Pioneer").unwrap();
+        let result = state.execute_command("p1", Command::Move { ship_id: ship_id.clone(), sector_id: "earth-3".to_string() });
+        assert!(result.success, "{}", result.message);
+        
+        let ship = &state.ships[&ship_id];
+        let transit = ship.transit.as_ref().unwrap();
+        assert_eq!(transit.route, vec!["earth-2".to_string(), "earth-3".to_string()]);
+        assert_eq!(transit.ticks_left(), 2 * ship.ticks_per_hop());
+        // Out of its old sector as soon as it leaves
+        assert!(!state.sectors[0].ships_present.contains(&ship_id));
+        
+        run_until_stopped(&mut state, &ship_id);
+        let ship = &state.ships[&ship_id];
+        assert_eq!(ship.current_sector, "earth-3");
+        assert_eq!(ship.fuel_used, Ship::fuel_for(2));
+        assert!(state.sectors.iter().find(|s| s.id == "earth-3").unwrap().ships_present.contains(&ship_id));
+    }
+    
+    #[test]
+    fn armed_hostiles_intercept_at_waypoints() {
+        let mut state = two_player_game();
+        let ship_id = state.spawn_ship("p1", ShipType::Frigate, "earth-1", "Pioneer").unwrap();
+        state.spawn_ship("p2", ShipType::Destroyer, "earth-2", "Picket");
+        state.execute_command("p1", Command::Move { ship_id: ship_id.clone(), sector_id: "earth-3".to_string() });
+        
+        run_until_stopped(&mut state, &ship_id);
+        let ship = &state.ships[&ship_id];
+        assert_eq!(ship.current_sector, "earth-2");
+        assert_eq!(ship.fuel_used, Ship::fuel_for(1));
+        let events = state.take_events();
+        assert!(events.iter().any(|e| e.player_id == "p1" && e.message.contains("intercepted")));
+        assert!(events.iter().any(|e| e.player_id == "p2" && e.message.contains("intercepted")));
+    }
+    
+    #[test]
+    fn inbound_ships_show_up_on_scans() {
+        let mut state = two_player_game();
+        let ship_id = state.spawn_ship("p1", ShipType::Frigate, "earth-1", "Pioneer").unwrap();
+        state.execute_command("p1", Command::Move { ship_id: ship_id.clone(), sector_id: "earth-3".to_string() });
+        
+        let inbound: Vec<&str> = state.inbound_ships("earth-3", "p2", 100).iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(inbound, vec![ship_id.as_str()]);
+        assert!(state.inbound_ships("earth-3", "p1", 100).is_empty(), "own ships aren't reported");
+        assert!(state.inbound_ships("earth-2", "p2", 100).is_empty(), "only the destination counts");
+    }
+}
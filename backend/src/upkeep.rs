@@ -0,0 +1,226 @@
+use crate::game::{GameState, GameEvent, Player};
+use crate::ships::ship_class;
+
+const BASE_INCOME: i32 = 10; // Credits every commander receives each cycle
+const SECTOR_INCOME: i32 = 10; // Per controlled sector, each cycle
+const AP_REFILL: i32 = 50;
+const DEBT_AP_REFILL: i32 = 25; // While in debt
+const DECOMMISSION_AFTER: u32 = 3; // Cycles in debt before ships start being scrapped
+
+impl Player {
+    // AP added at the start of each cycle
+    pub fn ap_refill(&self) -> i32 {
+        if self.debt_cycles > 0 { DEBT_AP_REFILL } else { AP_REFILL }
+    }
+}
+
+impl GameState {
+    pub fn income(&self, player_id: &str) -> i32 {
+        let sectors = self.sectors.iter()
+            .filter(|s| s.controlled_by.as_deref() == Some(player_id))
+            .count() as i32;
+        BASE_INCOME + sectors * SECTOR_INCOME
+    }
+    
+    pub fn upkeep(&self, player_id: &str) -> i32 {
+        self.ships.values()
+            .filter(|s| s.owner == player_id)
+            .map(|s| ship_class(&s.ship_type).upkeep)
+            .sum()
+    }
+    
+    // Pays income and charges upkeep. Players still in debt after that get a reduced AP
+    // refill, and once they've been in debt long enough, lose their lowest tier ship each
+    // cycle. Called once per cycle, before the AP refill
+    pub fn process_upkeep(&mut self) {
+        let mut player_ids: Vec<String> = self.players.keys().cloned().collect();
+        player_ids.sort();
+        
+        for player_id in player_ids {
+            let net = self.income(&player_id) - self.upkeep(&player_id);
+            let player = self.players.get_mut(&player_id).unwrap();
+            player.credits += net;
+            if player.credits >= 0 {
+                if player.debt_cycles > 0 {
+                    player.debt_cycles = 0;
+                    self.events.push(GameEvent {
+                        player_id: player_id.clone(),
+                        message: "You're out of debt; AP refill is back to normal".to_string(),
+                    });
+                }
+            } else {
+                player.debt_cycles += 1;
+                let credits = player.credits;
+                let debt_cycles = player.debt_cycles;
+                if debt_cycles >= DECOMMISSION_AFTER {
+                    self.decommission_lowest_tier(&player_id);
+                } else {
+                    self.events.push(GameEvent {
+                        player_id: player_id.clone(),
+                        message: format!(
+                            "You're {} credits in debt: AP refill is cut to {} until you're back in credit",
+                            -credits, DEBT_AP_REFILL
+                        ),
+                    });
+                }
+            }
+            
+            self.warn_of_debt(&player_id);
+        }
+    }
+    
+    // Tells a player a cycle ahead when upkeep is about to put them into debt or cost them a ship
+    fn warn_of_debt(&mut self, player_id: &str) {
+        let player = &self.players[player_id];
+        let projected = player.credits + self.income(player_id) - self.upkeep(player_id);
+        if projected >= 0 {
+            return;
+        }
+        
+        let message = if player.credits >= 0 {
+            format!(
+                "Warning: upkeep will put you {} credits in debt next cycle, cutting your AP refill to {}",
+                -projected, DEBT_AP_REFILL
+            )
+        } else if player.debt_cycles + 1 >= DECOMMISSION_AFTER && !player.owned_ships.is_empty() {
+            "Warning: if you're still in debt next cycle, your lowest tier ship will be decommissioned".to_string()
+        } else {
+            return;
+        };
+        self.events.push(GameEvent {
+            player_id: player_id.to_string(),
+            message,
+        });
+    }
+    
+    fn decommission_lowest_tier(&mut self, player_id: &str) {
+        let ship = self.ships.values()
+            .filter(|s| s.owner == player_id && !self.ship_in_escrow(&s.id))
+            .min_by_key(|s| {
+                let class = ship_class(&s.ship_type);
+                (class.tier, class.upkeep, s.id.clone())
+            })
+            .cloned();
+        let ship = match ship {
+            Some(s) => s,
+            None => return,
+        };
+        
+        self.destroy_ship(&ship.id);
+        self.events.push(GameEvent {
+            player_id: player_id.to_string(),
+            message: format!("{} ({:?}) was decommissioned to cut costs: you've been in debt too long", ship.name, ship.ship_type),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::ShipType;
+    use crate::trade::TradeBundle;
+    
+    fn indebted_fleet(credits: i32) -> GameState {
+        let mut state = GameState::new();
+        state.players.insert("p1".to_string(), Player::new("p1", "Alpha"));
+        state.players.insert("p2".to_string(), Player::new("p2", "Beta"));
+        state.players.get_mut("p1").unwrap().credits = credits;
+        state
+    }
+    
+    fn messages_for(state: &mut GameState, player_id: &str) -> Vec<String> {
+        state.take_events().into_iter()
+            .filter(|e| e.player_id == player_id)
+            .map(|e| e.message)
+            .collect()
+    }
+    
+    #[test]
+    fn debt_cuts_the_ap_refill_until_paid_off() {
+        let mut state = indebted_fleet(0);
+        state.spawn_ship("p1", ShipType::Battleship, "earth-1", "Bulwark");
+        let net = state.income("p1") - state.upkeep("p1");
+        assert!(net < 0);
+        
+        state.process_upkeep();
+        let player = &state.players["p1"];
+        assert_eq!(player.credits, net);
+        assert_eq!(player.debt_cycles, 1);
+        assert_eq!(player.ap_refill(), DEBT_AP_REFILL);
+        assert!(messages_for(&mut state, "p1").iter().any(|m| m.contains("in debt")));
+        
+        state.players.get_mut("p1").unwrap().credits = 1000;
+        state.process_upkeep();
+        let player = &state.players["p1"];
+        assert_eq!(player.debt_cycles, 0);
+        assert_eq!(player.ap_refill(), AP_REFILL);
+        assert!(messages_for(&mut state, "p1").iter().any(|m| m.contains("out of debt")));
+    }
+    
+    #[test]
+    fn long_debt_decommissions_the_lowest_tier_first() {
+        let mut state = indebted_fleet(-1000);
+        let battleship = state.spawn_ship("p1", ShipType::Battleship, "earth-1", "Bulwark").unwrap();
+        let interceptor = state.spawn_ship("p1", ShipType::Interceptor, "earth-1", "Dart").unwrap();
+        let drone = state.spawn_ship("p1", ShipType::ScoutDrone, "earth-1", "Eye").unwrap();
+        
+        for _ in 1..DECOMMISSION_AFTER {
+            state.process_upkeep();
+        }
+        assert_eq!(state.players["p1"].debt_cycles, DECOMMISSION_AFTER - 1);
+        assert_eq!(state.players["p1"].owned_ships.len(), 3);
+        
+        // Tier 1 both, but the drone costs less to keep
+        state.process_upkeep();
+        assert!(!state.ships.contains_key(&drone));
+        assert!(messages_for(&mut state, "p1").iter().any(|m| m.contains("Eye") && m.contains("decommissioned")));
+        
+        state.process_upkeep();
+        assert!(!state.ships.contains_key(&interceptor));
+        assert!(state.ships.contains_key(&battleship));
+    }
+    
+    #[test]
+    fn escrowed_ships_are_not_decommissioned() {
+        let mut state = indebted_fleet(0);
+        let interceptor = state.spawn_ship("p1", ShipType::Interceptor, "earth-1", "Dart").unwrap();
+        let drone = state.spawn_ship("p1", ShipType::ScoutDrone, "earth-1", "Eye").unwrap();
+        state.players.get_mut("p2").unwrap().resources.insert(crate::game::Resource::Ore, 1);
+        let give = TradeBundle::parse(&[drone.as_str()]).unwrap();
+        let want = TradeBundle::parse(&["ore:1"]).unwrap();
+        assert!(state.offer_trade("p1", "Beta", give, want).success);
+        
+        state.players.get_mut("p1").unwrap().credits = -1000;
+        state.players.get_mut("p1").unwrap().debt_cycles = DECOMMISSION_AFTER - 1;
+        state.process_upkeep();
+        assert!(state.ships.contains_key(&drone));
+        assert!(!state.ships.contains_key(&interceptor));
+    }
+    
+    #[test]
+    fn warns_a_cycle_ahead() {
+        let mut state = indebted_fleet(0);
+        state.spawn_ship("p1", ShipType::Battleship, "earth-1", "Bulwark");
+        let shortfall = state.upkeep("p1") - state.income("p1");
+        
+        state.players.get_mut("p1").unwrap().credits = shortfall - 1;
+        state.warn_of_debt("p1");
+        assert_eq!(messages_for(&mut state, "p1").len(), 1);
+        
+        // Still in credit, but not for long
+        state.players.get_mut("p1").unwrap().credits = shortfall / 2;
+        state.warn_of_debt("p1");
+        let messages = messages_for(&mut state, "p1");
+        assert!(messages[0].contains("in debt next cycle"), "{:?}", messages);
+        
+        // Already in debt: only the cycle before ships go gets a warning
+        state.players.get_mut("p1").unwrap().credits = -5;
+        state.players.get_mut("p1").unwrap().debt_cycles = 1;
+        state.warn_of_debt("p1");
+        assert!(messages_for(&mut state, "p1").is_empty());
+        state.players.get_mut("p1").unwrap().debt_cycles = DECOMMISSION_AFTER - 1;
+        state.warn_of_debt("p1");
+        let messages = messages_for(&mut state, "p1");
+        assert!(messages[0].contains("decommissioned"), "{:?}", messages);
+    }
+}
@@ -0,0 +1,248 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+
+use crate::game::{GameState, Player, ShipType};
+use crate::diplomacy::TreatyKind;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum VictoryCondition {
+    Elimination, // Last side with ships or territory
+    HoldSector { sector_id: String, cycles: u32 }, // Control a sector for N consecutive cycles
+    ControlPercent { percent: u32 }, // Control at least this share of all sectors
+    ScoreAtCycle { cycle_limit: u32 }, // Highest score once the cycle limit is reached
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Standing {
+    pub player_id: String,
+    pub name: String,
+    pub score: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct GameOutcome {
+    pub winners: Vec<String>, // Player IDs, more than one for shared-victory alliances
+    pub condition: VictoryCondition,
+    pub cycle: u32,
+    pub standings: Vec<Standing>,
+    pub finished_at: DateTime<Utc>,
+}
+
+pub fn default_victory_conditions() -> Vec<VictoryCondition> {
+    vec![
+        VictoryCondition::Elimination,
+        VictoryCondition::HoldSector { sector_id: "earth-core".to_string(), cycles: 9 },
+        VictoryCondition::ControlPercent { percent: 60 },
+        VictoryCondition::ScoreAtCycle { cycle_limit: 90 },
+    ]
+}
+
+impl GameState {
+    pub fn standings(&self) -> Vec<Standing> {
+        let mut standings: Vec<Standing> = self.players.values()
+            .map(|p| Standing {
+                player_id: p.id.clone(),
+                name: p.name.clone(),
+                score: self.score(&p.id),
+            })
+            .collect();
+        standings.sort_by(|a, b| b.score.cmp(&a.score).then(a.name.cmp(&b.name)));
+        standings
+    }
+    
+    // A player plus every ally they agreed to share victory with
+    fn victory_side(&self, player_id: &str) -> Vec<String> {
+        let mut side = vec![player_id.to_string()];
+        side.extend(self.relations.iter()
+            .filter(|t| t.accepted && t.kind == TreatyKind::Alliance && t.shared_victory)
+            .filter(|t| t.proposer == player_id || t.partner == player_id)
+            .map(|t| t.other_party(player_id).to_string()));
+        side.sort();
+        side
+    }
+    
+    fn is_eliminated(&self, player_id: &str) -> bool {
+        let player = &self.players[player_id];
+        player.owned_ships.is_empty()
+            && !self.sectors.iter().any(|s| s.controlled_by.as_deref() == Some(player_id))
+    }
+    
+    // Called every cycle, before victory is checked
+    pub fn update_sector_holds(&mut self) {
+        let held: Vec<String> = self.settings.victory_conditions.iter()
+            .filter_map(|c| match c {
+                VictoryCondition::HoldSector { sector_id, .. } => Some(sector_id.clone()),
+                _ => None,
+            })
+            .collect();
+        
+        for sector_id in held {
+            let controller = self.sectors.iter()
+                .find(|s| s.id == sector_id)
+                .and_then(|s| s.controlled_by.clone());
+            match controller {
+                Some(player_id) => {
+                    let streak = self.sector_holds.entry(sector_id).or_insert((player_id.clone(), 0));
+                    if streak.0 == player_id {
+                        streak.1 += 1;
+                    } else {
+                        *streak = (player_id, 1);
+                    }
+                }
+                None => {
+                    self.sector_holds.remove(&sector_id);
+                }
+            }
+        }
+    }
+    
+    // Evaluated after every command and cycle; the server announces the result
+    pub fn check_victory(&mut self) {
+        if self.outcome.is_some() {
+            return;
+        }
+        
+        let conditions = self.settings.victory_conditions.clone();
+        let winner = conditions.iter().find_map(|condition| {
+            self.victory_winner(condition).map(|w| (condition.clone(), w))
+        });
+        let (condition, winner_id) = match winner {
+            Some(w) => w,
+            None => return,
+        };
+        
+        let winners = self.victory_side(&winner_id);
+        let standings = self.standings();
+        
+        self.outcome = Some(GameOutcome {
+            winners,
+            condition,
+            cycle: self.cycle_number,
+            standings,
+            finished_at: Utc::now(),
+        });
+    }
+    
+    fn victory_winner(&self, condition: &VictoryCondition) -> Option<String> {
+        match condition {
+            VictoryCondition::Elimination => {
+                if self.players.len() < 2 {
+                    return None;
+                }
+                let mut alive: Vec<&String> = self.players.keys().filter(|id| !self.is_eliminated(id)).collect();
+                alive.sort();
+                let first = alive.first()?;
+                let side = self.victory_side(first);
+                if alive.iter().all(|id| side.contains(id)) && self.players.len() > side.len() {
+                    Some(first.to_string())
+                } else {
+                    None
+                }
+            }
+            VictoryCondition::HoldSector { sector_id, cycles } => {
+                self.sector_holds.get(sector_id)
+                    .filter(|(_, held)| held >= cycles)
+                    .map(|(player_id, _)| player_id.clone())
+            }
+            VictoryCondition::ControlPercent { percent } => {
+                let mut ids: Vec<&String> = self.players.keys().collect();
+                ids.sort();
+                ids.into_iter()
+                    .find(|id| {
+                        let controlled = self.sectors.iter()
+                            .filter(|s| s.controlled_by.as_deref() == Some(id.as_str()))
+                            .count();
+                        !self.sectors.is_empty() && controlled * 100 >= self.sectors.len() * *percent as usize
+                    })
+                    .cloned()
+            }
+            VictoryCondition::ScoreAtCycle { cycle_limit } => {
+                if self.cycle_number < *cycle_limit {
+                    return None;
+                }
+                self.standings().first().map(|s| s.player_id.clone())
+            }
+        }
+    }
+    
+    // Fresh map for the next season. Everyone keeps their account and bots keep playing;
+    // progress resets to the standard start of one Frigate
+    pub fn next_season(&self) -> GameState {
+        let mut next = GameState::with_settings(self.settings.clone());
+        next.season = self.season + 1;
+        next.bots = self.bots.clone();
+        next.banned = self.banned.clone();
+        
+        let mut player_ids: Vec<&String> = self.players.keys().collect();
+        player_ids.sort();
+        for player_id in player_ids {
+            let name = &self.players[player_id].name;
+            let mut player = Player::new(player_id, name);
+            // Where to reach someone isn't part of the standings, so it outlasts the season
+            player.notifications = self.players[player_id].notifications.clone();
+            player.inbox = self.players[player_id].inbox.clone();
+            player.push_subscriptions = self.players[player_id].push_subscriptions.clone();
+            next.players.insert(player_id.clone(), player);
+            if let Some(home) = next.open_home_sector() {
+                next.spawn_ship(player_id, ShipType::Frigate, &home, &format!("{} Pioneer", name));
+            }
+        }
+        
+        next
+    }
+}
+
+impl VictoryCondition {
+    pub fn describe(&self) -> String {
+        match self {
+            VictoryCondition::Elimination => "elimination".to_string(),
+            VictoryCondition::HoldSector { sector_id, cycles } => format!("held {} for {} cycles", sector_id, cycles),
+            VictoryCondition::ControlPercent { percent } => format!("controlled {}% of sectors", percent),
+            VictoryCondition::ScoreAtCycle { cycle_limit } => format!("highest score at cycle {}", cycle_limit),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{GameState, Player};
+    use crate::notifications::NotificationKind;
+    use crate::webpush::{PushKeys, PushSubscription};
+    
+    #[test]
+    fn bans_outlast_the_season() {
+        let mut state = GameState::new();
+        state.players.insert("p1".to_string(), Player::new("p1", "Alpha"));
+        state.players.insert("p2".to_string(), Player::new("p2", "Beta"));
+        state.banned.push("p2".to_string());
+        
+        let next = state.next_season();
+        assert_eq!(next.season, 2);
+        assert_eq!(next.banned, vec!["p2".to_string()]);
+    }
+    
+    #[test]
+    fn notification_settings_outlast_the_season() {
+        let mut state = GameState::new();
+        let mut player = Player::new("p1", "Alpha");
+        player.notifications.webhook = Some("http://relay.local/hook".to_string());
+        player.notifications.email = Some("alpha@example.com".to_string());
+        player.push_subscriptions.push(PushSubscription {
+            endpoint: "https://fcm.googleapis.com/fcm/send/abc".to_string(),
+            keys: PushKeys { p256dh: "key".to_string(), auth: "secret".to_string() },
+        });
+        state.players.insert("p1".to_string(), player);
+        state.alert("p1", NotificationKind::TradeOffer, "An offer".to_string());
+        let alert = state.take_alerts().remove(0);
+        state.file_notification(&alert);
+        
+        let next = state.next_season();
+        let prefs = &next.players["p1"].notifications;
+        assert_eq!(prefs.webhook.as_deref(), Some("http://relay.local/hook"));
+        assert_eq!(prefs.email.as_deref(), Some("alpha@example.com"));
+        assert_eq!(next.players["p1"].inbox.len(), 1);
+        assert_eq!(next.players["p1"].push_subscriptions.len(), 1);
+    }
+}
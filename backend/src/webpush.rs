@@ -0,0 +1,487 @@
+use axum::{
+    Router,
+    routing::{get, post},
+    response::Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use aes_gcm::{Aes128Gcm, KeyInit, Nonce, aead::Aead};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use hkdf::Hkdf;
+use p256::{PublicKey, ecdh::EphemeralSecret, ecdsa::{Signature, SigningKey, signature::Signer}};
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::Sha256;
+use std::sync::{Arc, OnceLock};
+use chrono::Utc;
+
+use crate::game::{GameState, Player};
+use crate::lobby::GameRegistry;
+use crate::notifications::Notification;
+
+pub const VAPID_FILE: &str = "../data/vapid.json";
+const DEFAULT_SUBJECT: &str = "mailto:admin@interstellar.local"; // Who push services contact about abuse
+const MAX_SUBSCRIPTIONS: usize = 5; // Per player, one per device; the oldest is dropped past this
+const PUSH_TTL_SECS: u32 = 24 * 60 * 60; // How long a push service holds a message for a device that's off
+const JWT_LIFETIME_SECS: i64 = 12 * 60 * 60; // Push services refuse tokens valid for more than 24 hours
+const RECORD_SIZE: u32 = 4096;
+const HEADER_SIZE: usize = 16 + 4 + 1 + 65; // Salt, record size, key ID length and our public key
+// Push services cap the whole body at a record, so that's less the header, the GCM tag and
+// the padding delimiter
+const MAX_PLAINTEXT: usize = RECORD_SIZE as usize - HEADER_SIZE - 16 - 1;
+const MOCK_ORIGIN_VAR: &str = "PUSH_MOCK_ORIGIN"; // An http:// origin accepted as a push service, for testing
+
+// The push services browsers hand out endpoints on. Subscriptions come from players, so
+// anything else is refused rather than letting the server be pointed at arbitrary hosts
+const PUSH_SERVICE_HOSTS: [&str; 4] = [
+    "fcm.googleapis.com",
+    "updates.push.services.mozilla.com",
+    "notify.windows.com",
+    "push.apple.com",
+];
+
+// The server's VAPID key pair, identifying it to push services. Loaded at startup; until
+// then nothing is pushed
+static VAPID: OnceLock<SigningKey> = OnceLock::new();
+
+#[derive(Serialize, Deserialize)]
+struct VapidFile {
+    private_key: String, // Base64url of the raw P-256 scalar
+}
+
+// A browser's push subscription, as PushSubscription.toJSON() gives it
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PushSubscription {
+    pub endpoint: String,
+    pub keys: PushKeys,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PushKeys {
+    pub p256dh: String, // The browser's P-256 public key, base64url
+    pub auth: String, // 16-byte shared secret, base64url
+}
+
+#[derive(Deserialize)]
+struct SubscribeRequest {
+    player_id: String,
+    subscription: PushSubscription,
+}
+
+#[derive(Deserialize)]
+struct UnsubscribeRequest {
+    player_id: String,
+    endpoint: String,
+}
+
+// What the service worker receives, decrypted
+#[derive(Serialize)]
+struct PushPayload<'a> {
+    title: &'a str,
+    body: &'a str,
+    game_id: &'a str,
+    #[serde(flatten)]
+    notification: &'a Notification,
+}
+
+pub enum Delivery {
+    Sent,
+    Gone, // The subscription has expired or been revoked and should be forgotten
+}
+
+// Loads the VAPID key from VAPID_PRIVATE_KEY or the key file, generating and saving a new
+// one on first run. Returns whether a new key was generated
+pub async fn load_vapid_keys() -> Result<bool, String> {
+    let (encoded, generated) = match std::env::var("VAPID_PRIVATE_KEY") {
+        Ok(key) if !key.is_empty() => (key, false),
+        _ => match tokio::fs::read_to_string(VAPID_FILE).await {
+            Ok(json) => {
+                let file: VapidFile = serde_json::from_str(&json).map_err(|e| format!("{}: {}", VAPID_FILE, e))?;
+                (file.private_key, false)
+            }
+            Err(_) => {
+                let key = SigningKey::random(&mut OsRng);
+                let encoded = URL_SAFE_NO_PAD.encode(key.to_bytes());
+                let json = serde_json::to_string_pretty(&VapidFile { private_key: encoded.clone() }).unwrap();
+                tokio::fs::create_dir_all("../data").await.map_err(|e| e.to_string())?;
+                tokio::fs::write(VAPID_FILE, json).await.map_err(|e| format!("Can't write {}: {}", VAPID_FILE, e))?;
+                (encoded, true)
+            }
+        },
+    };
+    
+    let bytes = URL_SAFE_NO_PAD.decode(encoded.trim()).map_err(|e| format!("Invalid VAPID key: {}", e))?;
+    let key = SigningKey::from_slice(&bytes).map_err(|e| format!("Invalid VAPID key: {}", e))?;
+    let _ = VAPID.set(key);
+    Ok(generated)
+}
+
+// The applicationServerKey browsers subscribe with: the uncompressed public key, base64url
+pub fn vapid_public_key() -> Option<String> {
+    VAPID.get().map(|key| URL_SAFE_NO_PAD.encode(key.verifying_key().to_encoded_point(false).as_bytes()))
+}
+
+impl PushSubscription {
+    // The endpoint must be on a known push service and the keys a real P-256 point and a
+    // 16-byte secret
+    fn validate(&self) -> Result<(PublicKey, Vec<u8>), String> {
+        if !endpoint_allowed(&self.endpoint, &std::env::var(MOCK_ORIGIN_VAR).unwrap_or_default()) {
+            return Err("The endpoint must be an https:// URL on a browser push service".to_string());
+        }
+        self.keys()
+    }
+    
+    fn keys(&self) -> Result<(PublicKey, Vec<u8>), String> {
+        let p256dh = URL_SAFE_NO_PAD.decode(self.keys.p256dh.trim_end_matches('=')).map_err(|_| "p256dh isn't base64url")?;
+        let public_key = PublicKey::from_sec1_bytes(&p256dh).map_err(|_| "p256dh isn't a P-256 public key")?;
+        let auth = URL_SAFE_NO_PAD.decode(self.keys.auth.trim_end_matches('=')).map_err(|_| "auth isn't base64url")?;
+        if auth.len() != 16 {
+            return Err("auth must be 16 bytes".to_string());
+        }
+        Ok((public_key, auth))
+    }
+    
+    // Encrypts a payload for this subscription as an aes128gcm body (RFC 8291 and 8188): a
+    // fresh key pair is agreed with the browser's key, mixed with the auth secret, and the
+    // payload sealed in a single record
+    pub fn encrypt(&self, payload: &[u8]) -> Result<Vec<u8>, String> {
+        let (ua_public, auth) = self.keys()?;
+        if payload.len() > MAX_PLAINTEXT {
+            return Err(format!("payload is {} bytes; a record holds {}", payload.len(), MAX_PLAINTEXT));
+        }
+        let ua_public_bytes = ua_public.to_encoded_point(false);
+        let as_secret = EphemeralSecret::random(&mut OsRng);
+        let as_public_bytes = as_secret.public_key().to_encoded_point(false);
+        let shared = as_secret.diffie_hellman(&ua_public);
+        
+        let mut key_info = b"WebPush: info\0".to_vec();
+        key_info.extend_from_slice(ua_public_bytes.as_bytes());
+        key_info.extend_from_slice(as_public_bytes.as_bytes());
+        let mut ikm = [0u8; 32];
+        Hkdf::<Sha256>::new(Some(&auth), shared.raw_secret_bytes())
+            .expand(&key_info, &mut ikm)
+            .map_err(|e| e.to_string())?;
+        
+        let mut salt = [0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        let prk = Hkdf::<Sha256>::new(Some(&salt), &ikm);
+        let mut cek = [0u8; 16];
+        let mut nonce = [0u8; 12];
+        prk.expand(b"Content-Encoding: aes128gcm\0", &mut cek).map_err(|e| e.to_string())?;
+        prk.expand(b"Content-Encoding: nonce\0", &mut nonce).map_err(|e| e.to_string())?;
+        
+        // 0x02 marks the last (and only) record
+        let mut plaintext = payload.to_vec();
+        plaintext.push(2);
+        let ciphertext = Aes128Gcm::new_from_slice(&cek).unwrap()
+            .encrypt(Nonce::from_slice(&nonce), plaintext.as_slice())
+            .map_err(|e| e.to_string())?;
+        
+        let mut body = salt.to_vec();
+        body.extend_from_slice(&RECORD_SIZE.to_be_bytes());
+        body.push(as_public_bytes.as_bytes().len() as u8);
+        body.extend_from_slice(as_public_bytes.as_bytes());
+        body.extend_from_slice(&ciphertext);
+        Ok(body)
+    }
+}
+
+// https:// on one of the push services or a subdomain of one, or under the configured mock origin
+fn endpoint_allowed(endpoint: &str, mock_origin: &str) -> bool {
+    let mock_origin = mock_origin.trim_end_matches('/');
+    if !mock_origin.is_empty() && endpoint.strip_prefix(mock_origin).is_some_and(|rest| rest.starts_with('/')) {
+        return true;
+    }
+    let host = match endpoint.strip_prefix("https://") {
+        Some(rest) => rest.split('/').next().unwrap_or("").to_lowercase(),
+        None => return false,
+    };
+    if host.contains(['@', ':']) {
+        return false;
+    }
+    PUSH_SERVICE_HOSTS.iter().any(|known| host == *known || host.ends_with(&format!(".{}", known)))
+}
+
+// The encrypted payload has to fit in one record, so long messages are cut short
+fn push_payload(game_name: &str, game_id: &str, notification: &Notification) -> Vec<u8> {
+    let mut message = notification.message.as_str();
+    loop {
+        let body = if message.len() < notification.message.len() { format!("{}…", message) } else { message.to_string() };
+        let payload = serde_json::to_vec(&PushPayload {
+            title: game_name,
+            body: &body,
+            game_id,
+            notification: &Notification { message: body.clone(), ..notification.clone() },
+        }).unwrap();
+        if payload.len() <= MAX_PLAINTEXT || message.is_empty() {
+            return payload;
+        }
+        // Escaping can make the JSON longer than the text, so trim by the overshoot and recheck
+        let mut end = message.len().saturating_sub(payload.len() - MAX_PLAINTEXT);
+        while !message.is_char_boundary(end) {
+            end -= 1;
+        }
+        message = &message[..end];
+    }
+}
+
+// Signed VAPID token for the push service behind an endpoint
+fn vapid_authorization(key: &SigningKey, endpoint: &str) -> String {
+    let origin = endpoint.splitn(4, '/').take(3).collect::<Vec<_>>().join("/");
+    let subject = std::env::var("VAPID_SUBJECT").unwrap_or_else(|_| DEFAULT_SUBJECT.to_string());
+    let header = URL_SAFE_NO_PAD.encode(json!({"typ": "JWT", "alg": "ES256"}).to_string());
+    let claims = URL_SAFE_NO_PAD.encode(json!({
+        "aud": origin,
+        "exp": Utc::now().timestamp() + JWT_LIFETIME_SECS,
+        "sub": subject,
+    }).to_string());
+    let signing_input = format!("{}.{}", header, claims);
+    let signature: Signature = key.sign(signing_input.as_bytes());
+    let public_key = URL_SAFE_NO_PAD.encode(key.verifying_key().to_encoded_point(false).as_bytes());
+    format!("vapid t={}.{}, k={}", signing_input, URL_SAFE_NO_PAD.encode(signature.to_bytes()), public_key)
+}
+
+// Sends one notification to one subscription through its push service
+pub async fn send_push(subscription: &PushSubscription, game_name: &str, game_id: &str, notification: &Notification) -> Result<Delivery, String> {
+    let key = VAPID.get().ok_or("no VAPID key loaded")?;
+    // Subscriptions saved before endpoints were checked may point anywhere
+    subscription.validate()?;
+    deliver(key, subscription, &push_payload(game_name, game_id, notification)).await
+}
+
+async fn deliver(key: &SigningKey, subscription: &PushSubscription, payload: &[u8]) -> Result<Delivery, String> {
+    let body = subscription.encrypt(payload)?;
+    
+    let response = reqwest::Client::new()
+        .post(&subscription.endpoint)
+        .header("TTL", PUSH_TTL_SECS.to_string())
+        .header("Content-Encoding", "aes128gcm")
+        .header("Content-Type", "application/octet-stream")
+        .header("Urgency", "high")
+        .header("Authorization", vapid_authorization(key, &subscription.endpoint))
+        .timeout(std::time::Duration::from_secs(10))
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    
+    match response.status().as_u16() {
+        200..=299 => Ok(Delivery::Sent),
+        404 | 410 => Ok(Delivery::Gone),
+        status => Err(format!("push service answered {}", status)),
+    }
+}
+
+impl GameState {
+    pub fn forget_subscription(&mut self, player_id: &str, endpoint: &str) -> bool {
+        match self.players.get_mut(player_id) {
+            Some(player) => {
+                let before = player.push_subscriptions.len();
+                player.push_subscriptions.retain(|s| s.endpoint != endpoint);
+                player.push_subscriptions.len() != before
+            }
+            None => false,
+        }
+    }
+}
+
+impl Player {
+    // A device subscribing again replaces its old subscription
+    fn add_subscription(&mut self, subscription: PushSubscription) {
+        self.push_subscriptions.retain(|s| s.endpoint != subscription.endpoint);
+        self.push_subscriptions.push(subscription);
+        let excess = self.push_subscriptions.len().saturating_sub(MAX_SUBSCRIPTIONS);
+        self.push_subscriptions.drain(..excess);
+    }
+}
+
+pub fn router() -> Router<Arc<GameRegistry>> {
+    Router::new()
+        .route("/api/push/key", get(public_key))
+        .route("/api/games/:game_id/push/subscribe", post(subscribe))
+        .route("/api/games/:game_id/push/unsubscribe", post(unsubscribe))
+}
+
+async fn public_key() -> Result<Json<serde_json::Value>, StatusCode> {
+    let key = vapid_public_key().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    Ok(Json(json!({ "public_key": key })))
+}
+
+async fn subscribe(
+    Path(game_id): Path<String>,
+    State(registry): State<Arc<GameRegistry>>,
+    Json(req): Json<SubscribeRequest>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let game_server = registry.get(&game_id).await.ok_or((StatusCode::NOT_FOUND, format!("No game {}", game_id)))?;
+    req.subscription.validate().map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    
+    let mut game_state = game_server.game_state.write().await;
+    if game_state.banned.contains(&req.player_id) {
+        return Err((StatusCode::FORBIDDEN, "Banned from this game".to_string()));
+    }
+    let player = game_state.players.get_mut(&req.player_id).ok_or((StatusCode::NOT_FOUND, "No such player".to_string()))?;
+    player.add_subscription(req.subscription);
+    game_server.save(&game_state).await;
+    Ok(StatusCode::CREATED)
+}
+
+async fn unsubscribe(
+    Path(game_id): Path<String>,
+    State(registry): State<Arc<GameRegistry>>,
+    Json(req): Json<UnsubscribeRequest>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let game_server = registry.get(&game_id).await.ok_or((StatusCode::NOT_FOUND, format!("No game {}", game_id)))?;
+    
+    let mut game_state = game_server.game_state.write().await;
+    if !game_state.forget_subscription(&req.player_id, &req.endpoint) {
+        return Err((StatusCode::NOT_FOUND, "No such subscription".to_string()));
+    }
+    game_server.save(&game_state).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notifications::NotificationKind;
+    use p256::SecretKey;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    
+    // A browser's side of a subscription: its key pair and auth secret
+    fn browser() -> (SecretKey, [u8; 16], PushSubscription) {
+        let secret = SecretKey::random(&mut OsRng);
+        let mut auth = [0u8; 16];
+        OsRng.fill_bytes(&mut auth);
+        let subscription = PushSubscription {
+            endpoint: "https://fcm.googleapis.com/fcm/send/device".to_string(),
+            keys: PushKeys {
+                p256dh: URL_SAFE_NO_PAD.encode(secret.public_key().to_encoded_point(false).as_bytes()),
+                auth: URL_SAFE_NO_PAD.encode(auth),
+            },
+        };
+        (secret, auth, subscription)
+    }
+    
+    // What the browser does with an aes128gcm body, following RFC 8291 section 3.4
+    fn decrypt(secret: &SecretKey, auth: &[u8], body: &[u8]) -> Vec<u8> {
+        let salt = &body[..16];
+        assert_eq!(u32::from_be_bytes(body[16..20].try_into().unwrap()), RECORD_SIZE);
+        let id_len = body[20] as usize;
+        let as_public = PublicKey::from_sec1_bytes(&body[21..21 + id_len]).unwrap();
+        let ciphertext = &body[21 + id_len..];
+        
+        let shared = p256::ecdh::diffie_hellman(secret.to_nonzero_scalar(), as_public.as_affine());
+        let mut key_info = b"WebPush: info\0".to_vec();
+        key_info.extend_from_slice(secret.public_key().to_encoded_point(false).as_bytes());
+        key_info.extend_from_slice(as_public.to_encoded_point(false).as_bytes());
+        let mut ikm = [0u8; 32];
+        Hkdf::<Sha256>::new(Some(auth), shared.raw_secret_bytes()).expand(&key_info, &mut ikm).unwrap();
+        
+        let prk = Hkdf::<Sha256>::new(Some(salt), &ikm);
+        let mut cek = [0u8; 16];
+        let mut nonce = [0u8; 12];
+        prk.expand(b"Content-Encoding: aes128gcm\0", &mut cek).unwrap();
+        prk.expand(b"Content-Encoding: nonce\0", &mut nonce).unwrap();
+        let mut plaintext = Aes128Gcm::new_from_slice(&cek).unwrap()
+            .decrypt(Nonce::from_slice(&nonce), ciphertext)
+            .unwrap();
+        assert_eq!(plaintext.pop(), Some(2));
+        plaintext
+    }
+    
+    fn notification(message: &str) -> Notification {
+        Notification {
+            kind: NotificationKind::UnderAttack,
+            message: message.to_string(),
+            cycle: 3,
+            sent_at: Utc::now(),
+        }
+    }
+    
+    #[test]
+    fn encrypted_payload_round_trips() {
+        let (secret, auth, subscription) = browser();
+        let body = subscription.encrypt(b"Under attack at Earth-4").unwrap();
+        assert_eq!(decrypt(&secret, &auth, &body), b"Under attack at Earth-4");
+    }
+    
+    #[test]
+    fn long_messages_are_cut_to_fit_a_record() {
+        let (secret, auth, subscription) = browser();
+        let payload = push_payload("Season 1", "game-1", &notification(&"\"é\" ".repeat(2000)));
+        assert!(payload.len() <= MAX_PLAINTEXT);
+        
+        let body = subscription.encrypt(&payload).unwrap();
+        assert!(body.len() <= RECORD_SIZE as usize);
+        let json: serde_json::Value = serde_json::from_slice(&decrypt(&secret, &auth, &body)).unwrap();
+        assert!(json["body"].as_str().unwrap().ends_with('…'));
+        assert_eq!(json["body"], json["message"]);
+        assert_eq!(subscription.encrypt(&vec![b'x'; MAX_PLAINTEXT]).unwrap().len(), RECORD_SIZE as usize);
+        assert!(subscription.encrypt(&vec![b'x'; MAX_PLAINTEXT + 1]).is_err());
+    }
+    
+    #[test]
+    fn endpoints_must_be_push_services() {
+        assert!(endpoint_allowed("https://fcm.googleapis.com/fcm/send/abc", ""));
+        assert!(endpoint_allowed("https://wns2-par02p.notify.windows.com/w/?token=abc", ""));
+        assert!(!endpoint_allowed("http://fcm.googleapis.com/fcm/send/abc", ""));
+        assert!(!endpoint_allowed("https://evil-fcm.googleapis.com.example.org/", ""));
+        assert!(!endpoint_allowed("https://169.254.169.254/latest/meta-data", ""));
+        assert!(!endpoint_allowed("https://fcm.googleapis.com@10.0.0.1/", ""));
+        assert!(!endpoint_allowed("http://localhost:8088/push/1", ""));
+        assert!(endpoint_allowed("http://localhost:8088/push/1", "http://localhost:8088"));
+        assert!(!endpoint_allowed("http://localhost:8088.evil/push/1", "http://localhost:8088"));
+    }
+    
+    #[tokio::test]
+    async fn pushes_reach_the_push_service() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for status in ["201 Created", "410 Gone"] {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0u8; 4096];
+                loop {
+                    let n = stream.read(&mut buf).await.unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                    let text = String::from_utf8_lossy(&request).to_lowercase();
+                    let length = text.split("content-length: ").nth(1)
+                        .and_then(|rest| rest.split("\r\n").next())
+                        .and_then(|n| n.parse::<usize>().ok());
+                    match (text.find("\r\n\r\n"), length) {
+                        (Some(end), Some(length)) if request.len() >= end + 4 + length => break,
+                        _ if n == 0 => break,
+                        _ => {}
+                    }
+                }
+                stream.write_all(format!("HTTP/1.1 {}\r\nContent-Length: 0\r\n\r\n", status).as_bytes()).await.unwrap();
+                requests.push(request);
+            }
+            requests
+        });
+        
+        let key = SigningKey::random(&mut OsRng);
+        let (secret, auth, mut subscription) = browser();
+        subscription.endpoint = format!("http://{}/push/device", address);
+        let payload = push_payload("Season 1", "game-1", &notification("Under attack"));
+        assert!(matches!(deliver(&key, &subscription, &payload).await, Ok(Delivery::Sent)));
+        assert!(matches!(deliver(&key, &subscription, &payload).await, Ok(Delivery::Gone)));
+        
+        let requests = server.await.unwrap();
+        let request = &requests[0];
+        let split = request.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+        let head = String::from_utf8_lossy(&request[..split]).to_lowercase();
+        assert!(head.starts_with("post /push/device http/1.1"));
+        assert!(head.contains("content-encoding: aes128gcm"));
+        assert!(head.contains("ttl: 86400"));
+        assert!(head.contains("authorization: vapid t="));
+        let json: serde_json::Value = serde_json::from_slice(&decrypt(&secret, &auth, &request[split + 4..])).unwrap();
+        assert_eq!(json["body"], "Under attack");
+        assert_eq!(json["game_id"], "game-1");
+    }
+}
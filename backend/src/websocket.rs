@@ -1,23 +1,45 @@
 use axum::extract::ws::{Message, WebSocket};
-use tokio::sync::{mpsc, RwLock, broadcast};
-use std::sync::Arc;
+use tokio::sync::{RwLock, broadcast};
+use std::sync::{Arc, Mutex};
+use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
-use uuid::Uuid;
+use futures_util::{SinkExt, StreamExt};
+use chrono::Utc;
 
-use crate::game::{GameState, Command, CommandResult};
+use crate::game::{GameState, Command, CommandResult, GameEvent, ShipType, Resource};
+use crate::engagement::{RoeRule, Stance};
+use crate::notifications::{NotificationKind, NotifyChange};
+use crate::terrain::Terrain;
+use crate::transit::TRANSIT_TICKS_PER_CYCLE;
+use crate::diplomacy::TreatyKind;
+use crate::trade::TradeBundle;
+use crate::chat::{ChatState, ChatChannel, ChatMessage, MAX_MESSAGE_LENGTH};
+use crate::spectator::SPECTATORS;
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct GameServer {
+    pub game_id: String,
     pub game_state: Arc<RwLock<GameState>>,
-    pub broadcast_tx: broadcast::Sender<String>,
+    pub broadcast_tx: broadcast::Sender<Outbound>,
+    pub chat: Arc<RwLock<ChatState>>,
+    pub online: Arc<Mutex<HashMap<String, usize>>>, // Player ID -> open sockets
+}
+
+// A serialized server message and who should receive it
+#[derive(Debug, Clone)]
+pub struct Outbound {
+    pub recipient: Option<String>, // None = every connected player
+    pub payload: String,
+    pub disconnect: bool, // Close the recipient's socket after delivering
 }
 
 #[derive(Deserialize)]
 struct ClientMessage {
     #[serde(rename = "type")]
     msg_type: String,
-    player_id: Option<String>,
     content: Option<String>,
+    channel: Option<String>, // Chat: "global", "alliance" or "direct"
+    to: Option<String>, // Chat: recipient name for direct messages
 }
 
 #[derive(Serialize)]
@@ -29,6 +51,30 @@ struct ServerMessage {
     message: Option<String>,
 }
 
+#[derive(Serialize)]
+struct ChatUpdate<'a> {
+    #[serde(rename = "type")]
+    msg_type: String,
+    chat: &'a ChatMessage,
+}
+
+#[derive(Serialize)]
+struct GameOverUpdate<'a> {
+    #[serde(rename = "type")]
+    msg_type: String,
+    season: u32,
+    winners: Vec<String>, // Names
+    condition: String,
+    standings: Vec<FinalStanding<'a>>,
+}
+
+// A standing as players see it: by name, never by player ID
+#[derive(Serialize)]
+struct FinalStanding<'a> {
+    name: &'a str,
+    score: i64,
+}
+
 #[derive(Serialize)]
 struct PlayerUpdate {
     ap: i32,
@@ -43,18 +89,25 @@ struct SectorUpdate {
     id: String,
     name: String,
     position: (i32, i32),
-    controlled_by: Option<String>,
+    links: Vec<String>,
+    traits: Vec<String>,
+    terrain: Vec<Terrain>,
+    controlled_by: Option<String>, // Controller's name
+    controlled_by_you: bool,
     ship_count: usize,
     has_garrison: bool,
 }
 
 impl GameServer {
-    pub fn new(game_state: GameState) -> Self {
+    pub fn new(game_id: &str, game_state: GameState) -> Self {
         let (broadcast_tx, _) = broadcast::channel(100);
         
         Self {
+            game_id: game_id.to_string(),
             game_state: Arc::new(RwLock::new(game_state)),
             broadcast_tx,
+            chat: Arc::new(RwLock::new(ChatState::new())),
+            online: Arc::new(Mutex::new(HashMap::new())),
         }
     }
     
@@ -64,20 +117,35 @@ impl GameServer {
         // Subscribe to broadcasts
         let mut broadcast_rx = self.broadcast_tx.subscribe();
         
-        // Send initial state
+        // Send initial state and whatever chat and notifications were missed
+        self.set_online(&player_id, true);
         self.send_game_update(&mut sender, &player_id).await;
+        self.send_chat_history(&mut sender, &player_id).await;
+        self.send_inbox(&mut sender, &player_id).await;
         
-        // Spawn task to forward broadcasts
+        // Spawn task to forward broadcasts meant for this player
+        let recipient_id = player_id.clone();
         let broadcast_task = tokio::spawn(async move {
             while let Ok(msg) = broadcast_rx.recv().await {
-                if sender.send(Message::Text(msg)).await.is_err() {
+                if msg.recipient.as_deref().is_some_and(|r| r != recipient_id) {
+                    continue;
+                }
+                if sender.send(Message::Text(msg.payload)).await.is_err() {
+                    break;
+                }
+                if msg.disconnect {
+                    let _ = sender.send(Message::Close(None)).await;
                     break;
                 }
             }
         });
         
         // Handle incoming messages
-        while let Some(msg) = receiver.recv().await {
+        while let Some(msg) = receiver.next().await {
+            // Forwarding stops when the player is kicked or the socket died
+            if broadcast_task.is_finished() {
+                break;
+            }
             if let Ok(msg) = msg {
                 match msg {
                     Message::Text(text) => {
@@ -93,6 +161,7 @@ impl GameServer {
         
         // Clean up
         broadcast_task.abort();
+        self.set_online(&player_id, false);
     }
     
     async fn handle_client_message(&self, msg: ClientMessage, player_id: &str) {
@@ -106,10 +175,130 @@ impl GameServer {
                 // Heartbeat - send current state
                 self.broadcast_player_update(player_id).await;
             }
+            "chat" => {
+                self.process_chat(player_id, msg.channel.as_deref(), msg.to.as_deref(), msg.content.as_deref()).await;
+            }
+            "mute" | "unmute" => {
+                if let Some(target) = msg.content {
+                    self.process_mute(player_id, &target, msg.msg_type == "mute").await;
+                }
+            }
             _ => {}
         }
     }
     
+    async fn process_chat(&self, player_id: &str, channel: Option<&str>, to: Option<&str>, content: Option<&str>) {
+        let text = content.unwrap_or("").trim();
+        if text.is_empty() {
+            return;
+        }
+        if text.chars().count() > MAX_MESSAGE_LENGTH {
+            self.send_error(player_id, &format!("Message too long (max {} characters)", MAX_MESSAGE_LENGTH)).await;
+            return;
+        }
+        
+        let channel = match channel.unwrap_or("global") {
+            "global" => ChatChannel::Global,
+            "alliance" => ChatChannel::Alliance,
+            "direct" => ChatChannel::Direct,
+            other => {
+                self.send_error(player_id, &format!("Unknown chat channel: {}", other)).await;
+                return;
+            }
+        };
+        
+        let game_state = self.game_state.read().await;
+        let sender_name = match game_state.players.get(player_id) {
+            Some(p) => p.name.clone(),
+            None => {
+                self.send_error(player_id, "Player not found").await;
+                return;
+            }
+        };
+        
+        // Resolve who should get it
+        let recipients = match channel {
+            ChatChannel::Global => Vec::new(),
+            ChatChannel::Alliance => {
+                let allies = game_state.allies_of(player_id);
+                if allies.is_empty() {
+                    self.send_error(player_id, "You have no allies to talk to").await;
+                    return;
+                }
+                allies
+            }
+            ChatChannel::Direct => match to.and_then(|name| game_state.find_player_id(name)) {
+                Some(id) if id != player_id => vec![id],
+                Some(_) => {
+                    self.send_error(player_id, "You can't message yourself").await;
+                    return;
+                }
+                None => {
+                    self.send_error(player_id, &format!("No commander named {}", to.unwrap_or(""))).await;
+                    return;
+                }
+            },
+        };
+        
+        let mut chat = self.chat.write().await;
+        if let Err(e) = chat.check_rate_limit(player_id, Utc::now()) {
+            self.send_error(player_id, &e).await;
+            return;
+        }
+        
+        let message = ChatMessage {
+            channel,
+            from: player_id.to_string(),
+            from_name: sender_name,
+            recipient_names: recipients.iter().map(|r| game_state.owner_name(r).to_string()).collect(),
+            recipients: recipients.clone(),
+            text: text.to_string(),
+            sent_at: Utc::now(),
+        };
+        
+        // Sender gets an echo; anyone who muted them gets nothing
+        let audience: Vec<String> = match channel {
+            ChatChannel::Global => game_state.players.keys().cloned().collect(),
+            _ => std::iter::once(player_id.to_string()).chain(recipients).collect(),
+        };
+        let update = ChatUpdate {
+            msg_type: "chat".to_string(),
+            chat: &message,
+        };
+        for recipient in audience.iter().filter(|id| !chat.is_muted(id, player_id)) {
+            self.send_to(Some(recipient), &update);
+        }
+        
+        chat.record(message);
+    }
+    
+    async fn process_mute(&self, player_id: &str, target: &str, mute: bool) {
+        let target_id = match self.game_state.read().await.find_player_id(target.trim()) {
+            Some(id) => id,
+            None => {
+                self.send_error(player_id, &format!("No commander named {}", target.trim())).await;
+                return;
+            }
+        };
+        
+        let mut chat = self.chat.write().await;
+        let changed = if mute { chat.mute(player_id, &target_id) } else { chat.unmute(player_id, &target_id) };
+        let message = match (mute, changed) {
+            (true, true) => format!("Muted {}", target.trim()),
+            (true, false) => format!("{} is already muted", target.trim()),
+            (false, true) => format!("Unmuted {}", target.trim()),
+            (false, false) => format!("{} wasn't muted", target.trim()),
+        };
+        
+        let update = ServerMessage {
+            msg_type: "event".to_string(),
+            player: None,
+            sectors: None,
+            message: Some(message),
+        };
+        self.send_to(Some(player_id), &update);
+    }
+    
     async fn process_command(&self, player_id: &str, command_str: &str) {
         // Parse command string into Command enum
         let command = match self.parse_command(command_str) {
@@ -120,23 +309,129 @@ impl GameServer {
             }
         };
         
-        // Execute command
         let mut game_state = self.game_state.write().await;
+        self.run_command(&mut game_state, player_id, command);
+    }
+    
+    // Executes a command and pushes the outcome to every client; shared by the socket
+    // handler and server-side bots so both go through the same rules
+    pub fn run_command(&self, game_state: &mut GameState, player_id: &str, command: Command) -> CommandResult {
+        let finished = game_state.outcome.is_some();
         let result = game_state.execute_command(player_id, command);
         
-        // Broadcast result
+        // Result goes to the issuing player only, scan reports are private intel
         let update = ServerMessage {
             msg_type: "command_result".to_string(),
             player: Some(self.get_player_update(&result.game_state, player_id)),
-            sectors: Some(self.get_sector_updates(&result.game_state)),
-            message: Some(result.message),
+            sectors: Some(self.get_sector_updates(&result.game_state, player_id)),
+            message: Some(result.message.clone()),
+        };
+        self.send_to(Some(player_id), &update);
+        
+        // Everyone else just gets their refreshed view of the map
+        for other_id in game_state.players.keys().filter(|id| id.as_str() != player_id) {
+            let update = ServerMessage {
+                msg_type: "sector_update".to_string(),
+                player: None,
+                sectors: Some(self.get_sector_updates(game_state, other_id)),
+                message: None,
+            };
+            self.send_to(Some(other_id), &update);
+        }
+        
+        let events = game_state.take_events();
+        self.dispatch_events(game_state, events);
+        self.deliver_alerts(game_state);
+        self.spectate_world(game_state);
+        if !finished && game_state.outcome.is_some() {
+            self.announce_outcome(game_state);
+        }
+        result
+    }
+    
+    // Final standings go to everyone, and the finished season is archived before the
+    // next cycle replaces it
+    fn announce_outcome(&self, game_state: &GameState) {
+        let outcome = match &game_state.outcome {
+            Some(o) => o,
+            None => return,
+        };
+        
+        let update = GameOverUpdate {
+            msg_type: "game_over".to_string(),
+            season: game_state.season,
+            winners: outcome.winners.iter()
+                .map(|id| game_state.owner_name(id).to_string())
+                .collect(),
+            condition: outcome.condition.describe(),
+            standings: outcome.standings.iter()
+                .map(|s| FinalStanding { name: &s.name, score: s.score })
+                .collect(),
         };
+        self.send_to(None, &update);
         
-        let _ = self.broadcast_tx.send(serde_json::to_string(&update).unwrap());
+        let path = format!("../data/archive/{}-season-{}.json", self.game_id, game_state.season);
+        let json = serde_json::to_string_pretty(game_state);
+        tokio::spawn(async move {
+            let result = match json {
+                Ok(json) => match tokio::fs::create_dir_all("../data/archive").await {
+                    Ok(()) => tokio::fs::write(&path, json).await,
+                    Err(e) => Err(e),
+                },
+                Err(e) => Err(e.into()),
+            };
+            if let Err(e) = result {
+                eprintln!("Failed to archive {}: {}", path, e);
+            }
+        });
+    }
+    
+    pub fn send_to<T: Serialize>(&self, recipient: Option<&str>, message: &T) {
+        let _ = self.broadcast_tx.send(Outbound {
+            recipient: recipient.map(|r| r.to_string()),
+            payload: serde_json::to_string(message).unwrap(),
+            disconnect: false,
+        });
+    }
+    
+    // Tells the player why, then closes every socket they have open
+    pub fn kick(&self, player_id: &str, reason: &str) {
+        let update = ServerMessage {
+            msg_type: "error".to_string(),
+            player: None,
+            sectors: None,
+            message: Some(reason.to_string()),
+        };
+        let _ = self.broadcast_tx.send(Outbound {
+            recipient: Some(player_id.to_string()),
+            payload: serde_json::to_string(&update).unwrap(),
+            disconnect: true,
+        });
+    }
+    
+    // Spectators get a copy of every event, and a few events are for them alone
+    fn dispatch_events(&self, game_state: &GameState, events: Vec<GameEvent>) {
+        self.spectate_events(game_state, &events);
+        for event in events.into_iter().filter(|e| e.player_id != SPECTATORS) {
+            let update = ServerMessage {
+                msg_type: "event".to_string(),
+                player: None,
+                sectors: None,
+                message: Some(event.message),
+            };
+            self.send_to(Some(&event.player_id), &update);
+        }
+    }
+    
+    // `<resource> <amount>` after buy or sell
+    fn parse_market_order(&self, parts: &[&str]) -> Option<(Resource, i32)> {
+        let resource = Resource::parse(parts.get(1)?)?;
+        let amount = parts.get(2)?.parse::<i32>().ok().filter(|a| *a > 0)?;
+        Some((resource, amount))
     }
     
     fn parse_command(&self, input: &str) -> Result<Command, String> {
-        let parts: Vec<&str> = input.trim().split_whitespace().collect();
+        let parts: Vec<&str> = input.split_whitespace().collect();
         
         if parts.is_empty() {
             return Err("Empty command".to_string());
@@ -154,12 +449,176 @@ impl GameServer {
             }
             "scan" => {
                 if parts.len() < 2 {
-                    return Err("Usage: scan <sector-id>".to_string());
+                    return Err("Usage: scan <sector-id> [ship-id]".to_string());
                 }
                 Ok(Command::Scan {
                     sector_id: parts[1].to_string(),
+                    scanner_id: parts.get(2).map(|s| s.to_string()),
+                })
+            }
+            "attack" => {
+                if parts.len() < 2 {
+                    return Err("Usage: attack <target-ship-id>".to_string());
+                }
+                Ok(Command::Attack {
+                    target_ship_id: parts[1].to_string(),
                 })
             }
+            "build" => {
+                if parts.len() < 3 {
+                    return Err("Usage: build <ship-class> <sector-id> [name]".to_string());
+                }
+                let ship_type = ShipType::parse(parts[1])
+                    .ok_or_else(|| format!("Unknown ship class {}", parts[1]))?;
+                let name = parts[3..].join(" ");
+                Ok(Command::Build {
+                    ship_type,
+                    sector_id: parts[2].to_string(),
+                    name: if name.is_empty() { None } else { Some(name) },
+                })
+            }
+            "cloak" => {
+                if parts.len() < 2 {
+                    return Err("Usage: cloak <ship-id>".to_string());
+                }
+                Ok(Command::Cloak {
+                    ship_id: parts[1].to_string(),
+                })
+            }
+            "decloak" => {
+                if parts.len() < 2 {
+                    return Err("Usage: decloak <ship-id>".to_string());
+                }
+                Ok(Command::Decloak {
+                    ship_id: parts[1].to_string(),
+                })
+            }
+            "propose" => {
+                // Player names may contain spaces, so the treaty terms are read from the end
+                let mut args = parts[1..].to_vec();
+                let shared_victory = args.last().is_some_and(|a| a.eq_ignore_ascii_case("shared"));
+                if shared_victory {
+                    args.pop();
+                }
+                let kind = match args.pop().map(|a| a.to_lowercase()) {
+                    Some(k) if k == "truce" => TreatyKind::Truce,
+                    Some(k) if k == "alliance" => TreatyKind::Alliance,
+                    _ => return Err("Usage: propose <player> <truce|alliance> [shared]".to_string()),
+                };
+                if args.is_empty() {
+                    return Err("Usage: propose <player> <truce|alliance> [shared]".to_string());
+                }
+                Ok(Command::Propose {
+                    player: args.join(" "),
+                    kind,
+                    shared_victory,
+                })
+            }
+            "accept" => {
+                if parts.len() < 2 {
+                    return Err("Usage: accept <player>".to_string());
+                }
+                Ok(Command::Accept {
+                    player: parts[1..].join(" "),
+                })
+            }
+            "break" => {
+                if parts.len() < 2 {
+                    return Err("Usage: break <player>".to_string());
+                }
+                Ok(Command::Break {
+                    player: parts[1..].join(" "),
+                })
+            }
+            "relations" => Ok(Command::Relations),
+            "leaderboard" | "scores" => Ok(Command::Leaderboard),
+            "trade" => self.parse_trade(&parts[1..]),
+            "queue" => self.parse_queue(&parts[1..]),
+            "orders" => Ok(Command::Orders),
+            "cancel" => {
+                let order_id = parts.get(1)
+                    .and_then(|id| id.trim_start_matches('#').parse().ok())
+                    .ok_or_else(|| "Usage: cancel <order-number>".to_string())?;
+                Ok(Command::CancelOrder { order_id })
+            }
+            "reorder" => {
+                let order_id = parts.get(1).and_then(|id| id.trim_start_matches('#').parse().ok());
+                let position = parts.get(2).and_then(|p| p.parse().ok());
+                match (order_id, position) {
+                    (Some(order_id), Some(position)) => Ok(Command::ReorderOrder { order_id, position }),
+                    _ => Err("Usage: reorder <order-number> <position>".to_string()),
+                }
+            }
+            "rename" => {
+                if parts.len() < 3 {
+                    return Err("Usage: rename <ship-id> <name>".to_string());
+                }
+                Ok(Command::Rename {
+                    ship_id: parts[1].to_string(),
+                    name: parts[2..].join(" "),
+                })
+            }
+            "stance" => {
+                const USAGE: &str = "Usage: stance <ship-id|sector-id|all> <aggressive|defensive|evasive|passive>";
+                if parts.len() < 3 {
+                    return Err(USAGE.to_string());
+                }
+                let stance = Stance::parse(parts[2]).ok_or_else(|| USAGE.to_string())?;
+                Ok(Command::SetStance {
+                    target: parts[1].to_string(),
+                    stance,
+                })
+            }
+            "roe" => {
+                const USAGE: &str = "Usage: roe <ship-id|sector-id|all> retreat <percent|off> | roe <ship-id|sector-id|all> superior <avoid|engage>";
+                if parts.len() < 4 {
+                    return Err(USAGE.to_string());
+                }
+                let rule = match (parts[2].to_lowercase().as_str(), parts[3].to_lowercase().as_str()) {
+                    ("retreat", "off") => RoeRule::RetreatBelow(None),
+                    ("retreat", percent) => RoeRule::RetreatBelow(Some(
+                        percent.trim_end_matches('%').parse().map_err(|_| USAGE.to_string())?
+                    )),
+                    ("superior", "avoid") => RoeRule::AvoidSuperior(true),
+                    ("superior", "engage") => RoeRule::AvoidSuperior(false),
+                    _ => return Err(USAGE.to_string()),
+                };
+                Ok(Command::SetRoe {
+                    target: parts[1].to_string(),
+                    rule,
+                })
+            }
+            "notify" => {
+                const USAGE: &str = "Usage: notify [on|off <attack|sector|cycle|trade> | webhook <url|off> | email <address|off|confirm <code>>]";
+                if parts.len() == 1 {
+                    return Ok(Command::Notify { change: None });
+                }
+                if parts.len() < 3 {
+                    return Err(USAGE.to_string());
+                }
+                let setting = |value: &str| if value.eq_ignore_ascii_case("off") { None } else { Some(value.to_string()) };
+                let change = match parts[1].to_lowercase().as_str() {
+                    "on" => NotifyChange::Enable(NotificationKind::parse(parts[2]).ok_or_else(|| USAGE.to_string())?),
+                    "off" => NotifyChange::Disable(NotificationKind::parse(parts[2]).ok_or_else(|| USAGE.to_string())?),
+                    "webhook" => NotifyChange::Webhook(setting(parts[2])),
+                    "email" if parts[2].eq_ignore_ascii_case("confirm") =>
+                        NotifyChange::ConfirmEmail(parts.get(3).ok_or_else(|| USAGE.to_string())?.to_string()),
+                    "email" => NotifyChange::Email(setting(parts[2])),
+                    _ => return Err(USAGE.to_string()),
+                };
+                Ok(Command::Notify { change: Some(change) })
+            }
+            "market" => Ok(Command::Market),
+            "buy" => {
+                let (resource, amount) = self.parse_market_order(&parts)
+                    .ok_or_else(|| "Usage: buy <ore|fuel|components> <amount>".to_string())?;
+                Ok(Command::Buy { resource, amount })
+            }
+            "sell" => {
+                let (resource, amount) = self.parse_market_order(&parts)
+                    .ok_or_else(|| "Usage: sell <ore|fuel|components> <amount>".to_string())?;
+                Ok(Command::Sell { resource, amount })
+            }
             "status" => Ok(Command::Status),
             "fleet" => Ok(Command::Fleet),
             "declare" => {
@@ -184,19 +643,112 @@ impl GameServer {
         }
     }
     
+    // queue <command> [then <command>...] [@next-cycle | @cycle-<n>]
+    fn parse_queue(&self, args: &[&str]) -> Result<Command, String> {
+        const USAGE: &str = "Usage: queue <command> [then <command>...] [@next-cycle | @cycle-<n>]";
+        
+        let mut args = args.to_vec();
+        let at_cycle = match args.last() {
+            Some(timing) if timing.starts_with('@') => {
+                let timing = timing.to_lowercase();
+                args.pop();
+                match timing.as_str() {
+                    "@next-cycle" => None,
+                    _ => match timing.strip_prefix("@cycle-").and_then(|n| n.parse().ok()) {
+                        Some(cycle) => Some(cycle),
+                        None => return Err(USAGE.to_string()),
+                    },
+                }
+            }
+            _ => None,
+        };
+        
+        let steps: Vec<String> = args.split(|a| a.eq_ignore_ascii_case("then"))
+            .map(|step| step.join(" "))
+            .collect();
+        if steps.iter().any(|s| s.is_empty()) {
+            return Err(USAGE.to_string());
+        }
+        
+        let mut parsed = Vec::new();
+        for step in steps {
+            let command = self.parse_command(&step)?;
+            parsed.push((step, command));
+        }
+        Ok(Command::Queue { steps: parsed, at_cycle })
+    }
+    
+    // trade offer <player> give <items> want <items> | trade accept|decline <id> | trade list
+    fn parse_trade(&self, args: &[&str]) -> Result<Command, String> {
+        const USAGE: &str = "Usage: trade offer <player> give <items> want <items> | trade accept <id> | trade decline <id> | trade list\n\
+            Items: credits:<n>, ore:<n>, fuel:<n>, components:<n> or a ship ID";
+        
+        let offer_id = |arg: Option<&&str>| -> Result<u32, String> {
+            arg.and_then(|a| a.trim_start_matches('#').parse().ok()).ok_or_else(|| USAGE.to_string())
+        };
+        
+        match args.first().map(|a| a.to_lowercase()).as_deref() {
+            Some("offer") => {
+                // Player names may contain spaces, so the name runs up to the first give/want
+                let rest = &args[1..];
+                let give_at = rest.iter().position(|a| a.eq_ignore_ascii_case("give"));
+                let want_at = rest.iter().position(|a| a.eq_ignore_ascii_case("want"));
+                let name_end = match (give_at, want_at) {
+                    (Some(g), Some(w)) => g.min(w),
+                    (Some(i), None) | (None, Some(i)) => i,
+                    (None, None) => return Err(USAGE.to_string()),
+                };
+                if name_end == 0 || give_at.zip(want_at).is_some_and(|(g, w)| g > w) {
+                    return Err(USAGE.to_string());
+                }
+                
+                let give = match give_at {
+                    Some(g) => TradeBundle::parse(&rest[g + 1..want_at.unwrap_or(rest.len())])?,
+                    None => TradeBundle::default(),
+                };
+                let want = match want_at {
+                    Some(w) => TradeBundle::parse(&rest[w + 1..])?,
+                    None => TradeBundle::default(),
+                };
+                
+                Ok(Command::TradeOffer {
+                    player: rest[..name_end].join(" "),
+                    give,
+                    want,
+                })
+            }
+            Some("accept") => Ok(Command::TradeAccept { offer_id: offer_id(args.get(1))? }),
+            Some("decline") => Ok(Command::TradeDecline { offer_id: offer_id(args.get(1))? }),
+            Some("list") | None => Ok(Command::TradeList),
+            Some(_) => Err(USAGE.to_string()),
+        }
+    }
+    
     async fn send_game_update(&self, sender: &mut futures_util::stream::SplitSink<WebSocket, Message>, player_id: &str) {
         let game_state = self.game_state.read().await;
         
         let update = ServerMessage {
             msg_type: "game_update".to_string(),
             player: Some(self.get_player_update(&game_state, player_id)),
-            sectors: Some(self.get_sector_updates(&game_state)),
+            sectors: Some(self.get_sector_updates(&game_state, player_id)),
             message: Some("Connected to game server".to_string()),
         };
         
         let _ = sender.send(Message::Text(serde_json::to_string(&update).unwrap())).await;
     }
     
+    async fn send_chat_history(&self, sender: &mut futures_util::stream::SplitSink<WebSocket, Message>, player_id: &str) {
+        let chat = self.chat.read().await;
+        
+        for message in chat.history_for(player_id) {
+            let update = ChatUpdate {
+                msg_type: "chat".to_string(),
+                chat: message,
+            };
+            let _ = sender.send(Message::Text(serde_json::to_string(&update).unwrap())).await;
+        }
+    }
+    
     async fn broadcast_player_update(&self, player_id: &str) {
         let game_state = self.game_state.read().await;
         
@@ -207,7 +759,7 @@ impl GameServer {
             message: None,
         };
         
-        let _ = self.broadcast_tx.send(serde_json::to_string(&update).unwrap());
+        self.send_to(Some(player_id), &update);
     }
     
     async fn send_error(&self, player_id: &str, error: &str) {
@@ -218,7 +770,7 @@ impl GameServer {
             message: Some(error.to_string()),
         };
         
-        let _ = self.broadcast_tx.send(serde_json::to_string(&update).unwrap());
+        self.send_to(Some(player_id), &update);
     }
     
     fn get_player_update(&self, game_state: &GameState, player_id: &str) -> PlayerUpdate {
@@ -241,38 +793,135 @@ impl GameServer {
         }
     }
     
-    fn get_sector_updates(&self, game_state: &GameState) -> Vec<SectorUpdate> {
+    // Sector view for one player: cloaked ships they can't detect are left out of the counts
+    fn get_sector_updates(&self, game_state: &GameState, player_id: &str) -> Vec<SectorUpdate> {
         game_state.sectors.iter().map(|sector| {
             SectorUpdate {
                 id: sector.id.clone(),
                 name: sector.name.clone(),
                 position: sector.position,
-                controlled_by: sector.controlled_by.clone(),
-                ship_count: sector.ships_present.len(),
+                links: sector.links.clone(),
+                traits: sector.traits.clone(),
+                terrain: sector.terrain.clone(),
+                controlled_by: sector.controlled_by.as_deref().map(|id| game_state.owner_name(id).to_string()),
+                controlled_by_you: sector.controlled_by.as_deref() == Some(player_id),
+                ship_count: game_state.shown_ship_count(sector, player_id),
                 has_garrison: sector.garrison_ship.is_some(),
             }
         }).collect()
     }
     
-    // Cycle processing task
+    // Cycle processing task, one per game. Ships in transit move on several times a cycle
     pub async fn run_cycle_task(game_server: Arc<GameServer>) {
-        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(8 * 60 * 60)); // 8 hours
+        let cycle_secs = game_server.game_state.read().await.settings.cycle_secs;
+        let tick = tokio::time::Duration::from_secs(cycle_secs) / TRANSIT_TICKS_PER_CYCLE;
+        let mut interval = tokio::time::interval(tick);
         
+        let mut ticks: u32 = 0;
         loop {
             interval.tick().await;
-            
-            let mut game_state = game_server.game_state.write().await;
-            game_state.process_cycle();
-            
-            // Broadcast cycle update
+            if ticks.is_multiple_of(TRANSIT_TICKS_PER_CYCLE) {
+                game_server.run_cycle().await;
+            } else {
+                game_server.run_transit_tick().await;
+            }
+            ticks = ticks.wrapping_add(1);
+        }
+    }
+    
+    // A tick between cycles; only ships in transit move
+    pub async fn run_transit_tick(&self) {
+        let mut game_state = self.game_state.write().await;
+        if game_state.outcome.is_some() || !game_state.process_transit() {
+            return;
+        }
+        
+        for player_id in game_state.players.keys() {
+            let update = ServerMessage {
+                msg_type: "sector_update".to_string(),
+                player: None,
+                sectors: Some(self.get_sector_updates(&game_state, player_id)),
+                message: None,
+            };
+            self.send_to(Some(player_id), &update);
+        }
+        let events = game_state.take_events();
+        self.dispatch_events(&game_state, events);
+        self.deliver_alerts(&mut game_state);
+        self.spectate_world(&game_state);
+        self.save(&game_state).await;
+    }
+    
+    // One cycle tick; also used by admins to force a cycle
+    pub async fn run_cycle(&self) {
+        let mut game_state = self.game_state.write().await;
+        
+        // A finished season is replaced by a fresh one instead of running a cycle
+        if game_state.outcome.is_some() {
+            *game_state = game_state.next_season();
+            let message = format!("Season {} begins! Your fleet has been reset", game_state.season);
+            self.send_state_to_all(&game_state, "season_start", &message);
+            self.save(&game_state).await;
+            return;
+        }
+        
+        game_state.process_cycle();
+        
+        // Broadcast cycle update, one filtered map per player
+        for (player_id, player) in game_state.players.iter() {
             let update = ServerMessage {
                 msg_type: "cycle_update".to_string(),
                 player: None,
-                sectors: Some(game_server.get_sector_updates(&game_state)),
-                message: Some(format!("Cycle {} complete! +50 AP added", game_state.cycle_number)),
+                sectors: Some(self.get_sector_updates(&game_state, player_id)),
+                message: Some(format!("Cycle {} complete! +{} AP added", game_state.cycle_number, player.ap_refill())),
             };
-            
-            let _ = game_server.broadcast_tx.send(serde_json::to_string(&update).unwrap());
+            self.send_to(Some(player_id), &update);
+        }
+        self.spectate_event(&game_state, None, &format!("Cycle {} complete", game_state.cycle_number));
+        let events = game_state.take_events();
+        self.dispatch_events(&game_state, events);
+        self.deliver_alerts(&mut game_state);
+        self.spectate_world(&game_state);
+        if game_state.outcome.is_some() {
+            self.announce_outcome(&game_state);
         }
+        self.save(&game_state).await;
     }
-} 
\ No newline at end of file
+    
+    // Full refresh of every player's stats and map, after the state was replaced wholesale
+    pub fn send_state_to_all(&self, game_state: &GameState, msg_type: &str, message: &str) {
+        for player_id in game_state.players.keys() {
+            let update = ServerMessage {
+                msg_type: msg_type.to_string(),
+                player: Some(self.get_player_update(game_state, player_id)),
+                sectors: Some(self.get_sector_updates(game_state, player_id)),
+                message: Some(message.to_string()),
+            };
+            self.send_to(Some(player_id), &update);
+        }
+        self.spectate_event(game_state, None, message);
+        self.spectate_world(game_state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Player;
+    
+    #[test]
+    fn sector_updates_name_controllers() {
+        let mut state = GameState::new();
+        state.players.insert("secret-id-1".to_string(), Player::new("secret-id-1", "Alpha"));
+        state.players.insert("secret-id-2".to_string(), Player::new("secret-id-2", "Beta"));
+        state.sectors[0].controlled_by = Some("secret-id-1".to_string());
+        let server = GameServer::new("game-1", state.clone());
+        
+        let json = serde_json::to_string(&server.get_sector_updates(&state, "secret-id-2")).unwrap();
+        assert!(!json.contains("secret-id"));
+        assert!(json.contains("\"controlled_by\":\"Alpha\",\"controlled_by_you\":false"));
+        
+        let own = server.get_sector_updates(&state, "secret-id-1");
+        assert!(own[0].controlled_by_you);
+    }
+}
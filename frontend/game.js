@@ -3,6 +3,7 @@ class GameClient {
     constructor() {
         this.ws = null;
         this.playerId = null;
+        this.gameId = localStorage.getItem('gameId') || 'main';
         this.connected = false;
         this.terminal = new Terminal();
         this.map = null; // Will be initialized when map.js loads
@@ -37,13 +38,16 @@ class GameClient {
             this.handleConnect();
         });
         
-        // Test player buttons
+        // Test player buttons (they only exist in the default game)
         document.querySelectorAll('.test-player-btn').forEach(btn => {
             btn.addEventListener('click', () => {
+                this.gameId = 'main';
                 this.connectAsPlayer(btn.dataset.id);
             });
         });
         
+        this.loadGames();
+        
         // Check for saved player ID
         const savedPlayerId = localStorage.getItem('playerId');
         if (savedPlayerId) {
@@ -51,7 +55,30 @@ class GameClient {
         }
     }
     
+    async loadGames() {
+        const select = document.getElementById('game-select');
+        try {
+            const response = await fetch('/api/games');
+            if (!response.ok) return;
+            const games = await response.json();
+            
+            select.innerHTML = '';
+            games.forEach(game => {
+                const option = document.createElement('option');
+                option.value = game.id;
+                option.textContent = `${game.name} (${game.id}) - ${game.players} players, season ${game.season}`;
+                select.appendChild(option);
+            });
+            select.value = this.gameId;
+        } catch (error) {
+            // Keep the default game
+        }
+    }
+    
     async handleConnect() {
+        this.gameId = document.getElementById('game-select').value || 'main';
+        localStorage.setItem('gameId', this.gameId);
+        
         const playerIdInput = document.getElementById('player-id-input').value.trim();
         const playerName = document.getElementById('player-name-input').value.trim();
         
@@ -61,7 +88,7 @@ class GameClient {
         } else if (playerName) {
             // Register new player
             try {
-                const response = await fetch('/api/register', {
+                const response = await fetch(`/api/games/${this.gameId}/join`, {
                     method: 'POST',
                     headers: { 'Content-Type': 'application/json' },
                     body: JSON.stringify({ name: playerName })
@@ -93,7 +120,7 @@ class GameClient {
     
     connectWebSocket() {
         const protocol = window.location.protocol === 'https:' ? 'wss:' : 'ws:';
-        const wsUrl = `${protocol}//${window.location.host}/ws/${this.playerId}`;
+        const wsUrl = `${protocol}//${window.location.host}/ws/${this.gameId}/${this.playerId}`;
         
         this.terminal.print('Connecting to command server...', 'system-message');
         
@@ -156,6 +183,52 @@ class GameClient {
                 }
                 break;
                 
+            case 'season_start':
+                if (data.player) {
+                    this.updatePlayerStats(data.player);
+                }
+                if (data.sectors && this.map) {
+                    this.map.updateSectors(data.sectors);
+                }
+                if (data.message) {
+                    this.terminal.print(data.message, 'success-message');
+                }
+                break;
+                
+            case 'game_over':
+                this.terminal.print(`=== Season ${data.season} Over ===`, 'success-message');
+                this.terminal.print(`Victory for ${data.winners.join(' & ')} (${data.condition})`, 'success-message');
+                data.standings.forEach((s, i) => {
+                    this.terminal.print(`${i + 1}. ${s.name} - ${s.score}`);
+                });
+                this.terminal.print('A new season begins next cycle.', 'system-message');
+                break;
+                
+            case 'sector_update':
+                if (data.sectors && this.map) {
+                    this.map.updateSectors(data.sectors);
+                }
+                break;
+                
+            case 'chat':
+                if (data.chat) {
+                    this.printChat(data.chat);
+                }
+                break;
+                
+            case 'event':
+                if (data.message) {
+                    this.terminal.print(data.message, 'system-message');
+                }
+                break;
+                
+            case 'inbox':
+                this.terminal.print(`=== While you were away (${data.notifications.length}) ===`, 'system-message');
+                data.notifications.forEach(n => {
+                    this.terminal.print(`[Cycle ${n.cycle}] ${n.message}`, 'system-message');
+                });
+                break;
+                
             case 'error':
                 this.terminal.print(data.message, 'error-message');
                 break;
@@ -176,6 +249,23 @@ class GameClient {
             return;
         }
         
+        // Push notifications are set up by the browser, not the game server
+        const words = command.toLowerCase().split(/\s+/);
+        if (words[0] === 'push') {
+            this.terminal.print(`$ ${command}`, 'command-echo');
+            this.setPush(words[1] !== 'off');
+            return;
+        }
+        
+        // Chat and mute commands go out as their own message types
+        const chat = this.parseChat(command);
+        if (chat !== null) {
+            if (chat) {
+                this.ws.send(JSON.stringify(chat));
+            }
+            return;
+        }
+        
         // Echo command
         this.terminal.print(`$ ${command}`, 'command-echo');
         
@@ -185,6 +275,103 @@ class GameClient {
             content: command
         }));
     }
+    
+    // say <text> | ally <text> | tell <name>: <text> | mute <name> | unmute <name>
+    // Returns null for game commands, false for handled input with nothing to send
+    parseChat(command) {
+        const space = command.indexOf(' ');
+        if (space === -1) return null;
+        const verb = command.slice(0, space).toLowerCase();
+        const rest = command.slice(space + 1).trim();
+        
+        switch (verb) {
+            case 'say':
+                return { type: 'chat', channel: 'global', content: rest };
+            case 'ally':
+                return { type: 'chat', channel: 'alliance', content: rest };
+            case 'tell': {
+                const colon = rest.indexOf(':');
+                if (colon === -1) {
+                    this.terminal.print('Usage: tell <name>: <message>', 'error-message');
+                    return false;
+                }
+                return {
+                    type: 'chat',
+                    channel: 'direct',
+                    to: rest.slice(0, colon).trim(),
+                    content: rest.slice(colon + 1).trim()
+                };
+            }
+            case 'mute':
+            case 'unmute':
+                return { type: verb, content: rest };
+        }
+        return null;
+    }
+    
+    // push on | push off: alerts on this device while the game is closed
+    async setPush(enable) {
+        if (!('serviceWorker' in navigator) || !('PushManager' in window)) {
+            this.terminal.print('This browser does not support push notifications', 'error-message');
+            return;
+        }
+        try {
+            const registration = await navigator.serviceWorker.register('sw.js');
+            const existing = await registration.pushManager.getSubscription();
+            
+            if (!enable) {
+                if (existing) {
+                    await fetch(`/api/games/${this.gameId}/push/unsubscribe`, {
+                        method: 'POST',
+                        headers: { 'Content-Type': 'application/json' },
+                        body: JSON.stringify({ player_id: this.playerId, endpoint: existing.endpoint })
+                    });
+                    await existing.unsubscribe();
+                }
+                this.terminal.print('Push notifications off for this device', 'system-message');
+                return;
+            }
+            
+            if (await Notification.requestPermission() !== 'granted') {
+                this.terminal.print('Notifications were not allowed', 'error-message');
+                return;
+            }
+            const keyResponse = await fetch('/api/push/key');
+            if (!keyResponse.ok) {
+                this.terminal.print('Push notifications are not available on this server', 'error-message');
+                return;
+            }
+            const { public_key } = await keyResponse.json();
+            const subscription = existing || await registration.pushManager.subscribe({
+                userVisibleOnly: true,
+                applicationServerKey: urlBase64ToUint8Array(public_key)
+            });
+            const response = await fetch(`/api/games/${this.gameId}/push/subscribe`, {
+                method: 'POST',
+                headers: { 'Content-Type': 'application/json' },
+                body: JSON.stringify({ player_id: this.playerId, subscription: subscription.toJSON() })
+            });
+            if (response.ok) {
+                this.terminal.print('Push notifications on: you will be alerted while the game is closed', 'success-message');
+            } else {
+                this.terminal.print('Failed to register for push: ' + await response.text(), 'error-message');
+            }
+        } catch (error) {
+            this.terminal.print('Failed to set up push: ' + error.message, 'error-message');
+        }
+    }
+    
+    printChat(chat) {
+        const time = new Date(chat.sent_at).toLocaleTimeString([], { hour: '2-digit', minute: '2-digit' });
+        const label = chat.channel === 'global' ? '' : `[${chat.channel}] `;
+        this.terminal.print(`${time} ${label}<${chat.from_name}> ${chat.text}`, `chat-message chat-${chat.channel}`);
+    }
+}
+
+// VAPID keys are base64url, the Push API wants raw bytes
+function urlBase64ToUint8Array(base64) {
+    const padded = (base64 + '='.repeat((4 - base64.length % 4) % 4)).replace(/-/g, '+').replace(/_/g, '/');
+    return Uint8Array.from(atob(padded), c => c.charCodeAt(0));
 }
 
 // Terminal handler
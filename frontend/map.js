@@ -7,7 +7,7 @@ class SectorMap {
         this.selectedSector = null;
         this.sectorSize = 90;
         this.padding = 10;
-        
+
         // Colors
         this.colors = {
             neutral: '#000000',
@@ -17,12 +17,23 @@ class SectorMap {
             enemyBorder: '#ff0000',
             text: '#00ff00',
             enemyText: '#ff0000',
-            selectedBorder: '#ffff00'
+            selectedBorder: '#ffff00',
+            terrain: '#66ccff'
+        };
+
+        // Map glyph and name for each terrain type
+        this.terrain = {
+            nebula: { glyph: '≈', name: 'Nebula' },
+            asteroid_field: { glyph: '∴', name: 'Asteroid field' },
+            ion_storm: { glyph: 'ϟ', name: 'Ion storm' },
+            shipyard: { glyph: '⚒', name: 'Shipyard' },
+            jump_gate: { glyph: '◎', name: 'Jump gate' },
+            trade_hub: { glyph: '⚖', name: 'Trade hub' }
         };
-        
+
         this.setupEventListeners();
     }
-    
+
     setupEventListeners() {
         this.canvas.addEventListener('click', (e) => {
             const rect = this.canvas.getBoundingClientRect();
@@ -30,7 +41,7 @@ class SectorMap {
             const y = e.clientY - rect.top;
             this.handleClick(x, y);
         });
-        
+
         this.canvas.addEventListener('mousemove', (e) => {
             const rect = this.canvas.getBoundingClientRect();
             const x = e.clientX - rect.left;
@@ -38,44 +49,56 @@ class SectorMap {
             this.handleHover(x, y);
         });
     }
-    
+
     updateSectors(sectorData) {
         this.sectors = sectorData;
+        this.layout();
         this.draw();
     }
-    
+
+    // Fit the map to the canvas, whatever its shape
+    layout() {
+        const columns = Math.max(1, ...this.sectors.map(s => s.position[0] + 1));
+        const rows = Math.max(1, ...this.sectors.map(s => s.position[1] + 1));
+        const step = Math.min(this.canvas.width / columns, this.canvas.height / rows);
+        this.padding = Math.max(4, Math.round(step / 10));
+        this.sectorSize = Math.floor(step - this.padding);
+    }
+
+    sectorOrigin(sector) {
+        return {
+            x: sector.position[0] * (this.sectorSize + this.padding) + this.padding / 2,
+            y: sector.position[1] * (this.sectorSize + this.padding) + this.padding / 2
+        };
+    }
+
     draw() {
         // Clear canvas
         this.ctx.fillStyle = '#000000';
         this.ctx.fillRect(0, 0, this.canvas.width, this.canvas.height);
-        
-        // Draw grid lines
+
+        // Draw links between sectors, each once
         this.ctx.strokeStyle = '#004400';
-        this.ctx.lineWidth = 1;
-        
-        // Vertical lines
-        for (let i = 0; i <= 4; i++) {
-            const x = i * (this.sectorSize + this.padding) + this.padding / 2;
-            this.ctx.beginPath();
-            this.ctx.moveTo(x, 0);
-            this.ctx.lineTo(x, this.canvas.height);
-            this.ctx.stroke();
-        }
-        
-        // Horizontal lines
-        for (let i = 0; i <= 4; i++) {
-            const y = i * (this.sectorSize + this.padding) + this.padding / 2;
-            this.ctx.beginPath();
-            this.ctx.moveTo(0, y);
-            this.ctx.lineTo(this.canvas.width, y);
-            this.ctx.stroke();
-        }
-        
+        this.ctx.lineWidth = 2;
+        const half = this.sectorSize / 2;
+        this.sectors.forEach(sector => {
+            const from = this.sectorOrigin(sector);
+            (sector.links || []).forEach(linkId => {
+                const other = this.sectors.find(s => s.id === linkId);
+                if (!other || other.id < sector.id) return;
+                const to = this.sectorOrigin(other);
+                this.ctx.beginPath();
+                this.ctx.moveTo(from.x + half, from.y + half);
+                this.ctx.lineTo(to.x + half, to.y + half);
+                this.ctx.stroke();
+            });
+        });
+
         // Draw sectors
         this.sectors.forEach(sector => {
             this.drawSector(sector);
         });
-        
+
         // Draw selected sector highlight
         if (this.selectedSector) {
             const sector = this.sectors.find(s => s.id === this.selectedSector);
@@ -84,15 +107,16 @@ class SectorMap {
             }
         }
     }
-    
+
     drawSector(sector) {
-        const x = sector.position[0] * (this.sectorSize + this.padding) + this.padding;
-        const y = sector.position[1] * (this.sectorSize + this.padding) + this.padding;
-        
+        const { x, y } = this.sectorOrigin(sector);
+        const k = this.sectorSize / 90; // Text was laid out for 90px sectors
+        const compact = this.sectorSize < 45; // Only room for the name and ship count
+
         // Determine ownership
         const isControlled = sector.controlled_by !== null;
-        const isOwnSector = sector.controlled_by === window.gameClient?.playerId;
-        
+        const isOwnSector = sector.controlled_by_you;
+
         // Background
         if (isControlled) {
             this.ctx.fillStyle = isOwnSector ? this.colors.controlled : this.colors.enemyControlled;
@@ -100,57 +124,69 @@ class SectorMap {
             this.ctx.fillStyle = this.colors.neutral;
         }
         this.ctx.fillRect(x, y, this.sectorSize, this.sectorSize);
-        
+
         // Border
         this.ctx.strokeStyle = isControlled && !isOwnSector ? this.colors.enemyBorder : this.colors.border;
         this.ctx.lineWidth = 2;
         this.ctx.strokeRect(x, y, this.sectorSize, this.sectorSize);
-        
+
         // Text color
         this.ctx.fillStyle = isControlled && !isOwnSector ? this.colors.enemyText : this.colors.text;
-        this.ctx.font = '14px monospace';
-        
+        const font = size => `${Math.max(8, Math.round(size * k))}px monospace`;
+        this.ctx.font = font(14);
+
         // Sector name
-        this.ctx.fillText(sector.name, x + 5, y + 20);
-        
+        this.ctx.fillText(sector.name, x + 5 * k, y + Math.max(20 * k, 10));
+
         // Ship count
         if (sector.ship_count > 0) {
-            this.ctx.font = '12px monospace';
-            this.ctx.fillText(`Ships: ${sector.ship_count}`, x + 5, y + 40);
+            this.ctx.font = font(12);
+            this.ctx.fillText(compact ? `S${sector.ship_count}` : `Ships: ${sector.ship_count}`, x + 5 * k, y + Math.max(40 * k, 20));
         }
-        
+
+        // Terrain glyphs along the bottom edge
+        const glyphs = (sector.terrain || []).map(t => this.terrain[t]?.glyph || '?').join(' ');
+        if (glyphs) {
+            this.ctx.fillStyle = this.colors.terrain;
+            this.ctx.font = font(14);
+            this.ctx.fillText(glyphs, x + this.sectorSize - this.ctx.measureText(glyphs).width - 5 * k, y + this.sectorSize - 5 * k);
+            this.ctx.fillStyle = isControlled && !isOwnSector ? this.colors.enemyText : this.colors.text;
+        }
+
+        if (compact) return;
+
         // Garrison indicator
         if (sector.has_garrison) {
-            this.ctx.fillText('[G]', x + 5, y + 55);
+            this.ctx.fillText('[G]', x + 5 * k, y + 55 * k);
         }
-        
+
         // Control indicator
         if (isControlled) {
-            this.ctx.font = '10px monospace';
-            this.ctx.fillText(isOwnSector ? '[ALLIED]' : '[ENEMY]', x + 5, y + 75);
+            this.ctx.font = font(10);
+            this.ctx.fillText(isOwnSector ? '[ALLIED]' : '[ENEMY]', x + 5 * k, y + 75 * k);
         }
     }
-    
+
     highlightSector(sector) {
-        const x = sector.position[0] * (this.sectorSize + this.padding) + this.padding;
-        const y = sector.position[1] * (this.sectorSize + this.padding) + this.padding;
-        
+        const { x, y } = this.sectorOrigin(sector);
+
         this.ctx.strokeStyle = this.colors.selectedBorder;
         this.ctx.lineWidth = 3;
         this.ctx.strokeRect(x - 2, y - 2, this.sectorSize + 4, this.sectorSize + 4);
     }
-    
+
     handleClick(mouseX, mouseY) {
         const sector = this.getSectorAtPosition(mouseX, mouseY);
-        
+
         if (sector) {
             this.selectedSector = sector.id;
             this.draw();
-            
+
             // Update sector info
             const infoDiv = document.getElementById('sector-info');
-            infoDiv.textContent = `Selected: ${sector.name} (${sector.id})`;
-            
+            const terrain = (sector.terrain || []).map(t => this.terrain[t]?.name || t).join(', ');
+            infoDiv.textContent = `Selected: ${sector.name} (${sector.id})` + (terrain ? ` - ${terrain}` : '');
+
             // Add sector ID to command input for convenience
             const commandInput = document.getElementById('command-input');
             if (commandInput.value.endsWith(' ')) {
@@ -158,17 +194,16 @@ class SectorMap {
             }
         }
     }
-    
+
     handleHover(mouseX, mouseY) {
         const sector = this.getSectorAtPosition(mouseX, mouseY);
         this.canvas.style.cursor = sector ? 'pointer' : 'crosshair';
     }
-    
+
     getSectorAtPosition(mouseX, mouseY) {
         for (const sector of this.sectors) {
-            const x = sector.position[0] * (this.sectorSize + this.padding) + this.padding;
-            const y = sector.position[1] * (this.sectorSize + this.padding) + this.padding;
-            
+            const { x, y } = this.sectorOrigin(sector);
+
             if (mouseX >= x && mouseX <= x + this.sectorSize &&
                 mouseY >= y && mouseY <= y + this.sectorSize) {
                 return sector;
@@ -182,7 +217,7 @@ class SectorMap {
 window.addEventListener('DOMContentLoaded', () => {
     const canvas = document.getElementById('sector-map');
     const map = new SectorMap(canvas);
-    
+
     // Attach to game client
     if (window.gameClient) {
         window.gameClient.map = map;
@@ -0,0 +1,21 @@
+// Service worker: shows game alerts pushed while the app is closed
+self.addEventListener('push', (event) => {
+    const data = event.data ? event.data.json() : {};
+    event.waitUntil(self.registration.showNotification(data.title || 'Interstellar Command', {
+        body: data.body || data.message || '',
+        tag: `${data.game_id}-${data.kind}`,
+        renotify: true,
+        icon: 'icon-192.png'
+    }));
+});
+
+// Tapping an alert opens the game, or focuses it if it's already open
+self.addEventListener('notificationclick', (event) => {
+    event.notification.close();
+    event.waitUntil(self.clients.matchAll({ type: 'window' }).then((windows) => {
+        if (windows.length > 0) {
+            return windows[0].focus();
+        }
+        return self.clients.openWindow('/');
+    }));
+});